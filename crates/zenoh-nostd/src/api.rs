@@ -1,9 +1,14 @@
 pub mod arg;
+pub mod events;
 pub mod query;
 pub mod response;
 pub mod sample;
 
 pub mod callbacks;
+pub mod consolidation;
+pub mod keyexprs;
+pub mod matching;
+pub mod pending;
 
 #[cfg(feature = "alloc")]
 pub mod broker;
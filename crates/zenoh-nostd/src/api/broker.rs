@@ -78,7 +78,7 @@ where
                 south
                     .driver
                     .tx()
-                    .await
+                    .await?
                     .send_optimized_ref(core::iter::once((msg.as_ref(), bytes)))
                     .await?;
             }
@@ -87,7 +87,7 @@ where
                 north
                     .driver
                     .tx()
-                    .await
+                    .await?
                     .send_optimized_ref(core::iter::once((msg.as_ref(), bytes)))
                     .await?;
             }
@@ -96,7 +96,7 @@ where
                 south
                     .driver
                     .tx()
-                    .await
+                    .await?
                     .send_optimized_ref(core::iter::once((msg.as_ref(), bytes)))
                     .await?;
             }
@@ -141,7 +141,7 @@ where
 
             if let Err(e) = driver
                 .driver
-                .run(&self.state, Self::update_north)
+                .run(&self.state, |_| {}, Self::update_north)
                 .await
                 .map_err(|e| e.flatten_map::<BrokerError>())
             {
@@ -173,7 +173,7 @@ where
 
             if let Err(e) = driver
                 .driver
-                .run(&self.state, Self::update_south)
+                .run(&self.state, |_| {}, Self::update_south)
                 .await
                 .map_err(|e| e.flatten_map::<BrokerError>())
             {
@@ -3,9 +3,9 @@ use core::marker::PhantomData;
 use dyn_utils::{DynObject, storage::Storage};
 use embassy_time::Instant;
 use heapless::FnvIndexMap;
-use zenoh_proto::keyexpr;
+use zenoh_proto::{KeTree, KeTreeEntry, keyexpr};
 
-use crate::api::arg::ZArg;
+use crate::api::{arg::ZArg, keyexprs::OwnedKeyExpr};
 
 #[dyn_utils::dyn_trait(trait = ZDynCallback)]
 #[dyn_trait(dyn_utils::dyn_object)]
@@ -28,10 +28,11 @@ where
 
     fn empty() -> Self;
 
+    /// Registers `callback` under `id`, keeping its own copy of `ke`.
     fn insert(
         &mut self,
         id: u32,
-        ke: &'static keyexpr,
+        ke: &keyexpr,
         timedout: Option<Instant>,
         callback: DynCallback<'a, Self::Callback, Self::Future, Arg>,
     ) -> core::result::Result<(), zenoh_proto::CollectionError>;
@@ -49,38 +50,99 @@ where
 
     fn decrease(&mut self, id: u32) -> bool;
 
+    /// The callbacks, with their id, whose key expression intersects `ke`. Only the branches
+    /// of the key expression tree `ke` may match are looked at.
     fn intersects<'r>(
         &'r mut self,
         ke: &keyexpr,
-    ) -> impl Iterator<Item = &'r mut DynCallback<'a, Self::Callback, Self::Future, Arg>>
+    ) -> impl Iterator<
+        Item = (
+            u32,
+            &'r mut DynCallback<'a, Self::Callback, Self::Future, Arg>,
+        ),
+    >
+    where
+        DynCallback<'a, Self::Callback, Self::Future, Arg>: 'r;
+
+    /// All the registered callbacks, with their id and key expression.
+    fn iter_mut<'r>(
+        &'r mut self,
+    ) -> impl Iterator<
+        Item = (
+            u32,
+            &'r keyexpr,
+            &'r mut DynCallback<'a, Self::Callback, Self::Future, Arg>,
+        ),
+    >
     where
         DynCallback<'a, Self::Callback, Self::Future, Arg>: 'r;
 }
 
+/// A registered callback. The entries are kept sorted as a [`KeTree`].
+struct Entry<Ke, Callback> {
+    id: u32,
+    ke: Ke,
+    callback: Callback,
+}
+
+impl<Ke: AsRef<keyexpr>, Callback> KeTreeEntry for Entry<Ke, Callback> {
+    fn keyexpr(&self) -> &keyexpr {
+        self.ke.as_ref()
+    }
+}
+
+fn find<Ke, Callback>(entries: &[Entry<Ke, Callback>], id: u32) -> Option<usize> {
+    entries.iter().position(|entry| entry.id == id)
+}
+
+/// The callbacks of the entries at `indices`, which must be increasing.
+fn pick<'r, Ke, Callback>(
+    entries: &'r mut [Entry<Ke, Callback>],
+    indices: impl IntoIterator<Item = usize>,
+) -> impl Iterator<Item = (u32, &'r mut Callback)> {
+    let mut entries = entries.iter_mut();
+    let mut next = 0;
+
+    indices.into_iter().filter_map(move |index| {
+        let entry = entries.nth(index - next)?;
+        next = index + 1;
+
+        Some((entry.id, &mut entry.callback))
+    })
+}
+
+/// Stores up to `CAPACITY` callbacks, each on a key expression of up to `MAX_KEYEXPR` bytes.
 pub struct FixedCapacityCallbacks<
     'a,
     Arg: ZArg,
     const CAPACITY: usize,
     Callback: Storage,
     Future: Storage,
+    const MAX_KEYEXPR: usize,
 > {
-    keyexprs: FnvIndexMap<u32, &'static keyexpr, CAPACITY>,
-    callbacks:
-        FnvIndexMap<(u32, &'static keyexpr), DynCallback<'a, Callback, Future, Arg>, CAPACITY>,
+    callbacks: heapless::Vec<
+        Entry<OwnedKeyExpr<MAX_KEYEXPR>, DynCallback<'a, Callback, Future, Arg>>,
+        CAPACITY,
+    >,
     timedouts: FnvIndexMap<u32, Instant, CAPACITY>,
     counters: FnvIndexMap<u32, usize, CAPACITY>,
 }
 
-impl<'a, Arg: ZArg + 'a, const CAPACITY: usize, Callback: Storage, Future: Storage>
-    ZCallbacks<'a, Arg> for FixedCapacityCallbacks<'a, Arg, CAPACITY, Callback, Future>
+impl<
+    'a,
+    Arg: ZArg + 'a,
+    const CAPACITY: usize,
+    Callback: Storage,
+    Future: Storage,
+    const MAX_KEYEXPR: usize,
+> ZCallbacks<'a, Arg> for FixedCapacityCallbacks<'a, Arg, CAPACITY, Callback, Future, MAX_KEYEXPR>
 {
     type Callback = Callback;
     type Future = Future;
 
     fn empty() -> Self {
         Self {
-            keyexprs: FnvIndexMap::new(),
-            callbacks: FnvIndexMap::new(),
+            callbacks: heapless::Vec::new(),
             timedouts: FnvIndexMap::new(),
             counters: FnvIndexMap::new(),
         }
@@ -89,15 +151,11 @@ impl<'a, Arg: ZArg + 'a, const CAPACITY: usize, Callback: Storage, Future: Stora
     fn insert(
         &mut self,
         id: u32,
-        ke: &'static keyexpr,
+        ke: &keyexpr,
         timedout: Option<Instant>,
         callback: DynCallback<'a, Callback, Future, Arg>,
     ) -> core::result::Result<(), zenoh_proto::CollectionError> {
-        if self.keyexprs.contains_key(&id) {
-            return Err(zenoh_proto::CollectionError::KeyAlreadyExists);
-        }
-
-        if self.callbacks.contains_key(&(id, ke)) {
+        if find(&self.callbacks, id).is_some() {
             return Err(zenoh_proto::CollectionError::KeyAlreadyExists);
         }
 
@@ -105,12 +163,12 @@ impl<'a, Arg: ZArg + 'a, const CAPACITY: usize, Callback: Storage, Future: Stora
             return Err(zenoh_proto::CollectionError::KeyAlreadyExists);
         }
 
-        self.keyexprs
-            .insert(id, ke)
-            .map_err(|_| zenoh_proto::CollectionError::CollectionIsFull)?;
+        let position = KeTree::new(&self.callbacks).position(ke);
+        let ke = OwnedKeyExpr::try_from(ke)
+            .map_err(|_| zenoh_proto::CollectionError::CollectionTooSmall)?;
 
         self.callbacks
-            .insert((id, ke), callback)
+            .insert(position, Entry { id, ke, callback })
             .map_err(|_| zenoh_proto::CollectionError::CollectionIsFull)?;
 
         if let Some(timedout) = timedout {
@@ -125,9 +183,7 @@ impl<'a, Arg: ZArg + 'a, const CAPACITY: usize, Callback: Storage, Future: Stora
     fn drop_timedout(&mut self) {
         self.timedouts.retain(|id, timedout| {
             if Instant::now() >= *timedout {
-                if let Some(ke) = self.keyexprs.remove(id) {
-                    self.callbacks.remove(&(*id, ke));
-                }
+                self.callbacks.retain(|entry| entry.id != *id);
                 self.counters.remove(id);
 
                 false
@@ -138,8 +194,8 @@ impl<'a, Arg: ZArg + 'a, const CAPACITY: usize, Callback: Storage, Future: Stora
     }
 
     fn remove(&mut self, id: u32) -> core::result::Result<(), zenoh_proto::CollectionError> {
-        if let Some(ke) = self.keyexprs.remove(&id) {
-            self.callbacks.remove(&(id, ke));
+        if let Some(index) = find(&self.callbacks, id) {
+            self.callbacks.remove(index);
         }
         self.timedouts.remove(&id);
         self.counters.remove(&id);
@@ -148,8 +204,7 @@ impl<'a, Arg: ZArg + 'a, const CAPACITY: usize, Callback: Storage, Future: Stora
     }
 
     fn get(&mut self, id: u32) -> Option<&mut DynCallback<'a, Callback, Future, Arg>> {
-        let ke = self.keyexprs.get(&id)?;
-        self.callbacks.get_mut(&(id, ke))
+        find(&self.callbacks, id).map(|index| &mut self.callbacks[index].callback)
     }
 
     fn set_counter(
@@ -178,28 +233,41 @@ impl<'a, Arg: ZArg + 'a, const CAPACITY: usize, Callback: Storage, Future: Stora
     fn intersects<'r>(
         &'r mut self,
         ke: &keyexpr,
-    ) -> impl Iterator<Item = &'r mut DynCallback<'a, Callback, Future, Arg>>
+    ) -> impl Iterator<Item = (u32, &'r mut DynCallback<'a, Callback, Future, Arg>)>
+    where
+        DynCallback<'a, Callback, Future, Arg>: 'r,
+    {
+        let mut matches = heapless::Vec::<usize, CAPACITY>::new();
+        KeTree::new(&self.callbacks).intersecting(ke, |index| {
+            // There are never more matches than entries.
+            let _ = matches.push(index);
+        });
+
+        pick(&mut self.callbacks, matches)
+    }
+
+    fn iter_mut<'r>(
+        &'r mut self,
+    ) -> impl Iterator<
+        Item = (
+            u32,
+            &'r keyexpr,
+            &'r mut DynCallback<'a, Callback, Future, Arg>,
+        ),
+    >
     where
         DynCallback<'a, Callback, Future, Arg>: 'r,
     {
         self.callbacks
             .iter_mut()
-            .filter_map(move |((_, registered_ke), callback)| {
-                if registered_ke.intersects(ke) {
-                    Some(callback)
-                } else {
-                    None
-                }
-            })
+            .map(|Entry { id, ke, callback }| (*id, ke.as_keyexpr(), callback))
     }
 }
 
 #[cfg(feature = "alloc")]
 pub struct AllocCallbacks<'a, Arg: ZArg, Callback: Storage, Future: Storage> {
-    keyexprs: alloc::collections::BTreeMap<u32, &'static keyexpr>,
-    callbacks: alloc::collections::BTreeMap<
-        (u32, &'static keyexpr),
-        DynCallback<'a, Callback, Future, Arg>,
+    callbacks: alloc::vec::Vec<
+        Entry<crate::api::keyexprs::AllocKeyExpr, DynCallback<'a, Callback, Future, Arg>>,
     >,
     timedouts: alloc::collections::BTreeMap<u32, Instant>,
     counters: alloc::collections::BTreeMap<u32, usize>,
@@ -214,8 +282,7 @@ impl<'a, Arg: ZArg + 'a, Callback: Storage, Future: Storage> ZCallbacks<'a, Arg>
 
     fn empty() -> Self {
         Self {
-            keyexprs: alloc::collections::BTreeMap::new(),
-            callbacks: alloc::collections::BTreeMap::new(),
+            callbacks: alloc::vec::Vec::new(),
             timedouts: alloc::collections::BTreeMap::new(),
             counters: alloc::collections::BTreeMap::new(),
         }
@@ -224,15 +291,11 @@ impl<'a, Arg: ZArg + 'a, Callback: Storage, Future: Storage> ZCallbacks<'a, Arg>
     fn insert(
         &mut self,
         id: u32,
-        ke: &'static keyexpr,
+        ke: &keyexpr,
         timedout: Option<Instant>,
         callback: DynCallback<'a, Callback, Future, Arg>,
     ) -> core::result::Result<(), zenoh_proto::CollectionError> {
-        if self.keyexprs.contains_key(&id) {
-            return Err(zenoh_proto::CollectionError::KeyAlreadyExists);
-        }
-
-        if self.callbacks.contains_key(&(id, ke)) {
+        if find(&self.callbacks, id).is_some() {
             return Err(zenoh_proto::CollectionError::KeyAlreadyExists);
         }
 
@@ -240,9 +303,15 @@ impl<'a, Arg: ZArg + 'a, Callback: Storage, Future: Storage> ZCallbacks<'a, Arg>
             return Err(zenoh_proto::CollectionError::KeyAlreadyExists);
         }
 
-        self.keyexprs.insert(id, ke);
-
-        self.callbacks.insert((id, ke), callback);
+        let position = KeTree::new(&self.callbacks).position(ke);
+        self.callbacks.insert(
+            position,
+            Entry {
+                id,
+                ke: ke.into(),
+                callback,
+            },
+        );
 
         if let Some(timedout) = timedout {
             self.timedouts.insert(id, timedout);
@@ -254,9 +323,7 @@ impl<'a, Arg: ZArg + 'a, Callback: Storage, Future: Storage> ZCallbacks<'a, Arg>
     fn drop_timedout(&mut self) {
         self.timedouts.retain(|id, timedout| {
             if Instant::now() >= *timedout {
-                if let Some(ke) = self.keyexprs.remove(id) {
-                    self.callbacks.remove(&(*id, ke));
-                }
+                self.callbacks.retain(|entry| entry.id != *id);
                 self.counters.remove(id);
 
                 false
@@ -267,8 +334,8 @@ impl<'a, Arg: ZArg + 'a, Callback: Storage, Future: Storage> ZCallbacks<'a, Arg>
     }
 
     fn remove(&mut self, id: u32) -> core::result::Result<(), zenoh_proto::CollectionError> {
-        if let Some(ke) = self.keyexprs.remove(&id) {
-            self.callbacks.remove(&(id, ke));
+        if let Some(index) = find(&self.callbacks, id) {
+            self.callbacks.remove(index);
         }
         self.timedouts.remove(&id);
         self.counters.remove(&id);
@@ -277,8 +344,7 @@ impl<'a, Arg: ZArg + 'a, Callback: Storage, Future: Storage> ZCallbacks<'a, Arg>
     }
 
     fn get(&mut self, id: u32) -> Option<&mut DynCallback<'a, Callback, Future, Arg>> {
-        let ke = self.keyexprs.get(&id)?;
-        self.callbacks.get_mut(&(id, ke))
+        find(&self.callbacks, id).map(|index| &mut self.callbacks[index].callback)
     }
 
     fn set_counter(
@@ -306,19 +372,31 @@ impl<'a, Arg: ZArg + 'a, Callback: Storage, Future: Storage> ZCallbacks<'a, Arg>
     fn intersects<'r>(
         &'r mut self,
         ke: &keyexpr,
-    ) -> impl Iterator<Item = &'r mut DynCallback<'a, Callback, Future, Arg>>
+    ) -> impl Iterator<Item = (u32, &'r mut DynCallback<'a, Callback, Future, Arg>)>
+    where
+        DynCallback<'a, Callback, Future, Arg>: 'r,
+    {
+        let mut matches = alloc::vec::Vec::new();
+        KeTree::new(&self.callbacks).intersecting(ke, |index| matches.push(index));
+
+        pick(&mut self.callbacks, matches)
+    }
+
+    fn iter_mut<'r>(
+        &'r mut self,
+    ) -> impl Iterator<
+        Item = (
+            u32,
+            &'r keyexpr,
+            &'r mut DynCallback<'a, Callback, Future, Arg>,
+        ),
+    >
     where
         DynCallback<'a, Callback, Future, Arg>: 'r,
     {
         self.callbacks
             .iter_mut()
-            .filter_map(move |((_, registered_ke), callback)| {
-                if registered_ke.intersects(ke) {
-                    Some(callback)
-                } else {
-                    None
-                }
-            })
+            .map(|Entry { id, ke, callback }| (*id, ke.as_keyexpr(), callback))
     }
 }
 
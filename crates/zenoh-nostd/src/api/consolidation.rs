@@ -0,0 +1,267 @@
+use heapless::FnvIndexMap;
+use zenoh_proto::{CollectionError, fields::ConsolidationMode, keyexpr};
+
+use crate::api::sample::{FixedCapacitySample, Sample};
+
+/// The mode this session consolidates the replies to a query with: `Auto` stands for `Latest`,
+/// and `None` when the replies are handed over as they come.
+pub(crate) fn resolve(mode: ConsolidationMode) -> Option<ConsolidationMode> {
+    match mode {
+        ConsolidationMode::None => None,
+        ConsolidationMode::Auto | ConsolidationMode::Latest => Some(ConsolidationMode::Latest),
+        ConsolidationMode::Monotonic => Some(ConsolidationMode::Monotonic),
+    }
+}
+
+/// Whether a reply to `rid` goes to the application right away. `Monotonic` drops the replies
+/// that are not newer than the last one on the same key expression, `Latest` keeps the newest
+/// one until the `ResponseFinal`. Replies are let through when there is no room to track them.
+pub(crate) fn consolidate(
+    consolidations: &mut impl ZConsolidations,
+    rid: u32,
+    sample: &Sample<'_>,
+) -> bool {
+    let Some(mode) = consolidations.mode(rid) else {
+        return true;
+    };
+
+    if let Some(kept) = consolidations.get(rid, sample.keyexpr())
+        && kept.timestamp() >= sample.timestamp()
+    {
+        return false;
+    }
+
+    let kept = match mode {
+        ConsolidationMode::Monotonic => consolidations.keep(
+            rid,
+            &Sample::new(sample.keyexpr(), &[]).with_timestamp(sample.timestamp()),
+        ),
+        _ => consolidations.keep(rid, sample),
+    };
+
+    if kept.is_err() {
+        zenoh_proto::warn!(
+            "{}: No room to consolidate a reply, handing it over",
+            zenoh_proto::zctx!()
+        );
+
+        return true;
+    }
+
+    mode == ConsolidationMode::Monotonic
+}
+
+/// Replies kept to consolidate the queries of this session, by request id.
+pub trait ZConsolidations {
+    fn empty() -> Self;
+
+    fn insert(
+        &mut self,
+        rid: u32,
+        mode: ConsolidationMode,
+    ) -> core::result::Result<(), CollectionError>;
+
+    fn remove(&mut self, rid: u32) -> core::result::Result<(), CollectionError>;
+
+    fn retain(&mut self, f: impl FnMut(u32) -> bool);
+
+    fn mode(&self, rid: u32) -> Option<ConsolidationMode>;
+
+    fn get(&self, rid: u32, ke: &keyexpr) -> Option<Sample<'_>>;
+
+    /// Replaces the reply kept for the key expression of `sample`.
+    fn keep(&mut self, rid: u32, sample: &Sample<'_>) -> core::result::Result<(), CollectionError>;
+
+    fn replies(&self, rid: u32) -> impl Iterator<Item = Sample<'_>>;
+}
+
+/// Consolidates up to `CAPACITY` queries at once, keeping up to `REPLIES` replies among them.
+/// `MAX_PAYLOAD`, `MAX_ATTACHMENT` and `MAX_SCHEMA` only matter to `Latest`, see
+/// [`FixedCapacitySample`]. A get asking for `Latest` fails with `CollectionTooSmall` when
+/// `REPLIES` or `MAX_PAYLOAD` is 0, instead of handing over all the replies.
+pub struct FixedCapacityConsolidations<
+    const CAPACITY: usize,
+    const REPLIES: usize,
+    const MAX_KEYEXPR: usize,
+    const MAX_PAYLOAD: usize,
+    const MAX_ATTACHMENT: usize = 0,
+    const MAX_SCHEMA: usize = 0,
+> {
+    modes: FnvIndexMap<u32, ConsolidationMode, CAPACITY>,
+    replies: heapless::Vec<
+        (
+            u32,
+            FixedCapacitySample<MAX_KEYEXPR, MAX_PAYLOAD, MAX_ATTACHMENT, MAX_SCHEMA>,
+        ),
+        REPLIES,
+    >,
+}
+
+impl<
+    const CAPACITY: usize,
+    const REPLIES: usize,
+    const MAX_KEYEXPR: usize,
+    const MAX_PAYLOAD: usize,
+    const MAX_ATTACHMENT: usize,
+    const MAX_SCHEMA: usize,
+> ZConsolidations
+    for FixedCapacityConsolidations<
+        CAPACITY,
+        REPLIES,
+        MAX_KEYEXPR,
+        MAX_PAYLOAD,
+        MAX_ATTACHMENT,
+        MAX_SCHEMA,
+    >
+{
+    fn empty() -> Self {
+        Self {
+            modes: FnvIndexMap::new(),
+            replies: heapless::Vec::new(),
+        }
+    }
+
+    fn insert(
+        &mut self,
+        rid: u32,
+        mode: ConsolidationMode,
+    ) -> core::result::Result<(), CollectionError> {
+        if self.modes.contains_key(&rid) {
+            return Err(CollectionError::KeyAlreadyExists);
+        }
+
+        if mode == ConsolidationMode::Latest && (REPLIES == 0 || MAX_PAYLOAD == 0) {
+            return Err(CollectionError::CollectionTooSmall);
+        }
+
+        self.modes
+            .insert(rid, mode)
+            .map(|_| ())
+            .map_err(|_| CollectionError::CollectionIsFull)
+    }
+
+    fn remove(&mut self, rid: u32) -> core::result::Result<(), CollectionError> {
+        self.replies.retain(|(kept, _)| *kept != rid);
+        self.modes
+            .remove(&rid)
+            .map(|_| ())
+            .ok_or(CollectionError::KeyNotFound)
+    }
+
+    fn retain(&mut self, mut f: impl FnMut(u32) -> bool) {
+        self.modes.retain(|rid, _| f(*rid));
+        self.replies.retain(|(rid, _)| self.modes.contains_key(rid));
+    }
+
+    fn mode(&self, rid: u32) -> Option<ConsolidationMode> {
+        self.modes.get(&rid).copied()
+    }
+
+    fn get(&self, rid: u32, ke: &keyexpr) -> Option<Sample<'_>> {
+        self.replies
+            .iter()
+            .find(|(kept, sample)| *kept == rid && sample.keyexpr() == ke)
+            .map(|(_, sample)| sample.as_ref())
+    }
+
+    fn keep(&mut self, rid: u32, sample: &Sample<'_>) -> core::result::Result<(), CollectionError> {
+        let owned = FixedCapacitySample::try_from(sample)?;
+
+        match self
+            .replies
+            .iter_mut()
+            .find(|(kept, kept_sample)| *kept == rid && kept_sample.keyexpr() == sample.keyexpr())
+        {
+            Some((_, kept)) => *kept = owned,
+            None => self
+                .replies
+                .push((rid, owned))
+                .map_err(|_| CollectionError::CollectionIsFull)?,
+        }
+
+        Ok(())
+    }
+
+    fn replies(&self, rid: u32) -> impl Iterator<Item = Sample<'_>> {
+        self.replies
+            .iter()
+            .filter(move |(kept, _)| *kept == rid)
+            .map(|(_, sample)| sample.as_ref())
+    }
+}
+
+#[cfg(feature = "alloc")]
+pub struct AllocConsolidations {
+    modes: alloc::collections::BTreeMap<u32, ConsolidationMode>,
+    replies: alloc::vec::Vec<(u32, crate::api::sample::AllocSample)>,
+}
+
+#[cfg(feature = "alloc")]
+impl ZConsolidations for AllocConsolidations {
+    fn empty() -> Self {
+        Self {
+            modes: alloc::collections::BTreeMap::new(),
+            replies: alloc::vec::Vec::new(),
+        }
+    }
+
+    fn insert(
+        &mut self,
+        rid: u32,
+        mode: ConsolidationMode,
+    ) -> core::result::Result<(), CollectionError> {
+        if self.modes.contains_key(&rid) {
+            return Err(CollectionError::KeyAlreadyExists);
+        }
+
+        self.modes.insert(rid, mode);
+
+        Ok(())
+    }
+
+    fn remove(&mut self, rid: u32) -> core::result::Result<(), CollectionError> {
+        self.replies.retain(|(kept, _)| *kept != rid);
+        self.modes
+            .remove(&rid)
+            .map(|_| ())
+            .ok_or(CollectionError::KeyNotFound)
+    }
+
+    fn retain(&mut self, mut f: impl FnMut(u32) -> bool) {
+        self.modes.retain(|rid, _| f(*rid));
+        self.replies.retain(|(rid, _)| self.modes.contains_key(rid));
+    }
+
+    fn mode(&self, rid: u32) -> Option<ConsolidationMode> {
+        self.modes.get(&rid).copied()
+    }
+
+    fn get(&self, rid: u32, ke: &keyexpr) -> Option<Sample<'_>> {
+        self.replies
+            .iter()
+            .find(|(kept, sample)| *kept == rid && sample.keyexpr() == ke)
+            .map(|(_, sample)| sample.as_ref())
+    }
+
+    fn keep(&mut self, rid: u32, sample: &Sample<'_>) -> core::result::Result<(), CollectionError> {
+        let owned = crate::api::sample::AllocSample::try_from(sample)?;
+
+        match self
+            .replies
+            .iter_mut()
+            .find(|(kept, kept_sample)| *kept == rid && kept_sample.keyexpr() == sample.keyexpr())
+        {
+            Some((_, kept)) => *kept = owned,
+            None => self.replies.push((rid, owned)),
+        }
+
+        Ok(())
+    }
+
+    fn replies(&self, rid: u32) -> impl Iterator<Item = Sample<'_>> {
+        self.replies
+            .iter()
+            .filter(move |(kept, _)| *kept == rid)
+            .map(|(_, sample)| sample.as_ref())
+    }
+}
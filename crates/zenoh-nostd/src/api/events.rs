@@ -0,0 +1,19 @@
+use zenoh_proto::{fields::ZenohIdProto, msgs::CloseBehaviour};
+
+/// What happened to the transport of a session, see `Session::with_events`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TransportEvent {
+    /// A transport has been opened with the peer `zid`.
+    Opened { zid: ZenohIdProto },
+    /// The peer closed the transport with a `Close` message, e.g. because it is shutting down.
+    ClosedByPeer {
+        reason: u8,
+        behaviour: CloseBehaviour,
+    },
+    /// Nothing has been received from the peer, or sent to it, within the lease.
+    LeaseExpired,
+    /// The link failed while reading or writing.
+    LinkLost,
+    /// Messages from the peer were lost, according to the sequence numbers.
+    SnGap { missed: u32 },
+}
@@ -0,0 +1,536 @@
+use heapless::FnvIndexMap;
+use zenoh_proto::{
+    CollectionError, KeyexprError,
+    fields::{Mapping, WireExpr},
+    keyexpr,
+};
+
+/// Key expressions declared by this session, mapped to the id they are sent with.
+pub trait ZKeyExprs<'res> {
+    fn empty() -> Self;
+
+    /// Reserves a new id. Only ids declared with a key expression are used by `wire_expr`, the
+    /// others belong to an entity (e.g. a `Publisher`) that keeps track of its own id.
+    fn declare(&mut self, ke: Option<&'res keyexpr>) -> core::result::Result<u16, CollectionError>;
+
+    fn undeclare(&mut self, id: u16) -> core::result::Result<(), CollectionError>;
+
+    fn iter(&self) -> impl Iterator<Item = (u16, &'res keyexpr)>;
+
+    fn get(&self, id: u16) -> Option<&'res keyexpr> {
+        self.iter()
+            .find_map(|(declared, ke)| (declared == id).then_some(ke))
+    }
+
+    fn id(&self, ke: &keyexpr) -> Option<u16> {
+        self.iter()
+            .find_map(|(id, declared)| (declared == ke).then_some(id))
+    }
+
+    /// Shortens `ke` using the longest declared key expression it starts with.
+    fn wire_expr<'a>(&self, ke: &'a keyexpr) -> WireExpr<'a> {
+        self.iter()
+            .filter_map(|(id, declared)| {
+                let suffix = ke.as_str().strip_prefix(declared.as_str())?;
+                (suffix.is_empty() || suffix.starts_with('/')).then_some((id, suffix))
+            })
+            .min_by_key(|(_, suffix)| suffix.len())
+            .map(|(scope, suffix)| WireExpr {
+                scope,
+                mapping: Mapping::Sender,
+                suffix,
+            })
+            .unwrap_or_else(|| WireExpr::from(ke))
+    }
+}
+
+pub struct FixedCapacityKeyExprs<'res, const CAPACITY: usize> {
+    keyexprs: FnvIndexMap<u16, Option<&'res keyexpr>, CAPACITY>,
+}
+
+impl<'res, const CAPACITY: usize> ZKeyExprs<'res> for FixedCapacityKeyExprs<'res, CAPACITY> {
+    fn empty() -> Self {
+        Self {
+            keyexprs: FnvIndexMap::new(),
+        }
+    }
+
+    fn declare(&mut self, ke: Option<&'res keyexpr>) -> core::result::Result<u16, CollectionError> {
+        if self.keyexprs.len() == self.keyexprs.capacity() {
+            return Err(CollectionError::CollectionIsFull);
+        }
+
+        // Scope `0` means that the key expression is not scoped at all.
+        let id = (1..=u16::MAX)
+            .find(|id| !self.keyexprs.contains_key(id))
+            .ok_or(CollectionError::CollectionIsFull)?;
+
+        self.keyexprs
+            .insert(id, ke)
+            .map_err(|_| CollectionError::CollectionIsFull)?;
+
+        Ok(id)
+    }
+
+    fn undeclare(&mut self, id: u16) -> core::result::Result<(), CollectionError> {
+        self.keyexprs
+            .remove(&id)
+            .map(|_| ())
+            .ok_or(CollectionError::KeyNotFound)
+    }
+
+    fn iter(&self) -> impl Iterator<Item = (u16, &'res keyexpr)> {
+        self.keyexprs
+            .iter()
+            .filter_map(|(id, ke)| ke.map(|ke| (*id, ke)))
+    }
+}
+
+#[cfg(feature = "alloc")]
+pub struct AllocKeyExprs<'res> {
+    keyexprs: alloc::collections::BTreeMap<u16, Option<&'res keyexpr>>,
+}
+
+#[cfg(feature = "alloc")]
+impl<'res> ZKeyExprs<'res> for AllocKeyExprs<'res> {
+    fn empty() -> Self {
+        Self {
+            keyexprs: alloc::collections::BTreeMap::new(),
+        }
+    }
+
+    fn declare(&mut self, ke: Option<&'res keyexpr>) -> core::result::Result<u16, CollectionError> {
+        let id = (1..=u16::MAX)
+            .find(|id| !self.keyexprs.contains_key(id))
+            .ok_or(CollectionError::CollectionIsFull)?;
+
+        self.keyexprs.insert(id, ke);
+
+        Ok(id)
+    }
+
+    fn undeclare(&mut self, id: u16) -> core::result::Result<(), CollectionError> {
+        self.keyexprs
+            .remove(&id)
+            .map(|_| ())
+            .ok_or(CollectionError::KeyNotFound)
+    }
+
+    fn iter(&self) -> impl Iterator<Item = (u16, &'res keyexpr)> {
+        self.keyexprs
+            .iter()
+            .filter_map(|(id, ke)| ke.map(|ke| (*id, ke)))
+    }
+}
+
+/// Key expressions declared by the peer, used to rebuild the scoped `WireExpr`s it sends.
+pub trait ZRemoteKeyExprs {
+    fn empty() -> Self;
+
+    /// Declares `id` as the key expression of `wire_expr`. `local` is the key expression
+    /// `wire_expr.scope` refers to when it is one of this session's declarations.
+    fn declare(
+        &mut self,
+        id: u16,
+        wire_expr: &WireExpr<'_>,
+        local: Option<&str>,
+    ) -> core::result::Result<(), CollectionError>;
+
+    fn undeclare(&mut self, id: u16) -> core::result::Result<(), CollectionError>;
+
+    /// Rebuilds the full key expression of `wire_expr`, in a scratch buffer if it is scoped.
+    fn resolve<'a>(
+        &'a mut self,
+        wire_expr: &WireExpr<'a>,
+        local: Option<&str>,
+    ) -> core::result::Result<&'a str, CollectionError>;
+}
+
+fn prefix<'a>(
+    wire_expr: &WireExpr<'_>,
+    local: Option<&'a str>,
+    remote: impl FnOnce(u16) -> Option<&'a str>,
+) -> core::result::Result<&'a str, CollectionError> {
+    if wire_expr.scope == 0 {
+        return Ok("");
+    }
+
+    match wire_expr.mapping {
+        Mapping::Receiver => local,
+        Mapping::Sender => remote(wire_expr.scope),
+    }
+    .ok_or(CollectionError::KeyNotFound)
+}
+
+fn concat<const N: usize>(
+    dst: &mut heapless::String<N>,
+    prefix: &str,
+    suffix: &str,
+) -> core::result::Result<(), CollectionError> {
+    dst.clear();
+    dst.push_str(prefix)
+        .and_then(|_| dst.push_str(suffix))
+        .map_err(|_| CollectionError::CollectionTooSmall)
+}
+
+pub struct FixedCapacityRemoteKeyExprs<const CAPACITY: usize, const MAX_KEYEXPR: usize> {
+    keyexprs: FnvIndexMap<u16, heapless::String<MAX_KEYEXPR>, CAPACITY>,
+    scratch: heapless::String<MAX_KEYEXPR>,
+}
+
+impl<const CAPACITY: usize, const MAX_KEYEXPR: usize> ZRemoteKeyExprs
+    for FixedCapacityRemoteKeyExprs<CAPACITY, MAX_KEYEXPR>
+{
+    fn empty() -> Self {
+        Self {
+            keyexprs: FnvIndexMap::new(),
+            scratch: heapless::String::new(),
+        }
+    }
+
+    fn declare(
+        &mut self,
+        id: u16,
+        wire_expr: &WireExpr<'_>,
+        local: Option<&str>,
+    ) -> core::result::Result<(), CollectionError> {
+        let mut ke = heapless::String::new();
+        let prefix = prefix(wire_expr, local, |id| {
+            self.keyexprs.get(&id).map(|ke| ke.as_str())
+        })?;
+        concat(&mut ke, prefix, wire_expr.suffix)?;
+
+        self.keyexprs
+            .insert(id, ke)
+            .map(|_| ())
+            .map_err(|_| CollectionError::CollectionIsFull)
+    }
+
+    fn undeclare(&mut self, id: u16) -> core::result::Result<(), CollectionError> {
+        self.keyexprs
+            .remove(&id)
+            .map(|_| ())
+            .ok_or(CollectionError::KeyNotFound)
+    }
+
+    fn resolve<'a>(
+        &'a mut self,
+        wire_expr: &WireExpr<'a>,
+        local: Option<&str>,
+    ) -> core::result::Result<&'a str, CollectionError> {
+        if wire_expr.scope == 0 {
+            return Ok(wire_expr.suffix);
+        }
+
+        let prefix = prefix(wire_expr, local, |id| {
+            self.keyexprs.get(&id).map(|ke| ke.as_str())
+        })?;
+        concat(&mut self.scratch, prefix, wire_expr.suffix)?;
+
+        Ok(self.scratch.as_str())
+    }
+}
+
+#[cfg(feature = "alloc")]
+pub struct AllocRemoteKeyExprs {
+    keyexprs: alloc::collections::BTreeMap<u16, alloc::string::String>,
+    scratch: alloc::string::String,
+}
+
+#[cfg(feature = "alloc")]
+impl ZRemoteKeyExprs for AllocRemoteKeyExprs {
+    fn empty() -> Self {
+        Self {
+            keyexprs: alloc::collections::BTreeMap::new(),
+            scratch: alloc::string::String::new(),
+        }
+    }
+
+    fn declare(
+        &mut self,
+        id: u16,
+        wire_expr: &WireExpr<'_>,
+        local: Option<&str>,
+    ) -> core::result::Result<(), CollectionError> {
+        let prefix = prefix(wire_expr, local, |id| {
+            self.keyexprs.get(&id).map(|ke| ke.as_str())
+        })?;
+
+        let ke = alloc::format!("{prefix}{}", wire_expr.suffix);
+        self.keyexprs.insert(id, ke);
+
+        Ok(())
+    }
+
+    fn undeclare(&mut self, id: u16) -> core::result::Result<(), CollectionError> {
+        self.keyexprs
+            .remove(&id)
+            .map(|_| ())
+            .ok_or(CollectionError::KeyNotFound)
+    }
+
+    fn resolve<'a>(
+        &'a mut self,
+        wire_expr: &WireExpr<'a>,
+        local: Option<&str>,
+    ) -> core::result::Result<&'a str, CollectionError> {
+        if wire_expr.scope == 0 {
+            return Ok(wire_expr.suffix);
+        }
+
+        let prefix = prefix(wire_expr, local, |id| {
+            self.keyexprs.get(&id).map(|ke| ke.as_str())
+        })?;
+
+        self.scratch.clear();
+        self.scratch.push_str(prefix);
+        self.scratch.push_str(wire_expr.suffix);
+
+        Ok(self.scratch.as_str())
+    }
+}
+
+/// Entities (e.g. subscribers) declared by the peer, mapped to their key expression so that
+/// their undeclarations, which only carry their id, can be resolved.
+pub trait ZRemoteDeclarations {
+    fn empty() -> Self;
+
+    fn declare(&mut self, id: u32, ke: &keyexpr) -> core::result::Result<(), CollectionError>;
+
+    /// Forgets `id` and returns the key expression it was declared on.
+    fn undeclare(&mut self, id: u32) -> Option<&keyexpr>;
+}
+
+pub struct FixedCapacityRemoteDeclarations<const CAPACITY: usize, const MAX_KEYEXPR: usize> {
+    declarations: FnvIndexMap<u32, heapless::String<MAX_KEYEXPR>, CAPACITY>,
+    /// The key expression of the last undeclared entity
+    scratch: heapless::String<MAX_KEYEXPR>,
+}
+
+impl<const CAPACITY: usize, const MAX_KEYEXPR: usize> ZRemoteDeclarations
+    for FixedCapacityRemoteDeclarations<CAPACITY, MAX_KEYEXPR>
+{
+    fn empty() -> Self {
+        Self {
+            declarations: FnvIndexMap::new(),
+            scratch: heapless::String::new(),
+        }
+    }
+
+    fn declare(&mut self, id: u32, ke: &keyexpr) -> core::result::Result<(), CollectionError> {
+        let ke = heapless::String::try_from(ke.as_str())
+            .map_err(|_| CollectionError::CollectionTooSmall)?;
+
+        self.declarations
+            .insert(id, ke)
+            .map(|_| ())
+            .map_err(|_| CollectionError::CollectionIsFull)
+    }
+
+    fn undeclare(&mut self, id: u32) -> Option<&keyexpr> {
+        self.scratch = self.declarations.remove(&id)?;
+
+        Some(keyexpr::from_str_unchecked(self.scratch.as_str()))
+    }
+}
+
+#[cfg(feature = "alloc")]
+pub struct AllocRemoteDeclarations {
+    declarations: alloc::collections::BTreeMap<u32, alloc::string::String>,
+    /// The key expression of the last undeclared entity
+    scratch: alloc::string::String,
+}
+
+#[cfg(feature = "alloc")]
+impl ZRemoteDeclarations for AllocRemoteDeclarations {
+    fn empty() -> Self {
+        Self {
+            declarations: alloc::collections::BTreeMap::new(),
+            scratch: alloc::string::String::new(),
+        }
+    }
+
+    fn declare(&mut self, id: u32, ke: &keyexpr) -> core::result::Result<(), CollectionError> {
+        self.declarations.insert(id, ke.as_str().into());
+
+        Ok(())
+    }
+
+    fn undeclare(&mut self, id: u32) -> Option<&keyexpr> {
+        self.scratch = self.declarations.remove(&id)?;
+
+        Some(keyexpr::from_str_unchecked(self.scratch.as_str()))
+    }
+}
+
+/// A key expression of up to `N` bytes, for the ones only known at runtime (e.g. built from a
+/// device serial number) that cannot be borrowed for `'static`.
+#[derive(Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct OwnedKeyExpr<const N: usize> {
+    ke: heapless::String<N>,
+}
+
+impl<const N: usize> OwnedKeyExpr<N> {
+    pub fn new(ke: &str) -> core::result::Result<Self, KeyexprError> {
+        Self::try_from(keyexpr::new(ke)?)
+    }
+
+    /// Canonizes `ke` before validating it, see [`keyexpr::autocanonize`].
+    pub fn autocanonize(ke: &str) -> core::result::Result<Self, KeyexprError> {
+        let mut owned = heapless::String::<N>::try_from(ke).map_err(|_| too_long(ke))?;
+        let len = keyexpr::autocanonize(owned.as_mut_str())?.len();
+        owned.truncate(len);
+
+        Ok(Self { ke: owned })
+    }
+
+    /// Formats the key expression in place, e.g. `format_args!("fleet/{mac}/status")`.
+    pub fn format(args: core::fmt::Arguments<'_>) -> core::result::Result<Self, KeyexprError> {
+        let mut owned = heapless::String::<N>::new();
+        if core::fmt::Write::write_fmt(&mut owned, args).is_err() {
+            zenoh_proto::zbail!(KeyexprError::KeyexprTooLong);
+        }
+
+        Self::new(owned.as_str())
+    }
+
+    /// Appends `/other` and canonizes the result.
+    pub fn join(&self, other: &str) -> core::result::Result<Self, KeyexprError> {
+        let mut buf = [0u8; N];
+        Self::try_from(self.as_keyexpr().join(other, &mut buf)?)
+    }
+
+    pub fn as_keyexpr(&self) -> &keyexpr {
+        keyexpr::from_str_unchecked(self.ke.as_str())
+    }
+}
+
+#[cold]
+fn too_long(ke: &str) -> KeyexprError {
+    zenoh_proto::error!("keyexpr '{}' does not fit", ke);
+    KeyexprError::KeyexprTooLong
+}
+
+impl<const N: usize> TryFrom<&keyexpr> for OwnedKeyExpr<N> {
+    type Error = KeyexprError;
+
+    fn try_from(ke: &keyexpr) -> core::result::Result<Self, Self::Error> {
+        heapless::String::try_from(ke.as_str())
+            .map(|ke| Self { ke })
+            .map_err(|_| too_long(ke))
+    }
+}
+
+impl<const N: usize> core::ops::Deref for OwnedKeyExpr<N> {
+    type Target = keyexpr;
+
+    fn deref(&self) -> &Self::Target {
+        self.as_keyexpr()
+    }
+}
+
+impl<const N: usize> AsRef<keyexpr> for OwnedKeyExpr<N> {
+    fn as_ref(&self) -> &keyexpr {
+        self.as_keyexpr()
+    }
+}
+
+impl<const N: usize> core::fmt::Debug for OwnedKeyExpr<N> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        core::fmt::Debug::fmt(self.as_keyexpr(), f)
+    }
+}
+
+impl<const N: usize> core::fmt::Display for OwnedKeyExpr<N> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(self.ke.as_str())
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl<const N: usize> defmt::Format for OwnedKeyExpr<N> {
+    fn format(&self, fmt: defmt::Formatter) {
+        defmt::Format::format(self.as_keyexpr(), fmt)
+    }
+}
+
+#[cfg(feature = "alloc")]
+#[derive(Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct AllocKeyExpr {
+    ke: alloc::string::String,
+}
+
+#[cfg(feature = "alloc")]
+impl AllocKeyExpr {
+    pub fn new(ke: &str) -> core::result::Result<Self, KeyexprError> {
+        Ok(Self::from(keyexpr::new(ke)?))
+    }
+
+    /// Canonizes `ke` before validating it, see [`keyexpr::autocanonize`].
+    pub fn autocanonize(ke: &str) -> core::result::Result<Self, KeyexprError> {
+        let mut owned = alloc::string::String::from(ke);
+        let len = keyexpr::autocanonize(owned.as_mut_str())?.len();
+        owned.truncate(len);
+
+        Ok(Self { ke: owned })
+    }
+
+    pub fn format(args: core::fmt::Arguments<'_>) -> core::result::Result<Self, KeyexprError> {
+        Self::new(alloc::fmt::format(args).as_str())
+    }
+
+    /// Appends `/other` and canonizes the result.
+    pub fn join(&self, other: &str) -> core::result::Result<Self, KeyexprError> {
+        Self::autocanonize(alloc::format!("{}/{other}", self.ke).as_str())
+    }
+
+    pub fn as_keyexpr(&self) -> &keyexpr {
+        keyexpr::from_str_unchecked(self.ke.as_str())
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl From<&keyexpr> for AllocKeyExpr {
+    fn from(ke: &keyexpr) -> Self {
+        Self {
+            ke: alloc::string::String::from(ke.as_str()),
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl core::ops::Deref for AllocKeyExpr {
+    type Target = keyexpr;
+
+    fn deref(&self) -> &Self::Target {
+        self.as_keyexpr()
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl AsRef<keyexpr> for AllocKeyExpr {
+    fn as_ref(&self) -> &keyexpr {
+        self.as_keyexpr()
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl core::fmt::Debug for AllocKeyExpr {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        core::fmt::Debug::fmt(self.as_keyexpr(), f)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl core::fmt::Display for AllocKeyExpr {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(self.ke.as_str())
+    }
+}
+
+#[cfg(all(feature = "alloc", feature = "defmt"))]
+impl defmt::Format for AllocKeyExpr {
+    fn format(&self, fmt: defmt::Formatter) {
+        defmt::Format::format(self.as_keyexpr(), fmt)
+    }
+}
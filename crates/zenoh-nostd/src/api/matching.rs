@@ -0,0 +1,228 @@
+use embassy_sync::channel::DynamicSender;
+use heapless::FnvIndexMap;
+use zenoh_proto::{CollectionError, keyexpr};
+
+/// Whether a publisher has subscribers to talk to, or a querier has queryables.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct MatchingStatus {
+    matching: bool,
+}
+
+impl MatchingStatus {
+    pub fn matching(&self) -> bool {
+        self.matching
+    }
+}
+
+/// The remote entities matching a publisher or a querier, as declared by the peer in reply to
+/// its interest.
+pub struct Matching<'res> {
+    querier: bool,
+    // The key expression id of a publisher, declared again to each new peer.
+    scope: Option<u16>,
+    count: usize,
+    listener: Option<DynamicSender<'res, MatchingStatus>>,
+}
+
+impl<'res> Matching<'res> {
+    pub(crate) fn new(querier: bool) -> Self {
+        Self {
+            querier,
+            scope: None,
+            count: 0,
+            listener: None,
+        }
+    }
+
+    pub(crate) fn querier(&self) -> bool {
+        self.querier
+    }
+
+    pub(crate) fn scope(&self) -> Option<u16> {
+        self.scope
+    }
+
+    pub(crate) fn set_scope(&mut self, scope: Option<u16>) {
+        self.scope = scope;
+    }
+
+    pub(crate) fn status(&self) -> MatchingStatus {
+        MatchingStatus {
+            matching: self.count != 0,
+        }
+    }
+
+    pub(crate) fn listen(&mut self, listener: DynamicSender<'res, MatchingStatus>) {
+        self.listener = Some(listener);
+    }
+
+    /// Counts an entity declared, or undeclared, by the peer.
+    pub(crate) fn update(&mut self, declared: bool) {
+        let before = self.status();
+        self.count = match declared {
+            true => self.count.saturating_add(1),
+            false => self.count.saturating_sub(1),
+        };
+
+        self.notify(before);
+    }
+
+    /// Forgets the entities declared by a previous peer.
+    pub(crate) fn reset(&mut self) {
+        let before = self.status();
+        self.count = 0;
+
+        self.notify(before);
+    }
+
+    fn notify(&self, before: MatchingStatus) {
+        let status = self.status();
+        if status == before {
+            return;
+        }
+
+        if let Some(listener) = &self.listener
+            && listener.try_send(status).is_err()
+        {
+            zenoh_proto::debug!(
+                "{}: Matching listener is full, dropping a status",
+                zenoh_proto::zctx!()
+            );
+        }
+    }
+}
+
+/// Updates the publishers, or the queriers, concerned by an entity on `ke` that the peer
+/// declared or undeclared. Declarations replying to an interest only concern its sender.
+pub(crate) fn update<'res>(
+    matchings: &mut impl ZMatchings<'res>,
+    interest: Option<u32>,
+    querier: bool,
+    ke: &keyexpr,
+    declared: bool,
+) {
+    for (id, matched, matching) in matchings.iter_mut() {
+        let concerned = match interest {
+            Some(interest) => interest == id,
+            None => matched.intersects(ke),
+        };
+
+        if concerned && matching.querier() == querier {
+            matching.update(declared);
+        }
+    }
+}
+
+/// Publishers and queriers of this session, by the id of their interest.
+pub trait ZMatchings<'res> {
+    fn empty() -> Self;
+
+    fn insert(
+        &mut self,
+        id: u32,
+        ke: &keyexpr,
+        matching: Matching<'res>,
+    ) -> core::result::Result<(), CollectionError>;
+
+    fn remove(&mut self, id: u32) -> core::result::Result<(), CollectionError>;
+
+    fn get(&mut self, id: u32) -> Option<&mut Matching<'res>>;
+
+    fn iter_mut(&mut self) -> impl Iterator<Item = (u32, &keyexpr, &mut Matching<'res>)>;
+}
+
+pub struct FixedCapacityMatchings<'res, const CAPACITY: usize, const MAX_KEYEXPR: usize> {
+    matchings: FnvIndexMap<u32, (heapless::String<MAX_KEYEXPR>, Matching<'res>), CAPACITY>,
+}
+
+impl<'res, const CAPACITY: usize, const MAX_KEYEXPR: usize> ZMatchings<'res>
+    for FixedCapacityMatchings<'res, CAPACITY, MAX_KEYEXPR>
+{
+    fn empty() -> Self {
+        Self {
+            matchings: FnvIndexMap::new(),
+        }
+    }
+
+    fn insert(
+        &mut self,
+        id: u32,
+        ke: &keyexpr,
+        matching: Matching<'res>,
+    ) -> core::result::Result<(), CollectionError> {
+        if self.matchings.contains_key(&id) {
+            return Err(CollectionError::KeyAlreadyExists);
+        }
+
+        let ke = heapless::String::try_from(ke.as_str())
+            .map_err(|_| CollectionError::CollectionTooSmall)?;
+
+        self.matchings
+            .insert(id, (ke, matching))
+            .map(|_| ())
+            .map_err(|_| CollectionError::CollectionIsFull)
+    }
+
+    fn remove(&mut self, id: u32) -> core::result::Result<(), CollectionError> {
+        self.matchings
+            .remove(&id)
+            .map(|_| ())
+            .ok_or(CollectionError::KeyNotFound)
+    }
+
+    fn get(&mut self, id: u32) -> Option<&mut Matching<'res>> {
+        self.matchings.get_mut(&id).map(|(_, matching)| matching)
+    }
+
+    fn iter_mut(&mut self) -> impl Iterator<Item = (u32, &keyexpr, &mut Matching<'res>)> {
+        self.matchings
+            .iter_mut()
+            .map(|(id, (ke, matching))| (*id, keyexpr::from_str_unchecked(ke.as_str()), matching))
+    }
+}
+
+#[cfg(feature = "alloc")]
+pub struct AllocMatchings<'res> {
+    matchings: alloc::collections::BTreeMap<u32, (alloc::string::String, Matching<'res>)>,
+}
+
+#[cfg(feature = "alloc")]
+impl<'res> ZMatchings<'res> for AllocMatchings<'res> {
+    fn empty() -> Self {
+        Self {
+            matchings: alloc::collections::BTreeMap::new(),
+        }
+    }
+
+    fn insert(
+        &mut self,
+        id: u32,
+        ke: &keyexpr,
+        matching: Matching<'res>,
+    ) -> core::result::Result<(), CollectionError> {
+        if self.matchings.contains_key(&id) {
+            return Err(CollectionError::KeyAlreadyExists);
+        }
+
+        self.matchings.insert(id, (ke.as_str().into(), matching));
+
+        Ok(())
+    }
+
+    fn remove(&mut self, id: u32) -> core::result::Result<(), CollectionError> {
+        self.matchings
+            .remove(&id)
+            .map(|_| ())
+            .ok_or(CollectionError::KeyNotFound)
+    }
+
+    fn get(&mut self, id: u32) -> Option<&mut Matching<'res>> {
+        self.matchings.get_mut(&id).map(|(_, matching)| matching)
+    }
+
+    fn iter_mut(&mut self) -> impl Iterator<Item = (u32, &keyexpr, &mut Matching<'res>)> {
+        self.matchings
+            .iter_mut()
+            .map(|(id, (ke, matching))| (*id, keyexpr::from_str_unchecked(ke.as_str()), matching))
+    }
+}
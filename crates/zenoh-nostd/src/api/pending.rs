@@ -0,0 +1,59 @@
+use zenoh_proto::CollectionError;
+
+/// Work left to `Session::run` by the handles that are dropped or cancelled outside of it: the
+/// ids of the dropped liveliness tokens, and of the cancelled gets.
+pub trait ZPending<T> {
+    fn empty() -> Self;
+
+    fn push(&mut self, item: T) -> core::result::Result<(), CollectionError>;
+
+    fn pop(&mut self) -> Option<T>;
+}
+
+/// Holds up to `CAPACITY` items. Each token or get is pending at most once, so a capacity as
+/// large as the one of the tokens or of the get callbacks never runs out.
+pub struct FixedCapacityPending<T, const CAPACITY: usize> {
+    items: heapless::Deque<T, CAPACITY>,
+}
+
+impl<T, const CAPACITY: usize> ZPending<T> for FixedCapacityPending<T, CAPACITY> {
+    fn empty() -> Self {
+        Self {
+            items: heapless::Deque::new(),
+        }
+    }
+
+    fn push(&mut self, item: T) -> core::result::Result<(), CollectionError> {
+        self.items
+            .push_back(item)
+            .map_err(|_| CollectionError::CollectionIsFull)
+    }
+
+    fn pop(&mut self) -> Option<T> {
+        self.items.pop_front()
+    }
+}
+
+#[cfg(feature = "alloc")]
+pub struct AllocPending<T> {
+    items: alloc::collections::VecDeque<T>,
+}
+
+#[cfg(feature = "alloc")]
+impl<T> ZPending<T> for AllocPending<T> {
+    fn empty() -> Self {
+        Self {
+            items: alloc::collections::VecDeque::new(),
+        }
+    }
+
+    fn push(&mut self, item: T) -> core::result::Result<(), CollectionError> {
+        self.items.push_back(item);
+
+        Ok(())
+    }
+
+    fn pop(&mut self) -> Option<T> {
+        self.items.pop_front()
+    }
+}
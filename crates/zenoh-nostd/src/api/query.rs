@@ -1,8 +1,16 @@
 use core::str::FromStr;
 
-use zenoh_proto::{CollectionError, SessionError, keyexpr};
-
-use crate::{api::session::Session, config::ZSessionConfig};
+use zenoh_proto::{
+    CollectionError, Parameters, SessionError, exts::QoS, fields::Reliability, keyexpr,
+};
+
+use crate::{
+    api::session::{
+        Session,
+        reply::{ReplyBuilder, ReplyDelBuilder, ReplyErrBuilder},
+    },
+    config::ZSessionConfig,
+};
 
 pub struct QueryableQuery<'a, 'res, Config>
 where
@@ -10,6 +18,9 @@ where
 {
     session: &'a Session<'res, Config>,
     rid: u32,
+    eid: u32,
+    reliability: Reliability,
+    qos: QoS,
     ke: &'a keyexpr,
     parameters: Option<&'a str>,
     payload: Option<&'a [u8]>,
@@ -23,6 +34,8 @@ where
     pub(crate) fn new(
         session: &'a Session<'res, Config>,
         rid: u32,
+        reliability: Reliability,
+        qos: QoS,
         ke: &'a keyexpr,
         parameters: Option<&'a str>,
         payload: Option<&'a [u8]>,
@@ -30,6 +43,9 @@ where
         Self {
             session,
             rid,
+            eid: 0,
+            reliability,
+            qos,
             ke,
             parameters,
             payload,
@@ -37,37 +53,71 @@ where
         }
     }
 
+    /// Sets the queryable the query is handed to, which signs the replies.
+    pub(crate) fn set_queryable(&mut self, id: u32) {
+        self.eid = id;
+    }
+
     pub fn keyexpr(&self) -> &keyexpr {
         self.ke
     }
 
-    pub fn parameters(&self) -> Option<&str> {
-        self.parameters
+    pub fn parameters(&self) -> Parameters<'a> {
+        Parameters::new(self.parameters.unwrap_or_default())
     }
 
     pub fn payload(&self) -> Option<&[u8]> {
         self.payload
     }
 
-    pub async fn reply(
-        &self,
-        ke: &keyexpr,
-        payload: &[u8],
-    ) -> core::result::Result<(), SessionError> {
-        self.session.reply(self.rid, ke, payload).await
+    pub fn reply<'b>(
+        &'b self,
+        ke: &'b keyexpr,
+        payload: &'b [u8],
+    ) -> ReplyBuilder<'b, 'res, Config> {
+        ReplyBuilder::new(
+            self.session,
+            self.rid,
+            self.eid,
+            self.reliability,
+            self.qos,
+            ke,
+            payload,
+        )
+    }
+
+    pub fn reply_del<'b>(&'b self, ke: &'b keyexpr) -> ReplyDelBuilder<'b, 'res, Config> {
+        ReplyDelBuilder::new(
+            self.session,
+            self.rid,
+            self.eid,
+            self.reliability,
+            self.qos,
+            ke,
+        )
     }
 
-    pub async fn err(
-        &self,
-        ke: &keyexpr,
-        payload: &[u8],
-    ) -> core::result::Result<(), SessionError> {
-        self.session.err(self.rid, ke, payload).await
+    pub fn err<'b>(
+        &'b self,
+        ke: &'b keyexpr,
+        payload: &'b [u8],
+    ) -> ReplyErrBuilder<'b, 'res, Config> {
+        ReplyErrBuilder::new(
+            self.session,
+            self.rid,
+            self.eid,
+            self.reliability,
+            self.qos,
+            ke,
+            payload,
+        )
     }
 
     pub async fn finalize(&mut self) -> core::result::Result<(), SessionError> {
         if !self.finalized {
-            self.session.finalize(self.rid).await?;
+            self.session
+                .finalize(self.rid, self.reliability, self.qos)
+                .await?;
             self.finalized = true
         }
 
@@ -85,6 +135,9 @@ pub struct FixedCapacityQueryableQuery<
 {
     session: &'static Session<'static, Config>,
     rid: u32,
+    eid: u32,
+    reliability: Reliability,
+    qos: QoS,
     ke: heapless::String<MAX_KEYEXPR>,
     parameters: Option<heapless::String<MAX_PARAMETERS>>,
     payload: Option<heapless::Vec<u8, MAX_PAYLOAD>>,
@@ -100,33 +153,62 @@ where
         keyexpr::from_str_unchecked(self.ke.as_str())
     }
 
-    pub fn parameters(&self) -> Option<&str> {
-        self.parameters.as_ref().map(|p| p.as_str())
+    pub fn parameters(&self) -> Parameters<'_> {
+        Parameters::new(self.parameters.as_ref().map_or("", |p| p.as_str()))
     }
 
     pub fn payload(&self) -> Option<&[u8]> {
         self.payload.as_ref().map(|p| p.as_slice())
     }
 
-    pub async fn reply(
-        &self,
-        ke: &keyexpr,
-        payload: &[u8],
-    ) -> core::result::Result<(), SessionError> {
-        self.session.reply(self.rid, ke, payload).await
+    pub fn reply<'b>(
+        &'b self,
+        ke: &'b keyexpr,
+        payload: &'b [u8],
+    ) -> ReplyBuilder<'b, 'static, Config> {
+        ReplyBuilder::new(
+            self.session,
+            self.rid,
+            self.eid,
+            self.reliability,
+            self.qos,
+            ke,
+            payload,
+        )
+    }
+
+    pub fn reply_del<'b>(&'b self, ke: &'b keyexpr) -> ReplyDelBuilder<'b, 'static, Config> {
+        ReplyDelBuilder::new(
+            self.session,
+            self.rid,
+            self.eid,
+            self.reliability,
+            self.qos,
+            ke,
+        )
     }
 
-    pub async fn err(
-        &self,
-        ke: &keyexpr,
-        payload: &[u8],
-    ) -> core::result::Result<(), SessionError> {
-        self.session.err(self.rid, ke, payload).await
+    pub fn err<'b>(
+        &'b self,
+        ke: &'b keyexpr,
+        payload: &'b [u8],
+    ) -> ReplyErrBuilder<'b, 'static, Config> {
+        ReplyErrBuilder::new(
+            self.session,
+            self.rid,
+            self.eid,
+            self.reliability,
+            self.qos,
+            ke,
+            payload,
+        )
     }
 
     pub async fn finalize(&mut self) -> core::result::Result<(), SessionError> {
         if !self.finalized {
-            self.session.finalize(self.rid).await?;
+            self.session
+                .finalize(self.rid, self.reliability, self.qos)
+                .await?;
             self.finalized = true
         }
 
@@ -155,6 +237,9 @@ where
         Ok(Self {
             session,
             rid: value.rid,
+            eid: value.eid,
+            reliability: value.reliability,
+            qos: value.qos,
             ke: heapless::String::from_str(value.keyexpr().as_str())
                 .map_err(|_| CollectionError::CollectionTooSmall)?,
             parameters: value
@@ -179,6 +264,9 @@ where
 {
     session: &'static Session<'static, Config>,
     rid: u32,
+    eid: u32,
+    reliability: Reliability,
+    qos: QoS,
     ke: alloc::string::String,
     parameters: Option<alloc::string::String>,
     payload: Option<alloc::vec::Vec<u8>>,
@@ -194,33 +282,62 @@ where
         keyexpr::from_str_unchecked(self.ke.as_str())
     }
 
-    pub fn parameters(&self) -> Option<&str> {
-        self.parameters.as_deref()
+    pub fn parameters(&self) -> Parameters<'_> {
+        Parameters::new(self.parameters.as_deref().unwrap_or_default())
     }
 
     pub fn payload(&self) -> Option<&[u8]> {
         self.payload.as_deref()
     }
 
-    pub async fn reply(
-        &self,
-        ke: &keyexpr,
-        payload: &[u8],
-    ) -> core::result::Result<(), SessionError> {
-        self.session.reply(self.rid, ke, payload).await
+    pub fn reply<'b>(
+        &'b self,
+        ke: &'b keyexpr,
+        payload: &'b [u8],
+    ) -> ReplyBuilder<'b, 'static, Config> {
+        ReplyBuilder::new(
+            self.session,
+            self.rid,
+            self.eid,
+            self.reliability,
+            self.qos,
+            ke,
+            payload,
+        )
+    }
+
+    pub fn reply_del<'b>(&'b self, ke: &'b keyexpr) -> ReplyDelBuilder<'b, 'static, Config> {
+        ReplyDelBuilder::new(
+            self.session,
+            self.rid,
+            self.eid,
+            self.reliability,
+            self.qos,
+            ke,
+        )
     }
 
-    pub async fn err(
-        &self,
-        ke: &keyexpr,
-        payload: &[u8],
-    ) -> core::result::Result<(), SessionError> {
-        self.session.err(self.rid, ke, payload).await
+    pub fn err<'b>(
+        &'b self,
+        ke: &'b keyexpr,
+        payload: &'b [u8],
+    ) -> ReplyErrBuilder<'b, 'static, Config> {
+        ReplyErrBuilder::new(
+            self.session,
+            self.rid,
+            self.eid,
+            self.reliability,
+            self.qos,
+            ke,
+            payload,
+        )
     }
 
     pub async fn finalize(&mut self) -> core::result::Result<(), SessionError> {
         if !self.finalized {
-            self.session.finalize(self.rid).await?;
+            self.session
+                .finalize(self.rid, self.reliability, self.qos)
+                .await?;
             self.finalized = true
         }
 
@@ -250,6 +367,9 @@ where
         Ok(Self {
             session,
             rid: value.rid,
+            eid: value.eid,
+            reliability: value.reliability,
+            qos: value.qos,
             ke: alloc::string::String::from(value.keyexpr().as_str()),
             parameters: value.parameters.map(alloc::string::String::from),
             payload: value.payload.map(alloc::vec::Vec::from),
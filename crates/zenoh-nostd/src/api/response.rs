@@ -18,13 +18,22 @@ impl<'a> GetResponse<'a> {
 }
 
 #[derive(Debug)]
-pub enum FixedCapacityGetResponse<const MAX_KEYEXPR: usize, const MAX_PAYLOAD: usize> {
-    Ok(FixedCapacitySample<MAX_KEYEXPR, MAX_PAYLOAD>),
-    Err(FixedCapacitySample<MAX_KEYEXPR, MAX_PAYLOAD>),
+pub enum FixedCapacityGetResponse<
+    const MAX_KEYEXPR: usize,
+    const MAX_PAYLOAD: usize,
+    const MAX_ATTACHMENT: usize = 0,
+    const MAX_SCHEMA: usize = 0,
+> {
+    Ok(FixedCapacitySample<MAX_KEYEXPR, MAX_PAYLOAD, MAX_ATTACHMENT, MAX_SCHEMA>),
+    Err(FixedCapacitySample<MAX_KEYEXPR, MAX_PAYLOAD, MAX_ATTACHMENT, MAX_SCHEMA>),
 }
 
-impl<const MAX_KEYEXPR: usize, const MAX_PAYLOAD: usize>
-    FixedCapacityGetResponse<MAX_KEYEXPR, MAX_PAYLOAD>
+impl<
+    const MAX_KEYEXPR: usize,
+    const MAX_PAYLOAD: usize,
+    const MAX_ATTACHMENT: usize,
+    const MAX_SCHEMA: usize,
+> FixedCapacityGetResponse<MAX_KEYEXPR, MAX_PAYLOAD, MAX_ATTACHMENT, MAX_SCHEMA>
 {
     pub fn as_ref(&self) -> GetResponse<'_> {
         match self {
@@ -34,8 +43,13 @@ impl<const MAX_KEYEXPR: usize, const MAX_PAYLOAD: usize>
     }
 }
 
-impl<const MAX_KEYEXPR: usize, const MAX_PAYLOAD: usize> TryFrom<&GetResponse<'_>>
-    for FixedCapacityGetResponse<MAX_KEYEXPR, MAX_PAYLOAD>
+impl<
+    const MAX_KEYEXPR: usize,
+    const MAX_PAYLOAD: usize,
+    const MAX_ATTACHMENT: usize,
+    const MAX_SCHEMA: usize,
+> TryFrom<&GetResponse<'_>>
+    for FixedCapacityGetResponse<MAX_KEYEXPR, MAX_PAYLOAD, MAX_ATTACHMENT, MAX_SCHEMA>
 {
     type Error = CollectionError;
 
@@ -1,16 +1,94 @@
 use core::str::FromStr;
 
-use zenoh_proto::{CollectionError, keyexpr};
+use zenoh_proto::{
+    CollectionError,
+    exts::SourceInfo,
+    fields::{Encoding, Timestamp},
+    keyexpr,
+    msgs::{Del, PushBody, Put},
+};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum SampleKind {
+    #[default]
+    Put,
+    Delete,
+}
 
 #[derive(Debug)]
 pub struct Sample<'a> {
     ke: &'a keyexpr,
     payload: &'a [u8],
+    kind: SampleKind,
+    encoding: Encoding<'a>,
+    timestamp: Option<Timestamp>,
+    attachment: Option<&'a [u8]>,
+    source_info: Option<SourceInfo>,
 }
 
 impl<'a> Sample<'a> {
     pub fn new(ke: &'a keyexpr, payload: &'a [u8]) -> Self {
-        Self { ke, payload }
+        Self {
+            ke,
+            payload,
+            kind: SampleKind::Put,
+            encoding: Encoding::default(),
+            timestamp: None,
+            attachment: None,
+            source_info: None,
+        }
+    }
+
+    pub fn delete(ke: &'a keyexpr) -> Self {
+        Self {
+            kind: SampleKind::Delete,
+            ..Self::new(ke, &[])
+        }
+    }
+
+    /// The sample carried by a `Put` or a `Del` message.
+    pub(crate) fn from_push(ke: &'a keyexpr, body: PushBody<'a>) -> Self {
+        match body {
+            PushBody::Put(Put {
+                timestamp,
+                encoding,
+                sinfo,
+                attachment,
+                payload,
+            }) => Self::new(ke, payload)
+                .with_encoding(encoding)
+                .with_timestamp(timestamp)
+                .with_attachment(attachment.map(|a| a.buffer))
+                .with_source_info(sinfo),
+            PushBody::Del(Del {
+                timestamp,
+                sinfo,
+                attachment,
+            }) => Self::delete(ke)
+                .with_timestamp(timestamp)
+                .with_attachment(attachment.map(|a| a.buffer))
+                .with_source_info(sinfo),
+        }
+    }
+
+    pub fn with_encoding(mut self, encoding: Encoding<'a>) -> Self {
+        self.encoding = encoding;
+        self
+    }
+
+    pub fn with_timestamp(mut self, timestamp: Option<Timestamp>) -> Self {
+        self.timestamp = timestamp;
+        self
+    }
+
+    pub fn with_attachment(mut self, attachment: Option<&'a [u8]>) -> Self {
+        self.attachment = attachment;
+        self
+    }
+
+    pub fn with_source_info(mut self, source_info: Option<SourceInfo>) -> Self {
+        self.source_info = source_info;
+        self
     }
 
     pub fn keyexpr(&self) -> &keyexpr {
@@ -20,16 +98,56 @@ impl<'a> Sample<'a> {
     pub fn payload(&self) -> &[u8] {
         self.payload
     }
+
+    pub fn kind(&self) -> SampleKind {
+        self.kind
+    }
+
+    pub fn encoding(&self) -> Encoding<'_> {
+        self.encoding.clone()
+    }
+
+    pub fn timestamp(&self) -> Option<Timestamp> {
+        self.timestamp
+    }
+
+    pub fn attachment(&self) -> Option<&[u8]> {
+        self.attachment
+    }
+
+    /// The entity that published the sample, and its sequence number, if the publisher sent
+    /// them.
+    pub fn source_info(&self) -> Option<SourceInfo> {
+        self.source_info
+    }
 }
 
+/// An owned [`Sample`]. Converting a sample fails with `CollectionTooSmall` if one of its
+/// parts does not fit. The attachment and the encoding schema are dropped when `MAX_ATTACHMENT`
+/// or `MAX_SCHEMA` is 0, so that they are only kept when asked for.
 #[derive(Debug)]
-pub struct FixedCapacitySample<const MAX_KEYEXPR: usize, const MAX_PAYLOAD: usize> {
+pub struct FixedCapacitySample<
+    const MAX_KEYEXPR: usize,
+    const MAX_PAYLOAD: usize,
+    const MAX_ATTACHMENT: usize = 0,
+    const MAX_SCHEMA: usize = 0,
+> {
     ke: heapless::String<MAX_KEYEXPR>,
     payload: heapless::Vec<u8, MAX_PAYLOAD>,
+    kind: SampleKind,
+    encoding: u16,
+    schema: Option<heapless::Vec<u8, MAX_SCHEMA>>,
+    timestamp: Option<Timestamp>,
+    attachment: Option<heapless::Vec<u8, MAX_ATTACHMENT>>,
+    source_info: Option<SourceInfo>,
 }
 
-impl<const MAX_KEYEXPR: usize, const MAX_PAYLOAD: usize>
-    FixedCapacitySample<MAX_KEYEXPR, MAX_PAYLOAD>
+impl<
+    const MAX_KEYEXPR: usize,
+    const MAX_PAYLOAD: usize,
+    const MAX_ATTACHMENT: usize,
+    const MAX_SCHEMA: usize,
+> FixedCapacitySample<MAX_KEYEXPR, MAX_PAYLOAD, MAX_ATTACHMENT, MAX_SCHEMA>
 {
     pub fn keyexpr(&self) -> &keyexpr {
         keyexpr::from_str_unchecked(self.ke.as_str())
@@ -39,25 +157,77 @@ impl<const MAX_KEYEXPR: usize, const MAX_PAYLOAD: usize>
         self.payload.as_slice()
     }
 
+    pub fn kind(&self) -> SampleKind {
+        self.kind
+    }
+
+    pub fn encoding(&self) -> Encoding<'_> {
+        Encoding {
+            id: self.encoding,
+            schema: self.schema.as_deref(),
+        }
+    }
+
+    pub fn timestamp(&self) -> Option<Timestamp> {
+        self.timestamp
+    }
+
+    pub fn attachment(&self) -> Option<&[u8]> {
+        self.attachment.as_deref()
+    }
+
+    pub fn source_info(&self) -> Option<SourceInfo> {
+        self.source_info
+    }
+
     pub fn as_ref(&self) -> Sample<'_> {
         Sample {
             ke: self.keyexpr(),
             payload: self.payload(),
+            kind: self.kind,
+            encoding: self.encoding(),
+            timestamp: self.timestamp,
+            attachment: self.attachment(),
+            source_info: self.source_info,
         }
     }
 }
 
-impl<const MAX_KEYEXPR: usize, const MAX_PAYLOAD: usize> TryFrom<&Sample<'_>>
-    for FixedCapacitySample<MAX_KEYEXPR, MAX_PAYLOAD>
+impl<
+    const MAX_KEYEXPR: usize,
+    const MAX_PAYLOAD: usize,
+    const MAX_ATTACHMENT: usize,
+    const MAX_SCHEMA: usize,
+> TryFrom<&Sample<'_>>
+    for FixedCapacitySample<MAX_KEYEXPR, MAX_PAYLOAD, MAX_ATTACHMENT, MAX_SCHEMA>
 {
     type Error = CollectionError;
 
     fn try_from(value: &Sample<'_>) -> Result<Self, Self::Error> {
+        fn bytes<const N: usize>(
+            bytes: Option<&[u8]>,
+        ) -> Result<Option<heapless::Vec<u8, N>>, CollectionError> {
+            if N == 0 {
+                return Ok(None);
+            }
+
+            bytes
+                .map(heapless::Vec::from_slice)
+                .transpose()
+                .map_err(|_| CollectionError::CollectionTooSmall)
+        }
+
         Ok(Self {
             ke: heapless::String::from_str(value.keyexpr().as_str())
                 .map_err(|_| CollectionError::CollectionTooSmall)?,
             payload: heapless::Vec::from_slice(value.payload())
                 .map_err(|_| CollectionError::CollectionTooSmall)?,
+            kind: value.kind(),
+            encoding: value.encoding.id,
+            schema: bytes(value.encoding.schema)?,
+            timestamp: value.timestamp,
+            attachment: bytes(value.attachment)?,
+            source_info: value.source_info,
         })
     }
 }
@@ -67,6 +237,12 @@ impl<const MAX_KEYEXPR: usize, const MAX_PAYLOAD: usize> TryFrom<&Sample<'_>>
 pub struct AllocSample {
     ke: alloc::string::String,
     payload: alloc::vec::Vec<u8>,
+    kind: SampleKind,
+    encoding: u16,
+    schema: Option<alloc::vec::Vec<u8>>,
+    timestamp: Option<Timestamp>,
+    attachment: Option<alloc::vec::Vec<u8>>,
+    source_info: Option<SourceInfo>,
 }
 
 #[cfg(feature = "alloc")]
@@ -79,10 +255,38 @@ impl AllocSample {
         self.payload.as_slice()
     }
 
+    pub fn kind(&self) -> SampleKind {
+        self.kind
+    }
+
+    pub fn encoding(&self) -> Encoding<'_> {
+        Encoding {
+            id: self.encoding,
+            schema: self.schema.as_deref(),
+        }
+    }
+
+    pub fn timestamp(&self) -> Option<Timestamp> {
+        self.timestamp
+    }
+
+    pub fn attachment(&self) -> Option<&[u8]> {
+        self.attachment.as_deref()
+    }
+
+    pub fn source_info(&self) -> Option<SourceInfo> {
+        self.source_info
+    }
+
     pub fn as_ref(&self) -> Sample<'_> {
         Sample {
             ke: self.keyexpr(),
             payload: self.payload(),
+            kind: self.kind,
+            encoding: self.encoding(),
+            timestamp: self.timestamp,
+            attachment: self.attachment(),
+            source_info: self.source_info,
         }
     }
 }
@@ -95,6 +299,12 @@ impl TryFrom<&Sample<'_>> for AllocSample {
         Ok(Self {
             ke: alloc::string::String::from(value.keyexpr().as_str()),
             payload: alloc::vec::Vec::from(value.payload()),
+            kind: value.kind(),
+            encoding: value.encoding.id,
+            schema: value.encoding.schema.map(alloc::vec::Vec::from),
+            timestamp: value.timestamp,
+            attachment: value.attachment.map(alloc::vec::Vec::from),
+            source_info: value.source_info,
         })
     }
 }
@@ -1,24 +1,53 @@
+use core::cell::RefCell;
+
+use embassy_futures::select::{Either, select};
 use embassy_sync::{
     blocking_mutex::raw::NoopRawMutex,
+    channel::{DynamicReceiver, DynamicSender},
     mutex::{Mutex, MutexGuard},
 };
-use zenoh_proto::{Endpoint, TransportLinkError};
+use zenoh_proto::{
+    Endpoint, SessionError, TransportLinkError,
+    exts::QoS,
+    fields::{Field, Reliability, Resolution},
+    keyexpr,
+    msgs::{
+        Declare, DeclareBody, Interest, InterestFinal, InterestInner, InterestMode, NetworkBody,
+        NetworkMessage,
+    },
+};
 
 use crate::{
-    api::callbacks::ZCallbacks,
+    api::{
+        callbacks::ZCallbacks,
+        consolidation::ZConsolidations,
+        events::TransportEvent,
+        keyexprs::{ZKeyExprs, ZRemoteDeclarations, ZRemoteKeyExprs},
+        pending::ZPending,
+    },
     config::ZSessionConfig,
-    io::{driver::Driver, transport::TransportLink},
+    io::{
+        driver::Driver,
+        transport::{TransportLink, ZTransportLinkTx},
+    },
+    notify::Notify,
     platform::ZLinkManager,
     resources::Resources,
 };
 
+mod close;
 mod run;
 
+pub mod delete;
 pub mod get;
+pub mod keyexpr;
+pub mod liveliness;
+mod matching;
 pub mod r#pub;
 pub mod put;
 pub mod querier;
 pub mod queryable;
+pub mod reply;
 pub mod sub;
 
 pub(crate) struct SessionState<'res, Config>
@@ -26,27 +55,51 @@ where
     Config: ZSessionConfig + 'res,
 {
     next: u32,
+    next_mask: u32,
     sub_callbacks: Config::SubCallbacks<'res>,
     get_callbacks: Config::GetCallbacks<'res>,
+    consolidations: Config::Consolidations,
     queryable_callbacks: Config::QueryableCallbacks<'res>,
+    liveliness_callbacks: Config::LivelinessCallbacks<'res>,
+    tokens: Config::Tokens<'res>,
+    matchings: Config::Matchings<'res>,
+    remote_keyexprs: Config::RemoteKeyExprs,
+    remote_subscribers: Config::RemoteDeclarations,
+    remote_queryables: Config::RemoteDeclarations,
+    remote_tokens: Config::RemoteDeclarations,
 }
 
 impl<'res, Config> SessionState<'res, Config>
 where
     Config: ZSessionConfig,
 {
-    pub fn new() -> Self {
+    pub fn new(resolution: Resolution) -> Self {
         Self {
             next: 0,
+            next_mask: resolution.get(Field::RequestID).mask() as u32,
             sub_callbacks: Config::SubCallbacks::empty(),
             get_callbacks: Config::GetCallbacks::empty(),
+            consolidations: Config::Consolidations::empty(),
             queryable_callbacks: Config::QueryableCallbacks::empty(),
+            liveliness_callbacks: Config::LivelinessCallbacks::empty(),
+            tokens: Config::Tokens::empty(),
+            matchings: Config::Matchings::empty(),
+            remote_keyexprs: Config::RemoteKeyExprs::empty(),
+            remote_subscribers: Config::RemoteDeclarations::empty(),
+            remote_queryables: Config::RemoteDeclarations::empty(),
+            remote_tokens: Config::RemoteDeclarations::empty(),
         }
     }
 
+    /// Bounds the next ids to the resolution negotiated with a new peer.
+    pub(crate) fn set_resolution(&mut self, resolution: Resolution) {
+        self.next_mask = resolution.get(Field::RequestID).mask() as u32;
+        self.next &= self.next_mask;
+    }
+
     pub(crate) fn next(&mut self) -> u32 {
         let next = self.next;
-        self.next += 1;
+        self.next = self.next.wrapping_add(1) & self.next_mask;
         next
     }
 }
@@ -57,6 +110,15 @@ where
 {
     driver: Driver<'res, <Config::LinkManager as ZLinkManager>::Link<'res>, Config::Buff>,
     state: Mutex<NoopRawMutex, SessionState<'res, Config>>,
+    keyexprs: Mutex<NoopRawMutex, Config::KeyExprs<'res>>,
+    reconnect: Option<(&'res Config, Endpoint<'res>)>,
+    events: Option<DynamicSender<'res, TransportEvent>>,
+    dropped_tokens: RefCell<Config::DroppedTokens>,
+    // The gets cancelled by their receiver, and whether they are interests.
+    cancelled_gets: RefCell<Config::CancelledGets>,
+    // Wakes up `run` when a token is dropped or a get is cancelled.
+    pending: Notify,
+    completed_gets: Notify,
 }
 
 impl<'res, Config> Session<'res, Config>
@@ -69,15 +131,148 @@ where
             Config::Buff,
         >,
     ) -> Self {
+        let resolution = transport.transport().resolution;
+
         Self {
             driver: Driver::new(transport),
-            state: Mutex::new(SessionState::new()),
+            state: Mutex::new(SessionState::new(resolution)),
+            keyexprs: Mutex::new(Config::KeyExprs::empty()),
+            reconnect: None,
+            events: None,
+            dropped_tokens: RefCell::new(Config::DroppedTokens::empty()),
+            cancelled_gets: RefCell::new(Config::CancelledGets::empty()),
+            pending: Notify::new(),
+            completed_gets: Notify::new(),
+        }
+    }
+
+    /// Makes `run` connect again to `endpoint` when the transport is closed, declaring all the
+    /// entities of the session to the new peer.
+    pub fn with_reconnect(mut self, config: &'res Config, endpoint: Endpoint<'res>) -> Self {
+        self.reconnect = Some((config, endpoint));
+        self
+    }
+
+    /// Reports the events of the transport to `sender`. Events are dropped while the channel is
+    /// full.
+    pub fn with_events(mut self, sender: DynamicSender<'res, TransportEvent>) -> Self {
+        self.events = Some(sender);
+        self
+    }
+
+    pub(crate) fn event(&self, event: TransportEvent) {
+        if let Some(sender) = &self.events
+            && sender.try_send(event).is_err()
+        {
+            zenoh_proto::debug!(
+                "{}: Event channel is full, dropping an event",
+                zenoh_proto::zctx!()
+            );
+        }
+    }
+
+    /// The next item of `receiver`, or `None` once the session is closed and `receiver` is
+    /// empty.
+    pub(crate) async fn recv<T>(&self, receiver: &DynamicReceiver<'_, T>) -> Option<T> {
+        loop {
+            if let Ok(item) = receiver.try_receive() {
+                return Some(item);
+            }
+
+            if self.driver.closed() {
+                return None;
+            }
+
+            match select(receiver.receive(), self.driver.wait_closed()).await {
+                Either::First(item) => return Some(item),
+                Either::Second(()) => {}
+            }
         }
     }
 
     pub(crate) async fn state(&self) -> MutexGuard<'_, NoopRawMutex, SessionState<'res, Config>> {
         self.state.lock().await
     }
+
+    /// Sends `msg`, returning `false` if it has been dropped: its congestion control is `Drop`
+    /// and the TX batch is full.
+    pub(crate) async fn send(
+        &self,
+        msg: NetworkMessage<'_>,
+    ) -> core::result::Result<bool, SessionError> {
+        let sent = self.driver.send(msg).await?;
+        if !sent {
+            zenoh_proto::debug!(
+                "{}: TX batch is full, dropping message",
+                zenoh_proto::zctx!()
+            );
+        }
+
+        Ok(sent)
+    }
+
+    pub(crate) async fn send_declare(
+        &self,
+        body: DeclareBody<'_>,
+    ) -> core::result::Result<(), SessionError> {
+        self.send(NetworkMessage {
+            reliability: Reliability::Reliable,
+            qos: QoS::declare(),
+            body: NetworkBody::Declare(Declare {
+                body,
+                qos: QoS::declare(),
+                ..Default::default()
+            }),
+        })
+        .await?;
+
+        Ok(())
+    }
+
+    /// Declares an interest in the entities on `ke` selected by `options`.
+    pub(crate) async fn send_interest(
+        &self,
+        id: u32,
+        mode: InterestMode,
+        options: u8,
+        ke: &keyexpr,
+    ) -> core::result::Result<(), SessionError> {
+        self.send(NetworkMessage {
+            reliability: Reliability::Reliable,
+            qos: QoS::declare(),
+            body: NetworkBody::Interest(Interest {
+                id,
+                mode,
+                inner: InterestInner {
+                    options,
+                    wire_expr: Some(self.wire_expr(ke).await),
+                },
+                qos: QoS::declare(),
+                ..Default::default()
+            }),
+        })
+        .await?;
+
+        Ok(())
+    }
+
+    pub(crate) async fn send_interest_final(
+        &self,
+        id: u32,
+    ) -> core::result::Result<(), SessionError> {
+        self.send(NetworkMessage {
+            reliability: Reliability::Reliable,
+            qos: QoS::declare(),
+            body: NetworkBody::InterestFinal(InterestFinal {
+                id,
+                qos: QoS::declare(),
+                ..Default::default()
+            }),
+        })
+        .await?;
+
+        Ok(())
+    }
 }
 
 pub async fn session_connect<'res, Config>(
@@ -93,6 +288,22 @@ where
     )))
 }
 
+pub async fn session_connect_reconnecting<'res, Config>(
+    resources: &'res mut Resources<'res, Config>,
+    config: &'res Config,
+    endpoint: Endpoint<'res>,
+) -> core::result::Result<Session<'res, Config>, TransportLinkError>
+where
+    Config: ZSessionConfig,
+{
+    let transport = config
+        .transports()
+        .connect(endpoint.clone(), config.buff())
+        .await?;
+
+    Ok(Session::new(resources.init(transport)).with_reconnect(config, endpoint))
+}
+
 pub async fn session_listen<'res, Config>(
     resources: &'res mut Resources<'res, Config>,
     config: &'res Config,
@@ -132,6 +343,36 @@ macro_rules! __session_connect {
     }};
 }
 
+#[macro_export]
+macro_rules! __session_connect_reconnecting {
+    (
+        $CONFIG:ty: $config:expr,
+        $endpoint:expr
+    ) => {{
+        static CONFIG: static_cell::StaticCell<$CONFIG> = static_cell::StaticCell::new();
+        let config = CONFIG.init($config);
+
+        static RESOURCES: static_cell::StaticCell<$crate::session::Resources<'static, $CONFIG>> =
+            static_cell::StaticCell::new();
+
+        static SESSION: static_cell::StaticCell<$crate::session::Session<'static, $CONFIG>> =
+            static_cell::StaticCell::new();
+
+        let endpoint = $endpoint;
+        SESSION.init(
+            $crate::session::Session::new(
+                RESOURCES.init($crate::session::Resources::default()).init(
+                    config
+                        .transports()
+                        .connect(endpoint.clone(), config.buff())
+                        .await?,
+                ),
+            )
+            .with_reconnect(config, endpoint),
+        ) as &$crate::session::Session<'static, $CONFIG>
+    }};
+}
+
 #[macro_export]
 macro_rules! __session_listen {
     (
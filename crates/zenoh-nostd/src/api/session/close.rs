@@ -0,0 +1,102 @@
+use zenoh_proto::{
+    SessionError,
+    msgs::{
+        Close, DeclareBody, UndeclareKeyExpr, UndeclareQueryable, UndeclareSubscriber,
+        UndeclareToken,
+    },
+};
+
+use crate::{
+    api::{
+        callbacks::ZCallbacks, consolidation::ZConsolidations, keyexprs::ZKeyExprs,
+        matching::ZMatchings, session::Session,
+    },
+    config::ZSessionConfig,
+};
+
+impl<'res, Config> Session<'res, Config>
+where
+    Config: ZSessionConfig,
+{
+    /// Undeclares all the entities of the session and sends a `Close` to the peer, so that it
+    /// drops the session right away instead of waiting for the lease to expire. `run` then
+    /// returns, the channel receivers return `None` once empty, and any later call fails with
+    /// `TransportClosed`. The session is closed even if some undeclarations fail, the first
+    /// error is returned.
+    pub async fn close(&self) -> core::result::Result<(), SessionError> {
+        // The entities are taken out of the state, so that `run` keeps going while they are
+        // undeclared.
+        let mut state = self.state().await;
+        let mut subs = core::mem::replace(&mut state.sub_callbacks, Config::SubCallbacks::empty());
+        let mut queryables = core::mem::replace(
+            &mut state.queryable_callbacks,
+            Config::QueryableCallbacks::empty(),
+        );
+        let mut liveliness = core::mem::replace(
+            &mut state.liveliness_callbacks,
+            Config::LivelinessCallbacks::empty(),
+        );
+        let mut matchings = core::mem::replace(&mut state.matchings, Config::Matchings::empty());
+        let tokens = core::mem::replace(&mut state.tokens, Config::Tokens::empty());
+        let gets = core::mem::replace(&mut state.get_callbacks, Config::GetCallbacks::empty());
+        state.consolidations = Config::Consolidations::empty();
+        drop(state);
+
+        let keyexprs =
+            core::mem::replace(&mut *self.keyexprs.lock().await, Config::KeyExprs::empty());
+
+        drop(gets);
+        self.complete_gets();
+
+        let mut undeclared = Ok(());
+
+        for (id, _, _) in subs.iter_mut() {
+            let sent = self
+                .send_declare(DeclareBody::UndeclareSubscriber(UndeclareSubscriber {
+                    id,
+                    ..Default::default()
+                }))
+                .await;
+            undeclared = undeclared.and(sent);
+        }
+
+        for (id, _, _) in queryables.iter_mut() {
+            let sent = self
+                .send_declare(DeclareBody::UndeclareQueryable(UndeclareQueryable {
+                    id,
+                    ..Default::default()
+                }))
+                .await;
+            undeclared = undeclared.and(sent);
+        }
+
+        for (id, _, _) in liveliness.iter_mut() {
+            undeclared = undeclared.and(self.send_interest_final(id).await);
+        }
+
+        for (id, _, _) in matchings.iter_mut() {
+            undeclared = undeclared.and(self.send_interest_final(id).await);
+        }
+
+        for (id, _) in tokens.iter() {
+            let sent = self
+                .send_declare(DeclareBody::UndeclareToken(UndeclareToken {
+                    id: id as u32,
+                    ..Default::default()
+                }))
+                .await;
+            undeclared = undeclared.and(sent);
+        }
+
+        for (id, _) in keyexprs.iter() {
+            let sent = self
+                .send_declare(DeclareBody::UndeclareKeyExpr(UndeclareKeyExpr { id }))
+                .await;
+            undeclared = undeclared.and(sent);
+        }
+
+        let closed = self.driver.close(Close::GENERIC).await;
+
+        undeclared.and(closed.map_err(SessionError::from))
+    }
+}
@@ -0,0 +1,102 @@
+use zenoh_proto::{exts::*, fields::*, msgs::*, *};
+
+use crate::{api::session::Session, config::ZSessionConfig};
+
+pub struct DeleteBuilder<'a, 'res, Config>
+where
+    Config: ZSessionConfig,
+{
+    pub(crate) session: &'a Session<'res, Config>,
+
+    pub(crate) ke: &'a keyexpr,
+
+    pub(crate) timestamp: Option<Timestamp>,
+    pub(crate) attachment: Option<Attachment<'a>>,
+
+    pub(crate) reliability: Reliability,
+    pub(crate) priority: Priority,
+    pub(crate) congestion_control: CongestionControl,
+    pub(crate) express: bool,
+}
+
+impl<'a, 'res, Config> DeleteBuilder<'a, 'res, Config>
+where
+    Config: ZSessionConfig,
+{
+    pub(crate) fn new(session: &'a Session<'res, Config>, ke: &'a keyexpr) -> Self {
+        Self {
+            session,
+            ke,
+            timestamp: None,
+            attachment: None,
+            reliability: Reliability::default(),
+            priority: Priority::default(),
+            congestion_control: CongestionControl::default(),
+            express: false,
+        }
+    }
+
+    pub fn timestamp(mut self, timestamp: Timestamp) -> Self {
+        self.timestamp = Some(timestamp);
+        self
+    }
+
+    pub fn attachment(mut self, attachment: &'a [u8]) -> Self {
+        self.attachment = Some(Attachment { buffer: attachment });
+        self
+    }
+
+    pub fn reliability(mut self, reliability: Reliability) -> Self {
+        self.reliability = reliability;
+        self
+    }
+
+    pub fn priority(mut self, priority: Priority) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    pub fn congestion_control(mut self, congestion_control: CongestionControl) -> Self {
+        self.congestion_control = congestion_control;
+        self
+    }
+
+    pub fn express(mut self, express: bool) -> Self {
+        self.express = express;
+        self
+    }
+
+    /// Returns `false` if the delete has been dropped, under `CongestionControl::Drop` with the TX
+    /// batch full.
+    pub async fn finish(self) -> core::result::Result<bool, SessionError> {
+        let qos = QoS::new(self.priority, self.congestion_control, self.express);
+        let msg = Push {
+            wire_expr: self.session.wire_expr(self.ke).await,
+            payload: PushBody::Del(Del {
+                timestamp: self.timestamp,
+                attachment: self.attachment,
+                ..Default::default()
+            }),
+            timestamp: self.timestamp,
+            qos,
+            ..Default::default()
+        };
+
+        self.session
+            .send(NetworkMessage {
+                reliability: self.reliability,
+                qos,
+                body: NetworkBody::Push(msg),
+            })
+            .await
+    }
+}
+
+impl<'res, Config> Session<'res, Config>
+where
+    Config: ZSessionConfig,
+{
+    pub fn delete<'a>(&'a self, ke: &'a keyexpr) -> DeleteBuilder<'a, 'res, Config> {
+        DeleteBuilder::new(self, ke)
+    }
+}
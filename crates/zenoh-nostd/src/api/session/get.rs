@@ -1,13 +1,13 @@
 use core::time::Duration;
 
 use dyn_utils::{DynObject, storage::RawOrBox};
-use embassy_futures::select::{Either, select};
-use embassy_sync::channel::{DynamicReceiver, DynamicSender};
+use embassy_futures::select::{Either3, select3};
+use embassy_sync::channel::DynamicReceiver;
 use embassy_time::{Instant, Timer};
 use zenoh_proto::{
-    SessionError,
-    exts::{QoS, Value},
-    fields::{ConsolidationMode, Reliability, WireExpr},
+    Parameters, SessionError,
+    exts::{Budget, QoS, QueryTarget, Value},
+    fields::{CongestionControl, ConsolidationMode, Priority, Reliability},
     keyexpr,
     msgs::{NetworkBody, NetworkMessage, Query, Request, RequestBody},
 };
@@ -18,10 +18,11 @@ use crate::{
     api::{
         arg::GetResponseRef,
         callbacks::{AsyncCallback, DynCallback, FixedCapacityCallbacks, SyncCallback, ZCallbacks},
+        consolidation::{self, ZConsolidations},
+        pending::ZPending,
         session::Session,
     },
     config::ZSessionConfig,
-    io::transport::ZTransportLinkTx,
     session::GetResponse,
 };
 
@@ -30,21 +31,77 @@ pub type FixedCapacityGetCallbacks<
     const CAPACITY: usize,
     Callback = RawOrBox<16>,
     Future = RawOrBox<128>,
-> = FixedCapacityCallbacks<'a, GetResponseRef, CAPACITY, Callback, Future>;
+    const MAX_KEYEXPR: usize = 128,
+> = FixedCapacityCallbacks<'a, GetResponseRef, CAPACITY, Callback, Future, MAX_KEYEXPR>;
 
 #[cfg(feature = "alloc")]
 pub type AllocGetCallbacks<'a, Callback = RawOrBox<16>, Future = RawOrBox<128>> =
     AllocCallbacks<'a, GetResponseRef, Callback, Future>;
 
-pub struct GetResponses<'res, OwnedResponse = (), const CHANNEL: bool = false> {
-    ke: &'static keyexpr,
-    timedout: Instant,
-    receiver: Option<DynamicReceiver<'res, OwnedResponse>>,
+pub struct GetResponses<'a, 'res, Config, OwnedResponse = (), const CHANNEL: bool = false>
+where
+    Config: ZSessionConfig,
+{
+    pub(crate) rid: u32,
+    pub(crate) ke: &'a keyexpr,
+    pub(crate) timedout: Instant,
+    pub(crate) receiver: Option<DynamicReceiver<'a, OwnedResponse>>,
+    pub(crate) complete: bool,
+    // Whether the get is an interest, e.g. a liveliness get, which the peer is told to drop.
+    pub(crate) interest: bool,
+    pub(crate) session: &'a Session<'res, Config>,
 }
 
-impl<'res, OwnedResponse, const CHANNEL: bool> GetResponses<'res, OwnedResponse, CHANNEL> {
+impl<'a, 'res, Config, OwnedResponse, const CHANNEL: bool>
+    GetResponses<'a, 'res, Config, OwnedResponse, CHANNEL>
+where
+    Config: ZSessionConfig,
+{
+    pub(crate) fn new(
+        session: &'a Session<'res, Config>,
+        rid: u32,
+        ke: &'a keyexpr,
+        timedout: Instant,
+        receiver: Option<DynamicReceiver<'a, OwnedResponse>>,
+    ) -> Self {
+        Self {
+            rid,
+            ke,
+            timedout,
+            receiver,
+            complete: false,
+            interest: false,
+            session,
+        }
+    }
+
+    /// Stops handing over the responses and completes the get. The peer is told to drop a
+    /// liveliness get, but there is no message to cancel a query in the protocol: the replies
+    /// still on their way are dropped.
     pub fn cancel(self) {
-        todo!()
+        // A get that is already over has nothing left to cancel, so that only the pending ones
+        // take room in `CancelledGets`.
+        if let Ok(mut state) = self.session.state.try_lock()
+            && state.get_callbacks.get(self.rid).is_none()
+        {
+            return;
+        }
+
+        if self
+            .session
+            .cancelled_gets
+            .borrow_mut()
+            .push((self.rid, self.interest))
+            .is_err()
+        {
+            zenoh_proto::warn!(
+                "{}: Too many cancelled gets, this one lasts until its timeout",
+                zenoh_proto::zctx!()
+            );
+            return;
+        }
+
+        self.session.pending.notify();
     }
 
     pub fn keyexpr(&self) -> &keyexpr {
@@ -52,20 +109,65 @@ impl<'res, OwnedResponse, const CHANNEL: bool> GetResponses<'res, OwnedResponse,
     }
 }
 
-impl<'res, OwnedResponse> GetResponses<'res, OwnedResponse, true> {
+impl<'a, 'res, Config, OwnedResponse> GetResponses<'a, 'res, Config, OwnedResponse, true>
+where
+    Config: ZSessionConfig,
+{
     pub fn try_recv(&self) -> Option<OwnedResponse> {
         self.receiver.as_ref().unwrap().try_receive().ok()
     }
 
-    pub async fn recv(&self) -> Option<OwnedResponse> {
-        match select(
-            Timer::at(self.timedout),
-            self.receiver.as_ref().unwrap().receive(),
-        )
-        .await
-        {
-            Either::First(_) => None,
-            Either::Second(v) => Some(v),
+    /// The next response, or `None` once the get is complete and all its responses have been
+    /// received, or has timed out.
+    pub async fn recv(&mut self) -> Option<OwnedResponse> {
+        let receiver = self.receiver.as_ref().unwrap();
+
+        loop {
+            if let Ok(response) = receiver.try_receive() {
+                return Some(response);
+            }
+
+            if self.complete {
+                return None;
+            }
+
+            // The get is complete once its callback is gone, which is checked under the state
+            // lock after reading the epoch, so that no completion is missed.
+            let epoch = self.session.completed_gets.epoch();
+            if self
+                .session
+                .state()
+                .await
+                .get_callbacks
+                .get(self.rid)
+                .is_none()
+            {
+                self.complete = true;
+                continue;
+            }
+
+            match select3(
+                Timer::at(self.timedout),
+                receiver.receive(),
+                self.session.completed_gets.changed(epoch),
+            )
+            .await
+            {
+                Either3::First(_) => return None,
+                Either3::Second(response) => return Some(response),
+                Either3::Third(()) => {}
+            }
+        }
+    }
+}
+
+/// Calls the `on_complete` of a get when its callback is dropped, i.e. when the get is over.
+struct Completion<F: FnOnce()>(Option<F>);
+
+impl<F: FnOnce()> Drop for Completion<F> {
+    fn drop(&mut self) {
+        if let Some(on_complete) = self.0.take() {
+            on_complete();
         }
     }
 }
@@ -83,14 +185,22 @@ pub struct GetBuilder<
     OwnedResponse = (),
     const READY: bool = false,
     const CHANNEL: bool = false,
+    OnComplete = fn(),
 > where
     Config: ZSessionConfig,
 {
     pub(crate) session: &'a Session<'res, Config>,
-    pub(crate) ke: &'static keyexpr,
+    pub(crate) ke: &'a keyexpr,
     pub(crate) parameters: Option<&'a str>,
     pub(crate) payload: Option<&'a [u8]>,
     pub(crate) timeout: Option<Duration>,
+    pub(crate) target: QueryTarget,
+    pub(crate) consolidation: ConsolidationMode,
+    pub(crate) budget: Option<u32>,
+    pub(crate) reliability: Reliability,
+    pub(crate) priority: Priority,
+    pub(crate) congestion_control: CongestionControl,
+    pub(crate) express: bool,
     pub(crate) callback: Option<
         DynCallback<
             'res,
@@ -100,21 +210,62 @@ pub struct GetBuilder<
         >,
     >,
     pub(crate) receiver: Option<DynamicReceiver<'res, OwnedResponse>>,
+    pub(crate) on_complete: Option<OnComplete>,
 }
 
 impl<'a, 'res, Config> GetBuilder<'a, 'res, Config, (), false, false>
 where
     Config: ZSessionConfig,
 {
-    pub(crate) fn new(session: &'a Session<'res, Config>, ke: &'static keyexpr) -> Self {
+    pub(crate) fn new(session: &'a Session<'res, Config>, ke: &'a keyexpr) -> Self {
         Self {
             session,
             ke,
             parameters: None,
             payload: None,
             timeout: None,
+            target: QueryTarget::default(),
+            consolidation: ConsolidationMode::None,
+            budget: None,
+            reliability: Reliability::default(),
+            priority: Priority::default(),
+            congestion_control: CongestionControl::Block,
+            express: false,
+            callback: None,
+            receiver: None,
+            on_complete: None,
+        }
+    }
+}
+
+impl<'a, 'res, Config, OnComplete> GetBuilder<'a, 'res, Config, (), false, false, OnComplete>
+where
+    Config: ZSessionConfig,
+    OnComplete: FnOnce() + 'res,
+{
+    /// Called once the get is over: all the responses have been received, or it has been
+    /// cancelled or timed out. Must be set before the callback or the channel, which then need
+    /// more storage.
+    pub fn on_complete<F>(self, on_complete: F) -> GetBuilder<'a, 'res, Config, (), false, false, F>
+    where
+        F: FnOnce() + 'res,
+    {
+        GetBuilder {
+            session: self.session,
+            ke: self.ke,
+            parameters: self.parameters,
+            payload: self.payload,
+            timeout: self.timeout,
+            target: self.target,
+            consolidation: self.consolidation,
+            budget: self.budget,
+            reliability: self.reliability,
+            priority: self.priority,
+            congestion_control: self.congestion_control,
+            express: self.express,
             callback: None,
             receiver: None,
+            on_complete: Some(on_complete),
         }
     }
 
@@ -128,8 +279,27 @@ where
             parameters: self.parameters,
             payload: self.payload,
             timeout: self.timeout,
-            callback: Some(DynObject::new(AsyncCallback::new(callback))),
+            target: self.target,
+            consolidation: self.consolidation,
+            budget: self.budget,
+            reliability: self.reliability,
+            priority: self.priority,
+            congestion_control: self.congestion_control,
+            express: self.express,
+            callback: Some(match self.on_complete {
+                Some(on_complete) => {
+                    let completion = Completion(Some(on_complete));
+                    DynObject::new(AsyncCallback::new(
+                        async move |resp: &'_ GetResponse<'_>| {
+                            let _completion = &completion;
+                            callback(resp).await
+                        },
+                    ))
+                }
+                None => DynObject::new(AsyncCallback::new(callback)),
+            }),
             receiver: None,
+            on_complete: None,
         }
     }
 
@@ -143,8 +313,25 @@ where
             parameters: self.parameters,
             payload: self.payload,
             timeout: self.timeout,
-            callback: Some(DynObject::new(SyncCallback::new(callback))),
+            target: self.target,
+            consolidation: self.consolidation,
+            budget: self.budget,
+            reliability: self.reliability,
+            priority: self.priority,
+            congestion_control: self.congestion_control,
+            express: self.express,
+            callback: Some(match self.on_complete {
+                Some(on_complete) => {
+                    let completion = Completion(Some(on_complete));
+                    DynObject::new(SyncCallback::new(move |resp: &'_ GetResponse<'_>| {
+                        let _completion = &completion;
+                        callback(resp)
+                    }))
+                }
+                None => DynObject::new(SyncCallback::new(callback)),
+            }),
             receiver: None,
+            on_complete: None,
         }
     }
 
@@ -156,41 +343,62 @@ where
     where
         OwnedResponse: for<'any> TryFrom<&'any GetResponse<'any>, Error = E>,
     {
+        let send = async move |resp: &'_ GetResponse<'_>| {
+            if let Ok(resp) = OwnedResponse::try_from(resp) {
+                sender.send(resp).await;
+            } else {
+                zenoh_proto::error!(
+                    "{}: Couldn't convert to a transferable response",
+                    zenoh_proto::zctx!()
+                )
+            }
+        };
+
         GetBuilder {
             session: self.session,
             ke: self.ke,
             parameters: self.parameters,
             payload: self.payload,
             timeout: self.timeout,
-            callback: Some(DynObject::new(AsyncCallback::new(
-                async move |resp: &'_ GetResponse<'_>| {
-                    if let Ok(resp) = OwnedResponse::try_from(resp) {
-                        sender.send(resp).await;
-                    } else {
-                        zenoh_proto::error!(
-                            "{}: Couldn't convert to a transferable response",
-                            zenoh_proto::zctx!()
-                        )
-                    }
-                },
-            ))),
+            target: self.target,
+            consolidation: self.consolidation,
+            budget: self.budget,
+            reliability: self.reliability,
+            priority: self.priority,
+            congestion_control: self.congestion_control,
+            express: self.express,
+            callback: Some(match self.on_complete {
+                Some(on_complete) => {
+                    let completion = Completion(Some(on_complete));
+                    DynObject::new(AsyncCallback::new(
+                        async move |resp: &'_ GetResponse<'_>| {
+                            let _completion = &completion;
+                            send(resp).await
+                        },
+                    ))
+                }
+                None => DynObject::new(AsyncCallback::new(send)),
+            }),
             receiver: Some(receiver),
+            on_complete: None,
         }
     }
 }
 
-impl<'a, 'res, Config, OwnedResponse, const READY: bool, const CHANNEL: bool>
-    GetBuilder<'a, 'res, Config, OwnedResponse, READY, CHANNEL>
+impl<'a, 'res, Config, OwnedResponse, const READY: bool, const CHANNEL: bool, OnComplete>
+    GetBuilder<'a, 'res, Config, OwnedResponse, READY, CHANNEL, OnComplete>
 where
     Config: ZSessionConfig,
 {
-    pub fn keyexpr(mut self, ke: &'static keyexpr) -> Self {
+    pub fn keyexpr(mut self, ke: &'a keyexpr) -> Self {
         self.ke = ke;
         self
     }
 
-    pub fn parameters(mut self, parameters: &'a str) -> Self {
-        self.parameters = Some(parameters);
+    /// Either a raw `key=value;...` string or [`Parameters`], such as the ones written by a
+    /// [`ParametersBuilder`](zenoh_proto::ParametersBuilder).
+    pub fn parameters(mut self, parameters: impl Into<Parameters<'a>>) -> Self {
+        self.parameters = Some(parameters.into().as_str());
         self
     }
 
@@ -199,10 +407,49 @@ where
         self
     }
 
+    /// How long the queryables have to reply, also sent along with the query.
     pub fn timeout(mut self, timeout: Duration) -> Self {
         self.timeout = Some(timeout);
         self
     }
+
+    pub fn target(mut self, target: QueryTarget) -> Self {
+        self.target = target;
+        self
+    }
+
+    /// `Monotonic` and `Latest` are also applied to the replies received, keeping one reply per
+    /// key expression based on their timestamp. `Auto` is applied as `Latest`.
+    pub fn consolidation(mut self, consolidation: ConsolidationMode) -> Self {
+        self.consolidation = consolidation;
+        self
+    }
+
+    /// The maximum number of replies the queryables may send.
+    pub fn budget(mut self, budget: u32) -> Self {
+        self.budget = Some(budget);
+        self
+    }
+
+    pub fn reliability(mut self, reliability: Reliability) -> Self {
+        self.reliability = reliability;
+        self
+    }
+
+    pub fn priority(mut self, priority: Priority) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    pub fn congestion_control(mut self, congestion_control: CongestionControl) -> Self {
+        self.congestion_control = congestion_control;
+        self
+    }
+
+    pub fn express(mut self, express: bool) -> Self {
+        self.express = express;
+        self
+    }
 }
 
 impl<'a, 'res, Config, OwnedResponse, const CHANNEL: bool>
@@ -212,7 +459,8 @@ where
 {
     pub async fn finish(
         self,
-    ) -> core::result::Result<GetResponses<'res, OwnedResponse, CHANNEL>, SessionError> {
+    ) -> core::result::Result<GetResponses<'a, 'res, Config, OwnedResponse, CHANNEL>, SessionError>
+    {
         let timedout = Instant::now()
             + self
                 .timeout
@@ -221,20 +469,34 @@ where
                 .unwrap();
 
         let mut state = self.session.state().await;
+        let state = &mut *state;
         let rid = state.next();
 
         if let Some(callback) = self.callback {
             state.get_callbacks.drop_timedout();
+            state
+                .consolidations
+                .retain(|pending| state.get_callbacks.get(pending).is_some());
             state
                 .get_callbacks
                 .insert(rid, self.ke, Some(timedout), callback)?;
+
+            if let Some(mode) = consolidation::resolve(self.consolidation)
+                && let Err(e) = state.consolidations.insert(rid, mode)
+            {
+                let _ = state.get_callbacks.remove(rid);
+                return Err(e.into());
+            }
         }
 
+        let responses = GetResponses::new(self.session, rid, self.ke, timedout, self.receiver);
+
+        let qos = QoS::new(self.priority, self.congestion_control, self.express);
         let msg = Request {
             id: rid,
-            wire_expr: WireExpr::from(self.ke),
+            wire_expr: self.session.wire_expr(self.ke).await,
             payload: RequestBody::Query(Query {
-                consolidation: ConsolidationMode::None,
+                consolidation: self.consolidation,
                 parameters: self.parameters.unwrap_or_default(),
                 body: self.payload.map(|p| Value {
                     payload: p,
@@ -242,25 +504,33 @@ where
                 }),
                 ..Default::default()
             }),
+            qos,
+            target: self.target,
+            budget: self.budget.map(|budget| Budget { budget }),
+            timeout: self.timeout,
             ..Default::default()
         };
 
-        self.session
-            .driver
-            .tx()
-            .await
-            .send(core::iter::once(NetworkMessage {
-                reliability: Reliability::default(),
-                qos: QoS::default(),
+        let sent = self
+            .session
+            .send(NetworkMessage {
+                reliability: self.reliability,
+                qos,
                 body: NetworkBody::Request(msg),
-            }))
+            })
             .await?;
 
-        Ok(GetResponses {
-            ke: self.ke,
-            timedout,
-            receiver: self.receiver,
-        })
+        // A dropped query has no reply to wait for, it completes right away.
+        if !sent {
+            let mut state = self.session.state().await;
+            let _ = state.consolidations.remove(rid);
+            let _ = state.get_callbacks.remove(rid);
+            drop(state);
+
+            self.session.complete_gets();
+        }
+
+        Ok(responses)
     }
 }
 
@@ -268,7 +538,44 @@ impl<'res, Config> Session<'res, Config>
 where
     Config: ZSessionConfig,
 {
-    pub fn get(&self, ke: &'static keyexpr) -> GetBuilder<'_, 'res, Config> {
+    pub fn get<'a>(&'a self, ke: &'a keyexpr) -> GetBuilder<'a, 'res, Config> {
         GetBuilder::new(self, ke)
     }
+
+    /// Wakes up the channel receivers of the gets, to check whether theirs is complete.
+    pub(crate) fn complete_gets(&self) {
+        self.completed_gets.notify();
+    }
+
+    /// Drops the gets cancelled with [`GetResponses::cancel`], calling their `on_complete`.
+    pub(crate) async fn cancel_gets(&self) -> core::convert::Infallible {
+        loop {
+            let epoch = self.pending.epoch();
+
+            loop {
+                let cancelled = self.cancelled_gets.borrow_mut().pop();
+                let Some((rid, interest)) = cancelled else {
+                    break;
+                };
+
+                let mut state = self.state().await;
+                let _ = state.consolidations.remove(rid);
+                let _ = state.get_callbacks.remove(rid);
+                drop(state);
+
+                self.complete_gets();
+
+                if interest && let Err(e) = self.send_interest_final(rid).await {
+                    zenoh_proto::warn!(
+                        "{}: Couldn't cancel the interest {} ({})",
+                        zenoh_proto::zctx!(),
+                        rid,
+                        e
+                    );
+                }
+            }
+
+            self.pending.changed(epoch).await;
+        }
+    }
 }
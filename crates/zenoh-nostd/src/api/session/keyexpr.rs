@@ -0,0 +1,126 @@
+use zenoh_proto::{
+    CollectionError, SessionError,
+    exts::QoS,
+    fields::{Mapping, Reliability, WireExpr},
+    keyexpr,
+    msgs::{Declare, DeclareBody, DeclareKeyExpr, NetworkBody, NetworkMessage, UndeclareKeyExpr},
+};
+
+use crate::{
+    api::{keyexprs::ZKeyExprs, session::Session},
+    config::ZSessionConfig,
+};
+
+impl<'res, Config> Session<'res, Config>
+where
+    Config: ZSessionConfig,
+{
+    /// Declares `ke` to the peer so that the messages sent on `ke`, or on any key expression
+    /// starting with `ke`, only carry its numeric id.
+    pub async fn declare_keyexpr(
+        &self,
+        ke: &'res keyexpr,
+    ) -> core::result::Result<(), SessionError> {
+        let mut keyexprs = self.keyexprs.lock().await;
+        if keyexprs.id(ke).is_some() {
+            return Ok(());
+        }
+
+        let id = keyexprs.declare(Some(ke))?;
+        if let Err(e) = self.send_declare_keyexpr(id, ke).await {
+            let _ = keyexprs.undeclare(id);
+            return Err(e);
+        }
+
+        Ok(())
+    }
+
+    pub async fn undeclare_keyexpr(&self, ke: &keyexpr) -> core::result::Result<(), SessionError> {
+        let mut keyexprs = self.keyexprs.lock().await;
+        let id = keyexprs.id(ke).ok_or(CollectionError::KeyNotFound)?;
+
+        keyexprs.undeclare(id)?;
+        self.send_undeclare_keyexpr(id).await
+    }
+
+    /// Declares `ke` under an id that is not used to shorten other key expressions. Returns
+    /// `None` if the mapping table is full, in which case `ke` should be sent as is.
+    pub(crate) async fn declare_keyexpr_id(&self, ke: &keyexpr) -> Option<u16> {
+        let mut keyexprs = self.keyexprs.lock().await;
+        let id = match keyexprs.declare(None) {
+            Ok(id) => id,
+            Err(_) => {
+                zenoh_proto::debug!("{}: Keyexpr table is full", zenoh_proto::zctx!());
+                return None;
+            }
+        };
+
+        match self.send_declare_keyexpr(id, ke).await {
+            Ok(()) => Some(id),
+            Err(_) => {
+                let _ = keyexprs.undeclare(id);
+                None
+            }
+        }
+    }
+
+    /// Undeclares an id given by [`Session::declare_keyexpr_id`].
+    pub(crate) async fn undeclare_keyexpr_id(
+        &self,
+        id: u16,
+    ) -> core::result::Result<(), SessionError> {
+        self.keyexprs.lock().await.undeclare(id)?;
+        self.send_undeclare_keyexpr(id).await
+    }
+
+    /// The key expression `wire_expr` is scoped by, if it is one of this session's declarations.
+    pub(crate) async fn local_keyexpr(&self, wire_expr: &WireExpr<'_>) -> Option<&'res keyexpr> {
+        match wire_expr.mapping {
+            Mapping::Receiver if wire_expr.scope != 0 => {
+                self.keyexprs.lock().await.get(wire_expr.scope)
+            }
+            _ => None,
+        }
+    }
+
+    pub(crate) async fn wire_expr<'a>(&self, ke: &'a keyexpr) -> WireExpr<'a> {
+        self.keyexprs.lock().await.wire_expr(ke)
+    }
+
+    pub(crate) async fn send_declare_keyexpr(
+        &self,
+        id: u16,
+        ke: &keyexpr,
+    ) -> core::result::Result<(), SessionError> {
+        self.send(NetworkMessage {
+            reliability: Reliability::Reliable,
+            qos: QoS::declare(),
+            body: NetworkBody::Declare(Declare {
+                body: DeclareBody::DeclareKeyExpr(DeclareKeyExpr {
+                    id,
+                    wire_expr: WireExpr::from(ke),
+                }),
+                qos: QoS::declare(),
+                ..Default::default()
+            }),
+        })
+        .await?;
+
+        Ok(())
+    }
+
+    async fn send_undeclare_keyexpr(&self, id: u16) -> core::result::Result<(), SessionError> {
+        self.send(NetworkMessage {
+            reliability: Reliability::Reliable,
+            qos: QoS::declare(),
+            body: NetworkBody::Declare(Declare {
+                body: DeclareBody::UndeclareKeyExpr(UndeclareKeyExpr { id }),
+                qos: QoS::declare(),
+                ..Default::default()
+            }),
+        })
+        .await?;
+
+        Ok(())
+    }
+}
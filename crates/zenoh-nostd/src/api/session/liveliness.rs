@@ -0,0 +1,525 @@
+use core::{mem::ManuallyDrop, time::Duration};
+
+use dyn_utils::DynObject;
+use embassy_sync::channel::{DynamicReceiver, DynamicSender};
+use embassy_time::Instant;
+use zenoh_proto::{
+    SessionError, keyexpr,
+    msgs::{DeclareBody, DeclareToken, InterestMode, InterestOptions, UndeclareToken},
+};
+
+use crate::{
+    api::{
+        arg::{GetResponseRef, SampleRef},
+        callbacks::{AsyncCallback, DynCallback, SyncCallback, ZCallbacks},
+        keyexprs::ZKeyExprs,
+        pending::ZPending,
+        sample::Sample,
+        session::{Session, get::GetResponses},
+    },
+    config::ZSessionConfig,
+    session::GetResponse,
+};
+
+/// Interest options asking for the tokens on a key expression.
+pub(crate) const TOKENS: u8 = InterestOptions::KEYEXPRS.options | InterestOptions::TOKENS.options;
+
+/// Entry point of the liveliness API, see [`Session::liveliness`].
+pub struct Liveliness<'a, 'res, Config>
+where
+    Config: ZSessionConfig,
+{
+    session: &'a Session<'res, Config>,
+}
+
+impl<'a, 'res, Config> Liveliness<'a, 'res, Config>
+where
+    Config: ZSessionConfig,
+{
+    /// Declares a token on `ke`, which stays alive for the other nodes until it is undeclared,
+    /// dropped, or the session is closed.
+    pub async fn declare_token(
+        &self,
+        ke: &'res keyexpr,
+    ) -> core::result::Result<LivelinessToken<'a, 'res, Config>, SessionError> {
+        let id = self.session.state().await.tokens.declare(Some(ke))?;
+
+        if let Err(e) = self.session.send_declare_token(id, ke).await {
+            let _ = self.session.state().await.tokens.undeclare(id);
+            return Err(e);
+        }
+
+        Ok(LivelinessToken {
+            id,
+            ke,
+            session: self.session,
+        })
+    }
+
+    /// Subscribes to the tokens matching `ke`: a put sample is received when one is declared,
+    /// and a delete sample when it is undeclared.
+    pub fn declare_subscriber(
+        &self,
+        ke: &'a keyexpr,
+    ) -> LivelinessSubscriberBuilder<'a, 'res, Config> {
+        LivelinessSubscriberBuilder::new(self.session, ke)
+    }
+
+    /// Gets the tokens currently alive on `ke`, each one as a put sample.
+    pub fn get(&self, ke: &'a keyexpr) -> LivelinessGetBuilder<'a, 'res, Config> {
+        LivelinessGetBuilder::new(self.session, ke)
+    }
+}
+
+/// A token declared with [`Liveliness::declare_token`]. Dropping it undeclares it as soon as
+/// `Session::run` gets to it, `undeclare` does it right away.
+pub struct LivelinessToken<'a, 'res, Config>
+where
+    Config: ZSessionConfig,
+{
+    id: u16,
+    ke: &'res keyexpr,
+    session: &'a Session<'res, Config>,
+}
+
+impl<'a, 'res, Config> LivelinessToken<'a, 'res, Config>
+where
+    Config: ZSessionConfig,
+{
+    pub async fn undeclare(self) -> core::result::Result<(), SessionError> {
+        let token = ManuallyDrop::new(self);
+        token.session.undeclare_token(token.id).await
+    }
+
+    pub fn keyexpr(&self) -> &keyexpr {
+        self.ke
+    }
+}
+
+impl<'a, 'res, Config> Drop for LivelinessToken<'a, 'res, Config>
+where
+    Config: ZSessionConfig,
+{
+    fn drop(&mut self) {
+        if self
+            .session
+            .dropped_tokens
+            .borrow_mut()
+            .push(self.id)
+            .is_err()
+        {
+            zenoh_proto::warn!(
+                "{}: Too many dropped tokens, {} stays declared",
+                zenoh_proto::zctx!(),
+                self.ke
+            );
+            return;
+        }
+
+        self.session.pending.notify();
+    }
+}
+
+pub struct LivelinessSubscriber<'a, 'res, Config, OwnedSample = (), const CHANNEL: bool = false>
+where
+    Config: ZSessionConfig,
+{
+    id: u32,
+    ke: &'a keyexpr,
+    session: &'a Session<'res, Config>,
+    receiver: Option<DynamicReceiver<'res, OwnedSample>>,
+}
+
+impl<'a, 'res, Config, OwnedSample, const CHANNEL: bool>
+    LivelinessSubscriber<'a, 'res, Config, OwnedSample, CHANNEL>
+where
+    Config: ZSessionConfig,
+{
+    /// Undeclares the subscriber and frees its callback slot. Samples still pending in the
+    /// channel, if any, are discarded.
+    pub async fn undeclare(self) -> core::result::Result<(), SessionError> {
+        self.session
+            .state()
+            .await
+            .liveliness_callbacks
+            .remove(self.id)?;
+
+        if let Some(receiver) = &self.receiver {
+            while receiver.try_receive().is_ok() {}
+        }
+
+        self.session.send_interest_final(self.id).await
+    }
+
+    pub fn keyexpr(&self) -> &keyexpr {
+        self.ke
+    }
+}
+
+impl<'a, 'res, Config, OwnedSample> LivelinessSubscriber<'a, 'res, Config, OwnedSample, true>
+where
+    Config: ZSessionConfig,
+{
+    pub fn try_recv(&self) -> Option<OwnedSample> {
+        self.receiver.as_ref().unwrap().try_receive().ok()
+    }
+
+    /// The next sample, or `None` once the session is closed and the channel is empty.
+    pub async fn recv(&self) -> Option<OwnedSample> {
+        self.session.recv(self.receiver.as_ref().unwrap()).await
+    }
+}
+
+type SubCallbackStorage<'res, Config> =
+    <<Config as ZSessionConfig>::LivelinessCallbacks<'res> as ZCallbacks<'res, SampleRef>>::Callback;
+
+type SubFutureStorage<'res, Config> =
+    <<Config as ZSessionConfig>::LivelinessCallbacks<'res> as ZCallbacks<'res, SampleRef>>::Future;
+
+pub struct LivelinessSubscriberBuilder<
+    'a,
+    'res,
+    Config,
+    OwnedSample = (),
+    const READY: bool = false,
+    const CHANNEL: bool = false,
+> where
+    Config: ZSessionConfig,
+{
+    session: &'a Session<'res, Config>,
+    ke: &'a keyexpr,
+    history: bool,
+    callback: Option<
+        DynCallback<
+            'res,
+            SubCallbackStorage<'res, Config>,
+            SubFutureStorage<'res, Config>,
+            SampleRef,
+        >,
+    >,
+    receiver: Option<DynamicReceiver<'res, OwnedSample>>,
+}
+
+impl<'a, 'res, Config> LivelinessSubscriberBuilder<'a, 'res, Config, (), false, false>
+where
+    Config: ZSessionConfig,
+{
+    pub(crate) fn new(session: &'a Session<'res, Config>, ke: &'a keyexpr) -> Self {
+        Self {
+            session,
+            ke,
+            history: false,
+            callback: None,
+            receiver: None,
+        }
+    }
+
+    pub fn callback(
+        self,
+        callback: impl AsyncFnMut(&Sample<'_>) + 'res,
+    ) -> LivelinessSubscriberBuilder<'a, 'res, Config, (), true, false> {
+        LivelinessSubscriberBuilder {
+            session: self.session,
+            ke: self.ke,
+            history: self.history,
+            callback: Some(DynObject::new(AsyncCallback::new(callback))),
+            receiver: None,
+        }
+    }
+
+    pub fn callback_sync(
+        self,
+        callback: impl FnMut(&Sample<'_>) + 'res,
+    ) -> LivelinessSubscriberBuilder<'a, 'res, Config, (), true, false> {
+        LivelinessSubscriberBuilder {
+            session: self.session,
+            ke: self.ke,
+            history: self.history,
+            callback: Some(DynObject::new(SyncCallback::new(callback))),
+            receiver: None,
+        }
+    }
+
+    pub fn channel<OwnedSample, E>(
+        self,
+        sender: DynamicSender<'res, OwnedSample>,
+        receiver: DynamicReceiver<'res, OwnedSample>,
+    ) -> LivelinessSubscriberBuilder<'a, 'res, Config, OwnedSample, true, true>
+    where
+        OwnedSample: for<'any> TryFrom<&'any Sample<'any>, Error = E>,
+    {
+        LivelinessSubscriberBuilder {
+            session: self.session,
+            ke: self.ke,
+            history: self.history,
+            callback: Some(DynObject::new(AsyncCallback::new(
+                async move |resp: &'_ Sample<'_>| {
+                    if let Ok(resp) = OwnedSample::try_from(resp) {
+                        sender.send(resp).await;
+                    } else {
+                        zenoh_proto::error!(
+                            "{}: Couldn't convert to a transferable sample",
+                            zenoh_proto::zctx!()
+                        )
+                    }
+                },
+            ))),
+            receiver: Some(receiver),
+        }
+    }
+}
+
+impl<'a, 'res, Config, OwnedSample, const READY: bool, const CHANNEL: bool>
+    LivelinessSubscriberBuilder<'a, 'res, Config, OwnedSample, READY, CHANNEL>
+where
+    Config: ZSessionConfig,
+{
+    /// Also receives the tokens that are already alive when the subscriber is declared.
+    pub fn history(mut self, history: bool) -> Self {
+        self.history = history;
+        self
+    }
+}
+
+impl<'a, 'res, Config, OwnedSample, const CHANNEL: bool>
+    LivelinessSubscriberBuilder<'a, 'res, Config, OwnedSample, true, CHANNEL>
+where
+    Config: ZSessionConfig,
+{
+    pub async fn finish(
+        self,
+    ) -> core::result::Result<
+        LivelinessSubscriber<'a, 'res, Config, OwnedSample, CHANNEL>,
+        SessionError,
+    > {
+        let mut state = self.session.state().await;
+        let id = state.next();
+
+        if let Some(callback) = self.callback {
+            state
+                .liveliness_callbacks
+                .insert(id, self.ke, None, callback)?;
+        }
+
+        let mode = if self.history {
+            InterestMode::CurrentFuture
+        } else {
+            InterestMode::Future
+        };
+
+        self.session
+            .send_interest(id, mode, TOKENS, self.ke)
+            .await?;
+
+        Ok(LivelinessSubscriber {
+            ke: self.ke,
+            id,
+            session: self.session,
+            receiver: self.receiver,
+        })
+    }
+}
+
+type GetCallbackStorage<'res, Config> =
+    <<Config as ZSessionConfig>::GetCallbacks<'res> as ZCallbacks<'res, GetResponseRef>>::Callback;
+
+type GetFutureStorage<'res, Config> =
+    <<Config as ZSessionConfig>::GetCallbacks<'res> as ZCallbacks<'res, GetResponseRef>>::Future;
+
+pub struct LivelinessGetBuilder<
+    'a,
+    'res,
+    Config,
+    OwnedResponse = (),
+    const READY: bool = false,
+    const CHANNEL: bool = false,
+> where
+    Config: ZSessionConfig,
+{
+    session: &'a Session<'res, Config>,
+    ke: &'a keyexpr,
+    timeout: Option<Duration>,
+    callback: Option<
+        DynCallback<
+            'res,
+            GetCallbackStorage<'res, Config>,
+            GetFutureStorage<'res, Config>,
+            GetResponseRef,
+        >,
+    >,
+    receiver: Option<DynamicReceiver<'res, OwnedResponse>>,
+}
+
+impl<'a, 'res, Config> LivelinessGetBuilder<'a, 'res, Config, (), false, false>
+where
+    Config: ZSessionConfig,
+{
+    pub(crate) fn new(session: &'a Session<'res, Config>, ke: &'a keyexpr) -> Self {
+        Self {
+            session,
+            ke,
+            timeout: None,
+            callback: None,
+            receiver: None,
+        }
+    }
+
+    pub fn callback(
+        self,
+        callback: impl AsyncFnMut(&GetResponse<'_>) + 'res,
+    ) -> LivelinessGetBuilder<'a, 'res, Config, (), true> {
+        LivelinessGetBuilder {
+            session: self.session,
+            ke: self.ke,
+            timeout: self.timeout,
+            callback: Some(DynObject::new(AsyncCallback::new(callback))),
+            receiver: None,
+        }
+    }
+
+    pub fn callback_sync(
+        self,
+        callback: impl FnMut(&GetResponse<'_>) + 'res,
+    ) -> LivelinessGetBuilder<'a, 'res, Config, (), true> {
+        LivelinessGetBuilder {
+            session: self.session,
+            ke: self.ke,
+            timeout: self.timeout,
+            callback: Some(DynObject::new(SyncCallback::new(callback))),
+            receiver: None,
+        }
+    }
+
+    pub fn channel<OwnedResponse, E>(
+        self,
+        sender: DynamicSender<'res, OwnedResponse>,
+        receiver: DynamicReceiver<'res, OwnedResponse>,
+    ) -> LivelinessGetBuilder<'a, 'res, Config, OwnedResponse, true, true>
+    where
+        OwnedResponse: for<'any> TryFrom<&'any GetResponse<'any>, Error = E>,
+    {
+        LivelinessGetBuilder {
+            session: self.session,
+            ke: self.ke,
+            timeout: self.timeout,
+            callback: Some(DynObject::new(AsyncCallback::new(
+                async move |resp: &'_ GetResponse<'_>| {
+                    if let Ok(resp) = OwnedResponse::try_from(resp) {
+                        sender.send(resp).await;
+                    } else {
+                        zenoh_proto::error!(
+                            "{}: Couldn't convert to a transferable response",
+                            zenoh_proto::zctx!()
+                        )
+                    }
+                },
+            ))),
+            receiver: Some(receiver),
+        }
+    }
+}
+
+impl<'a, 'res, Config, OwnedResponse, const READY: bool, const CHANNEL: bool>
+    LivelinessGetBuilder<'a, 'res, Config, OwnedResponse, READY, CHANNEL>
+where
+    Config: ZSessionConfig,
+{
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+}
+
+impl<'a, 'res, Config, OwnedResponse, const CHANNEL: bool>
+    LivelinessGetBuilder<'a, 'res, Config, OwnedResponse, true, CHANNEL>
+where
+    Config: ZSessionConfig,
+{
+    pub async fn finish(
+        self,
+    ) -> core::result::Result<GetResponses<'a, 'res, Config, OwnedResponse, CHANNEL>, SessionError>
+    {
+        let timedout = Instant::now()
+            + self
+                .timeout
+                .unwrap_or(Duration::from_secs(10))
+                .try_into()
+                .unwrap();
+
+        let mut state = self.session.state().await;
+        let id = state.next();
+
+        if let Some(callback) = self.callback {
+            state.get_callbacks.drop_timedout();
+            state
+                .get_callbacks
+                .insert(id, self.ke, Some(timedout), callback)?;
+        }
+
+        let mut responses = GetResponses::new(self.session, id, self.ke, timedout, self.receiver);
+        responses.interest = true;
+
+        self.session
+            .send_interest(id, InterestMode::Current, TOKENS, self.ke)
+            .await?;
+
+        Ok(responses)
+    }
+}
+
+impl<'res, Config> Session<'res, Config>
+where
+    Config: ZSessionConfig,
+{
+    pub fn liveliness(&self) -> Liveliness<'_, 'res, Config> {
+        Liveliness { session: self }
+    }
+
+    pub(crate) async fn send_declare_token(
+        &self,
+        id: u16,
+        ke: &keyexpr,
+    ) -> core::result::Result<(), SessionError> {
+        self.send_declare(DeclareBody::DeclareToken(DeclareToken {
+            id: id as u32,
+            wire_expr: self.wire_expr(ke).await,
+        }))
+        .await
+    }
+
+    pub(crate) async fn undeclare_token(&self, id: u16) -> core::result::Result<(), SessionError> {
+        self.state().await.tokens.undeclare(id)?;
+
+        self.send_declare(DeclareBody::UndeclareToken(UndeclareToken {
+            id: id as u32,
+            ..Default::default()
+        }))
+        .await
+    }
+
+    /// Undeclares the tokens that have been dropped, for as long as the returned future is
+    /// polled.
+    pub(crate) async fn undeclare_dropped_tokens(&self) -> core::convert::Infallible {
+        loop {
+            let epoch = self.pending.epoch();
+
+            loop {
+                let dropped = self.dropped_tokens.borrow_mut().pop();
+                let Some(id) = dropped else {
+                    break;
+                };
+
+                if let Err(e) = self.undeclare_token(id).await {
+                    zenoh_proto::warn!(
+                        "{}: Couldn't undeclare dropped token {} ({})",
+                        zenoh_proto::zctx!(),
+                        id,
+                        e
+                    );
+                }
+            }
+
+            self.pending.changed(epoch).await;
+        }
+    }
+}
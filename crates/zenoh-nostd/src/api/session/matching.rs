@@ -0,0 +1,91 @@
+use embassy_sync::channel::DynamicSender;
+use zenoh_proto::{
+    CollectionError, SessionError, keyexpr,
+    msgs::{InterestMode, InterestOptions},
+};
+
+use crate::{
+    api::{
+        matching::{Matching, MatchingStatus, ZMatchings},
+        session::Session,
+    },
+    config::ZSessionConfig,
+};
+
+/// Interest options asking for the subscribers on a key expression.
+pub(crate) const SUBSCRIBERS: u8 =
+    InterestOptions::KEYEXPRS.options | InterestOptions::SUBSCRIBERS.options;
+
+/// Interest options asking for the queryables on a key expression.
+pub(crate) const QUERYABLES: u8 =
+    InterestOptions::KEYEXPRS.options | InterestOptions::QUERYABLES.options;
+
+impl<'res, Config> Session<'res, Config>
+where
+    Config: ZSessionConfig,
+{
+    /// Starts tracking the subscribers, or the queryables, matching `ke`. Returns the id of
+    /// the interest declared to the peer.
+    pub(crate) async fn declare_matching(
+        &self,
+        ke: &keyexpr,
+        querier: bool,
+    ) -> core::result::Result<u32, SessionError> {
+        let mut state = self.state().await;
+        let id = state.next();
+
+        state.matchings.insert(id, ke, Matching::new(querier))?;
+        if let Err(e) = self
+            .send_interest(id, InterestMode::CurrentFuture, options(querier), ke)
+            .await
+        {
+            let _ = state.matchings.remove(id);
+            return Err(e);
+        }
+
+        Ok(id)
+    }
+
+    pub(crate) async fn undeclare_matching(
+        &self,
+        id: u32,
+    ) -> core::result::Result<(), SessionError> {
+        self.state().await.matchings.remove(id)?;
+        self.send_interest_final(id).await
+    }
+
+    pub(crate) async fn matching_status(
+        &self,
+        id: u32,
+    ) -> core::result::Result<MatchingStatus, SessionError> {
+        let mut state = self.state().await;
+        let matching = state
+            .matchings
+            .get(id)
+            .ok_or(CollectionError::KeyNotFound)?;
+
+        Ok(matching.status())
+    }
+
+    pub(crate) async fn matching_listener(
+        &self,
+        id: u32,
+        sender: DynamicSender<'res, MatchingStatus>,
+    ) -> core::result::Result<(), SessionError> {
+        let mut state = self.state().await;
+        let matching = state
+            .matchings
+            .get(id)
+            .ok_or(CollectionError::KeyNotFound)?;
+
+        matching.listen(sender);
+        Ok(())
+    }
+}
+
+pub(crate) fn options(querier: bool) -> u8 {
+    match querier {
+        true => QUERYABLES,
+        false => SUBSCRIBERS,
+    }
+}
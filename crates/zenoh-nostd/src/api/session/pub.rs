@@ -1,12 +1,16 @@
+use embassy_sync::channel::DynamicSender;
 use zenoh_proto::{
     SessionError,
     exts::Attachment,
-    fields::{Encoding, Timestamp},
+    fields::{CongestionControl, Encoding, Priority, Reliability, Timestamp},
     keyexpr,
 };
 
 use crate::{
-    api::session::{Session, put::PutBuilder},
+    api::{
+        matching::{MatchingStatus, ZMatchings},
+        session::{Session, put::PutBuilder},
+    },
     config::ZSessionConfig,
 };
 
@@ -15,12 +19,20 @@ where
     Config: ZSessionConfig,
 {
     session: &'a Session<'res, Config>,
+    // The id of the interest in the matching subscribers.
+    id: u32,
 
     ke: &'a keyexpr,
+    scope: Option<u16>,
 
     encoding: Encoding<'a>,
     timestamp: Option<Timestamp>,
     attachment: Option<Attachment<'a>>,
+
+    reliability: Reliability,
+    priority: Priority,
+    congestion_control: CongestionControl,
+    express: bool,
 }
 
 impl<'a, 'res, Config> Publisher<'a, 'res, Config>
@@ -31,16 +43,42 @@ where
         PutBuilder {
             session: self.session,
             ke: self.ke,
+            scope: self.scope,
             payload,
             encoding: self.encoding.clone(),
             timestamp: self.timestamp,
             attachment: self.attachment.clone(),
+            reliability: self.reliability,
+            priority: self.priority,
+            congestion_control: self.congestion_control,
+            express: self.express,
         }
     }
 
-    #[allow(dead_code)]
-    async fn undeclare(self) -> core::result::Result<(), SessionError> {
-        todo!("send undeclare interest")
+    /// Whether the peer knows of subscribers matching the publisher.
+    pub async fn matching_status(&self) -> core::result::Result<MatchingStatus, SessionError> {
+        self.session.matching_status(self.id).await
+    }
+
+    /// Sends the matching status to `sender` each time it changes. Statuses are dropped while
+    /// the channel is full.
+    pub async fn matching_listener(
+        &self,
+        sender: DynamicSender<'res, MatchingStatus>,
+    ) -> core::result::Result<(), SessionError> {
+        self.session.matching_listener(self.id, sender).await
+    }
+
+    /// Undeclares the publisher and the key expression id it was given.
+    pub async fn undeclare(self) -> core::result::Result<(), SessionError> {
+        let matching = self.session.undeclare_matching(self.id).await;
+
+        let scope = match self.scope {
+            Some(id) => self.session.undeclare_keyexpr_id(id).await,
+            None => Ok(()),
+        };
+
+        matching.and(scope)
     }
 
     pub fn keyexpr(&self) -> &keyexpr {
@@ -58,6 +96,11 @@ where
     encoding: Encoding<'a>,
     timestamp: Option<Timestamp>,
     attachment: Option<Attachment<'a>>,
+
+    reliability: Reliability,
+    priority: Priority,
+    congestion_control: CongestionControl,
+    express: bool,
 }
 
 impl<'a, 'res, Config> PublisherBuilder<'a, 'res, Config>
@@ -71,6 +114,10 @@ where
             encoding: Encoding::default(),
             timestamp: None,
             attachment: None,
+            reliability: Reliability::default(),
+            priority: Priority::default(),
+            congestion_control: CongestionControl::default(),
+            express: false,
         }
     }
 
@@ -94,14 +141,47 @@ where
         self
     }
 
+    pub fn reliability(mut self, reliability: Reliability) -> Self {
+        self.reliability = reliability;
+        self
+    }
+
+    pub fn priority(mut self, priority: Priority) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    pub fn congestion_control(mut self, congestion_control: CongestionControl) -> Self {
+        self.congestion_control = congestion_control;
+        self
+    }
+
+    pub fn express(mut self, express: bool) -> Self {
+        self.express = express;
+        self
+    }
+
     pub async fn finish(self) -> core::result::Result<Publisher<'a, 'res, Config>, SessionError> {
-        // TODO: send interest msg
+        let id = self.session.declare_matching(self.ke, false).await?;
+        let scope = self.session.declare_keyexpr_id(self.ke).await;
+
+        // The matching outlives the transport, so it keeps the scope for `reconnect`.
+        if let Some(matching) = self.session.state().await.matchings.get(id) {
+            matching.set_scope(scope);
+        }
+
         Ok(Publisher {
             session: self.session,
+            id,
             ke: self.ke,
+            scope,
             encoding: self.encoding,
             timestamp: self.timestamp,
             attachment: self.attachment,
+            reliability: self.reliability,
+            priority: self.priority,
+            congestion_control: self.congestion_control,
+            express: self.express,
         })
     }
 }
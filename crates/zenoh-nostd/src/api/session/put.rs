@@ -1,6 +1,6 @@
 use zenoh_proto::{exts::*, fields::*, msgs::*, *};
 
-use crate::{api::session::Session, config::ZSessionConfig, io::transport::ZTransportLinkTx};
+use crate::{api::session::Session, config::ZSessionConfig};
 
 pub struct PutBuilder<'a, 'res, Config>
 where
@@ -9,11 +9,17 @@ where
     pub(crate) session: &'a Session<'res, Config>,
 
     pub(crate) ke: &'a keyexpr,
+    pub(crate) scope: Option<u16>,
     pub(crate) payload: &'a [u8],
 
     pub(crate) encoding: Encoding<'a>,
     pub(crate) timestamp: Option<Timestamp>,
     pub(crate) attachment: Option<Attachment<'a>>,
+
+    pub(crate) reliability: Reliability,
+    pub(crate) priority: Priority,
+    pub(crate) congestion_control: CongestionControl,
+    pub(crate) express: bool,
 }
 
 impl<'a, 'res, Config> PutBuilder<'a, 'res, Config>
@@ -28,10 +34,15 @@ where
         Self {
             session,
             ke,
+            scope: None,
             payload,
             encoding: Encoding::default(),
             timestamp: None,
             attachment: None,
+            reliability: Reliability::default(),
+            priority: Priority::default(),
+            congestion_control: CongestionControl::default(),
+            express: false,
         }
     }
 
@@ -55,9 +66,41 @@ where
         self
     }
 
-    pub async fn finish(self) -> core::result::Result<(), SessionError> {
+    pub fn reliability(mut self, reliability: Reliability) -> Self {
+        self.reliability = reliability;
+        self
+    }
+
+    pub fn priority(mut self, priority: Priority) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    pub fn congestion_control(mut self, congestion_control: CongestionControl) -> Self {
+        self.congestion_control = congestion_control;
+        self
+    }
+
+    pub fn express(mut self, express: bool) -> Self {
+        self.express = express;
+        self
+    }
+
+    /// Returns `false` if the put has been dropped, under `CongestionControl::Drop` with the TX
+    /// batch full.
+    pub async fn finish(self) -> core::result::Result<bool, SessionError> {
+        let qos = QoS::new(self.priority, self.congestion_control, self.express);
+        let wire_expr = match self.scope {
+            Some(scope) => WireExpr {
+                scope,
+                mapping: Mapping::Sender,
+                suffix: "",
+            },
+            None => self.session.wire_expr(self.ke).await,
+        };
+
         let msg = Push {
-            wire_expr: WireExpr::from(self.ke),
+            wire_expr,
             payload: PushBody::Put(Put {
                 payload: self.payload,
                 encoding: self.encoding,
@@ -66,20 +109,17 @@ where
                 ..Default::default()
             }),
             timestamp: self.timestamp,
+            qos,
             ..Default::default()
         };
 
-        Ok(self
-            .session
-            .driver
-            .tx()
-            .await
-            .send(core::iter::once(NetworkMessage {
-                reliability: Reliability::default(),
-                qos: QoS::default(),
+        self.session
+            .send(NetworkMessage {
+                reliability: self.reliability,
+                qos,
                 body: NetworkBody::Push(msg),
-            }))
-            .await?)
+            })
+            .await
     }
 }
 
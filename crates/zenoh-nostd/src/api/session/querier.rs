@@ -1,14 +1,21 @@
 use core::time::Duration;
-use zenoh_proto::{SessionError, keyexpr};
+use embassy_sync::channel::DynamicSender;
+use zenoh_proto::{Parameters, SessionError, keyexpr};
 
-use crate::{api::session::Session, config::ZSessionConfig, session::GetBuilder};
+use crate::{
+    api::{matching::MatchingStatus, session::Session},
+    config::ZSessionConfig,
+    session::GetBuilder,
+};
 
 pub struct Querier<'a, 'res, Config>
 where
     Config: ZSessionConfig,
 {
     session: &'a Session<'res, Config>,
-    ke: &'static keyexpr,
+    // The id of the interest in the matching queryables.
+    id: u32,
+    ke: &'a keyexpr,
     parameters: Option<&'a str>,
     payload: Option<&'a [u8]>,
     timeout: Option<Duration>,
@@ -20,19 +27,28 @@ where
 {
     pub fn get(&self) -> GetBuilder<'a, 'res, Config> {
         GetBuilder {
-            session: self.session,
-            ke: self.ke,
             parameters: self.parameters,
             payload: self.payload,
             timeout: self.timeout,
-            callback: None,
-            receiver: None,
+            ..GetBuilder::new(self.session, self.ke)
         }
     }
 
-    #[allow(dead_code)]
-    async fn undeclare(self) -> core::result::Result<(), SessionError> {
-        todo!("send undeclare interest")
+    /// Whether the peer knows of queryables matching the querier.
+    pub async fn matching_status(&self) -> core::result::Result<MatchingStatus, SessionError> {
+        self.session.matching_status(self.id).await
+    }
+
+    /// See [`Publisher::matching_listener`](crate::session::Publisher::matching_listener).
+    pub async fn matching_listener(
+        &self,
+        sender: DynamicSender<'res, MatchingStatus>,
+    ) -> core::result::Result<(), SessionError> {
+        self.session.matching_listener(self.id, sender).await
+    }
+
+    pub async fn undeclare(self) -> core::result::Result<(), SessionError> {
+        self.session.undeclare_matching(self.id).await
     }
 
     pub fn keyexpr(&self) -> &keyexpr {
@@ -45,7 +61,7 @@ where
     Config: ZSessionConfig,
 {
     session: &'a Session<'res, Config>,
-    ke: &'static keyexpr,
+    ke: &'a keyexpr,
     parameters: Option<&'a str>,
     payload: Option<&'a [u8]>,
     timeout: Option<Duration>,
@@ -55,7 +71,7 @@ impl<'a, 'res, Config> QuerierBuilder<'a, 'res, Config>
 where
     Config: ZSessionConfig,
 {
-    pub(crate) fn new(session: &'a Session<'res, Config>, ke: &'static keyexpr) -> Self {
+    pub(crate) fn new(session: &'a Session<'res, Config>, ke: &'a keyexpr) -> Self {
         Self {
             session,
             ke,
@@ -65,8 +81,8 @@ where
         }
     }
 
-    pub fn parameters(mut self, parameters: &'a str) -> Self {
-        self.parameters = Some(parameters);
+    pub fn parameters(mut self, parameters: impl Into<Parameters<'a>>) -> Self {
+        self.parameters = Some(parameters.into().as_str());
         self
     }
 
@@ -81,9 +97,11 @@ where
     }
 
     pub async fn finish(self) -> core::result::Result<Querier<'a, 'res, Config>, SessionError> {
-        // TODO: send interest msg
+        let id = self.session.declare_matching(self.ke, true).await?;
+
         Ok(Querier {
             session: self.session,
+            id,
             ke: self.ke,
             parameters: self.parameters,
             payload: self.payload,
@@ -96,7 +114,7 @@ impl<'res, Config> Session<'res, Config>
 where
     Config: ZSessionConfig,
 {
-    pub fn declare_querier(&self, ke: &'static keyexpr) -> QuerierBuilder<'_, 'res, Config> {
+    pub fn declare_querier<'a>(&'a self, ke: &'a keyexpr) -> QuerierBuilder<'a, 'res, Config> {
         QuerierBuilder::new(self, ke)
     }
 }
@@ -3,7 +3,7 @@ use embassy_sync::channel::{DynamicReceiver, DynamicSender};
 use zenoh_proto::{
     SessionError,
     exts::QoS,
-    fields::{ConsolidationMode, Reliability, WireExpr},
+    fields::{CongestionControl, Reliability},
     keyexpr,
     msgs::*,
 };
@@ -28,7 +28,15 @@ pub type FixedCapacityQueryableCallbacks<
     const CAPACITY: usize,
     Callback = RawOrBox<16>,
     Future = RawOrBox<128>,
-> = FixedCapacityCallbacks<'a, QueryableQueryRef<'a, Config>, CAPACITY, Callback, Future>;
+    const MAX_KEYEXPR: usize = 128,
+> = FixedCapacityCallbacks<
+    'a,
+    QueryableQueryRef<'a, Config>,
+    CAPACITY,
+    Callback,
+    Future,
+    MAX_KEYEXPR,
+>;
 
 #[cfg(feature = "alloc")]
 pub type AllocQueryableCallbacks<'a, Config, Callback = RawOrBox<16>, Future = RawOrBox<128>> =
@@ -48,34 +56,25 @@ impl<Config, OwnedQuery, const CHANNEL: bool> Queryable<Config, OwnedQuery, CHAN
 where
     Config: ZSessionConfig,
 {
-    #[allow(dead_code)]
-    async fn undeclare(self) -> core::result::Result<(), SessionError> {
-        let msg = Declare {
-            body: DeclareBody::UndeclareQueryable(UndeclareQueryable {
-                id: self.id,
-                ..Default::default()
-            }),
-            ..Default::default()
-        };
-
+    /// Undeclares the queryable and frees its callback slot. Queries still pending in the
+    /// channel, if any, are discarded.
+    pub async fn undeclare(self) -> core::result::Result<(), SessionError> {
         self.session
             .state()
             .await
             .queryable_callbacks
             .remove(self.id)?;
 
+        if let Some(receiver) = &self.receiver {
+            while receiver.try_receive().is_ok() {}
+        }
+
         self.session
-            .driver
-            .tx()
-            .await
-            .send(core::iter::once(NetworkMessage {
-                reliability: Reliability::default(),
-                qos: QoS::default(),
-                body: NetworkBody::Declare(msg),
+            .send_declare(DeclareBody::UndeclareQueryable(UndeclareQueryable {
+                id: self.id,
+                ..Default::default()
             }))
-            .await?;
-
-        todo!("Also stop the channel if any")
+            .await
     }
 }
 
@@ -87,8 +86,9 @@ where
         self.receiver.as_ref().unwrap().try_receive().ok()
     }
 
+    /// The next query, or `None` once the session is closed and the channel is empty.
     pub async fn recv(&self) -> Option<OwnedQuery> {
-        Some(self.receiver.as_ref().unwrap().receive().await)
+        self.session.recv(self.receiver.as_ref().unwrap()).await
     }
 }
 
@@ -105,6 +105,7 @@ type FutureStorage<Config> =
     >>::Future;
 
 pub struct QueryableBuilder<
+    'a,
     Config,
     OwnedQuery = (),
     const READY: bool = false,
@@ -114,7 +115,7 @@ pub struct QueryableBuilder<
     OwnedQuery: 'static,
 {
     session: &'static Session<'static, Config>,
-    ke: &'static keyexpr,
+    ke: &'a keyexpr,
 
     callback: Option<
         DynCallback<
@@ -127,11 +128,11 @@ pub struct QueryableBuilder<
     receiver: Option<DynamicReceiver<'static, OwnedQuery>>,
 }
 
-impl<Config> QueryableBuilder<Config, (), false, false>
+impl<'a, Config> QueryableBuilder<'a, Config, (), false, false>
 where
     Config: ZSessionConfig,
 {
-    pub(crate) fn new(session: &'static Session<'static, Config>, ke: &'static keyexpr) -> Self {
+    pub(crate) fn new(session: &'static Session<'static, Config>, ke: &'a keyexpr) -> Self {
         Self {
             session,
             ke,
@@ -143,7 +144,7 @@ where
     pub fn callback(
         self,
         callback: impl AsyncFnMut(&QueryableQuery<'_, 'static, Config>) + 'static,
-    ) -> QueryableBuilder<Config, (), true, false> {
+    ) -> QueryableBuilder<'a, Config, (), true, false> {
         QueryableBuilder {
             session: self.session,
             ke: self.ke,
@@ -155,7 +156,7 @@ where
     pub fn callback_sync(
         self,
         callback: impl FnMut(&QueryableQuery<'_, 'static, Config>) + 'static,
-    ) -> QueryableBuilder<Config, (), true, false> {
+    ) -> QueryableBuilder<'a, Config, (), true, false> {
         QueryableBuilder {
             session: self.session,
             ke: self.ke,
@@ -165,7 +166,7 @@ where
     }
 }
 
-impl<Config> QueryableBuilder<Config, (), false, false>
+impl<'a, Config> QueryableBuilder<'a, Config, (), false, false>
 where
     Config: ZSessionConfig,
 {
@@ -173,7 +174,7 @@ where
         self,
         sender: DynamicSender<'static, OwnedQuery>,
         receiver: DynamicReceiver<'static, OwnedQuery>,
-    ) -> QueryableBuilder<Config, OwnedQuery, true, true>
+    ) -> QueryableBuilder<'a, Config, OwnedQuery, true, true>
     where
         OwnedQuery: for<'any> TryFrom<
                 (
@@ -183,12 +184,15 @@ where
                 Error = E,
             >,
     {
+        // Only the session goes into the callback, `ke` may not outlive the builder.
+        let session = self.session;
+
         QueryableBuilder {
-            session: self.session,
+            session,
             ke: self.ke,
             callback: Some(DynObject::new(AsyncCallback::new(
                 async move |resp: &'_ QueryableQuery<'_, 'static, Config>| {
-                    if let Ok(resp) = OwnedQuery::try_from((resp, self.session)) {
+                    if let Ok(resp) = OwnedQuery::try_from((resp, session)) {
                         sender.send(resp).await;
                     } else {
                         zenoh_proto::error!(
@@ -203,7 +207,8 @@ where
     }
 }
 
-impl<Config, OwnedQuery, const CHANNEL: bool> QueryableBuilder<Config, OwnedQuery, true, CHANNEL>
+impl<'a, Config, OwnedQuery, const CHANNEL: bool>
+    QueryableBuilder<'a, Config, OwnedQuery, true, CHANNEL>
 where
     Config: ZSessionConfig,
 {
@@ -223,7 +228,7 @@ where
         let msg = Declare {
             body: DeclareBody::DeclareQueryable(DeclareQueryable {
                 id,
-                wire_expr: WireExpr::from(self.ke),
+                wire_expr: self.session.wire_expr(self.ke).await,
                 ..Default::default()
             }),
             ..Default::default()
@@ -232,7 +237,7 @@ where
         self.session
             .driver
             .tx()
-            .await
+            .await?
             .send(core::iter::once(NetworkMessage {
                 reliability: Reliability::default(),
                 qos: QoS::default(),
@@ -252,7 +257,7 @@ impl<Config> Session<'static, Config>
 where
     Config: ZSessionConfig,
 {
-    pub fn declare_queryable(&'static self, ke: &'static keyexpr) -> QueryableBuilder<Config> {
+    pub fn declare_queryable<'a>(&'static self, ke: &'a keyexpr) -> QueryableBuilder<'a, Config> {
         QueryableBuilder::new(self, ke)
     }
 }
@@ -261,75 +266,25 @@ impl<'res, Config> Session<'res, Config>
 where
     Config: ZSessionConfig,
 {
-    pub(crate) async fn reply(
+    pub(crate) async fn finalize(
         &self,
         rid: u32,
-        ke: &keyexpr,
-        payload: &[u8],
+        reliability: Reliability,
+        qos: QoS,
     ) -> core::result::Result<(), SessionError> {
-        Ok(self
-            .driver
-            .tx()
-            .await
-            .send(core::iter::once(NetworkMessage {
-                reliability: Reliability::default(),
-                qos: QoS::default(),
-                body: NetworkBody::Response(Response {
-                    rid,
-                    wire_expr: WireExpr::from(ke),
-                    payload: ResponseBody::Reply(Reply {
-                        consolidation: ConsolidationMode::None,
-                        payload: PushBody::Put(Put {
-                            payload,
-                            ..Default::default()
-                        }),
-                    }),
-                    ..Default::default()
-                }),
-            }))
-            .await?)
-    }
-
-    pub(crate) async fn err(
-        &self,
-        rid: u32,
-        ke: &keyexpr,
-        payload: &[u8],
-    ) -> core::result::Result<(), SessionError> {
-        Ok(self
-            .driver
-            .tx()
-            .await
-            .send(core::iter::once(NetworkMessage {
-                reliability: Reliability::default(),
-                qos: QoS::default(),
-                body: NetworkBody::Response(Response {
+        if self.state().await.queryable_callbacks.decrease(rid) {
+            // Never drop the final response, the querier would wait until its timeout.
+            let qos = QoS::new(qos.priority(), CongestionControl::Block, qos.is_express());
+            self.send(NetworkMessage {
+                reliability,
+                qos,
+                body: NetworkBody::ResponseFinal(ResponseFinal {
                     rid,
-                    wire_expr: WireExpr::from(ke),
-                    payload: ResponseBody::Err(Err {
-                        payload,
-                        ..Default::default()
-                    }),
+                    qos,
                     ..Default::default()
                 }),
-            }))
-            .await?)
-    }
-
-    pub(crate) async fn finalize(&self, rid: u32) -> core::result::Result<(), SessionError> {
-        if self.state().await.queryable_callbacks.decrease(rid) {
-            self.driver
-                .tx()
-                .await
-                .send(core::iter::once(NetworkMessage {
-                    reliability: Reliability::default(),
-                    qos: QoS::default(),
-                    body: NetworkBody::ResponseFinal(ResponseFinal {
-                        rid,
-                        ..Default::default()
-                    }),
-                }))
-                .await?;
+            })
+            .await?;
         }
 
         Ok(())
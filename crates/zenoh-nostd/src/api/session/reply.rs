@@ -0,0 +1,349 @@
+use zenoh_proto::{exts::*, fields::*, msgs::*, *};
+
+use crate::{api::session::Session, config::ZSessionConfig};
+
+pub struct ReplyBuilder<'a, 'res, Config>
+where
+    Config: ZSessionConfig,
+{
+    pub(crate) session: &'a Session<'res, Config>,
+    pub(crate) rid: u32,
+    pub(crate) eid: u32,
+
+    pub(crate) ke: &'a keyexpr,
+    pub(crate) payload: &'a [u8],
+
+    pub(crate) encoding: Encoding<'a>,
+    pub(crate) timestamp: Option<Timestamp>,
+    pub(crate) attachment: Option<Attachment<'a>>,
+
+    pub(crate) reliability: Reliability,
+    pub(crate) priority: Priority,
+    pub(crate) congestion_control: CongestionControl,
+    pub(crate) express: bool,
+}
+
+impl<'a, 'res, Config> ReplyBuilder<'a, 'res, Config>
+where
+    Config: ZSessionConfig,
+{
+    /// Replies to the query `rid` received with `reliability` and `qos`, on behalf of the
+    /// queryable `eid`.
+    pub(crate) fn new(
+        session: &'a Session<'res, Config>,
+        rid: u32,
+        eid: u32,
+        reliability: Reliability,
+        qos: QoS,
+        ke: &'a keyexpr,
+        payload: &'a [u8],
+    ) -> Self {
+        Self {
+            session,
+            rid,
+            eid,
+            ke,
+            payload,
+            encoding: Encoding::default(),
+            timestamp: None,
+            attachment: None,
+            reliability,
+            priority: qos.priority(),
+            congestion_control: qos.congestion_control(),
+            express: qos.is_express(),
+        }
+    }
+
+    pub fn payload(mut self, payload: &'a [u8]) -> Self {
+        self.payload = payload;
+        self
+    }
+
+    pub fn encoding(mut self, encoding: Encoding<'a>) -> Self {
+        self.encoding = encoding;
+        self
+    }
+
+    pub fn timestamp(mut self, timestamp: Timestamp) -> Self {
+        self.timestamp = Some(timestamp);
+        self
+    }
+
+    pub fn attachment(mut self, attachment: &'a [u8]) -> Self {
+        self.attachment = Some(Attachment { buffer: attachment });
+        self
+    }
+
+    pub fn reliability(mut self, reliability: Reliability) -> Self {
+        self.reliability = reliability;
+        self
+    }
+
+    pub fn priority(mut self, priority: Priority) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    pub fn congestion_control(mut self, congestion_control: CongestionControl) -> Self {
+        self.congestion_control = congestion_control;
+        self
+    }
+
+    pub fn express(mut self, express: bool) -> Self {
+        self.express = express;
+        self
+    }
+
+    /// Returns `false` if the reply has been dropped, under `CongestionControl::Drop` with the
+    /// TX batch full.
+    pub async fn finish(self) -> core::result::Result<bool, SessionError> {
+        let qos = QoS::new(self.priority, self.congestion_control, self.express);
+        let payload = ResponseBody::Reply(Reply {
+            consolidation: ConsolidationMode::None,
+            payload: PushBody::Put(Put {
+                payload: self.payload,
+                encoding: self.encoding,
+                timestamp: self.timestamp,
+                attachment: self.attachment,
+                ..Default::default()
+            }),
+        });
+
+        self.session
+            .send(NetworkMessage {
+                reliability: self.reliability,
+                qos,
+                body: NetworkBody::Response(Response {
+                    rid: self.rid,
+                    wire_expr: self.session.wire_expr(self.ke).await,
+                    qos,
+                    timestamp: self.timestamp,
+                    respid: Some(self.session.entity_global_id(self.eid)),
+                    payload,
+                }),
+            })
+            .await
+    }
+}
+
+/// Answers a query with the deletion of a key expression.
+pub struct ReplyDelBuilder<'a, 'res, Config>
+where
+    Config: ZSessionConfig,
+{
+    pub(crate) session: &'a Session<'res, Config>,
+    pub(crate) rid: u32,
+    pub(crate) eid: u32,
+
+    pub(crate) ke: &'a keyexpr,
+
+    pub(crate) timestamp: Option<Timestamp>,
+    pub(crate) attachment: Option<Attachment<'a>>,
+
+    pub(crate) reliability: Reliability,
+    pub(crate) priority: Priority,
+    pub(crate) congestion_control: CongestionControl,
+    pub(crate) express: bool,
+}
+
+impl<'a, 'res, Config> ReplyDelBuilder<'a, 'res, Config>
+where
+    Config: ZSessionConfig,
+{
+    pub(crate) fn new(
+        session: &'a Session<'res, Config>,
+        rid: u32,
+        eid: u32,
+        reliability: Reliability,
+        qos: QoS,
+        ke: &'a keyexpr,
+    ) -> Self {
+        Self {
+            session,
+            rid,
+            eid,
+            ke,
+            timestamp: None,
+            attachment: None,
+            reliability,
+            priority: qos.priority(),
+            congestion_control: qos.congestion_control(),
+            express: qos.is_express(),
+        }
+    }
+
+    pub fn timestamp(mut self, timestamp: Timestamp) -> Self {
+        self.timestamp = Some(timestamp);
+        self
+    }
+
+    pub fn attachment(mut self, attachment: &'a [u8]) -> Self {
+        self.attachment = Some(Attachment { buffer: attachment });
+        self
+    }
+
+    pub fn reliability(mut self, reliability: Reliability) -> Self {
+        self.reliability = reliability;
+        self
+    }
+
+    pub fn priority(mut self, priority: Priority) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    pub fn congestion_control(mut self, congestion_control: CongestionControl) -> Self {
+        self.congestion_control = congestion_control;
+        self
+    }
+
+    pub fn express(mut self, express: bool) -> Self {
+        self.express = express;
+        self
+    }
+
+    /// Returns `false` if the reply has been dropped, under `CongestionControl::Drop` with the
+    /// TX batch full.
+    pub async fn finish(self) -> core::result::Result<bool, SessionError> {
+        let qos = QoS::new(self.priority, self.congestion_control, self.express);
+        let payload = ResponseBody::Reply(Reply {
+            consolidation: ConsolidationMode::None,
+            payload: PushBody::Del(Del {
+                timestamp: self.timestamp,
+                attachment: self.attachment,
+                ..Default::default()
+            }),
+        });
+
+        self.session
+            .send(NetworkMessage {
+                reliability: self.reliability,
+                qos,
+                body: NetworkBody::Response(Response {
+                    rid: self.rid,
+                    wire_expr: self.session.wire_expr(self.ke).await,
+                    qos,
+                    timestamp: self.timestamp,
+                    respid: Some(self.session.entity_global_id(self.eid)),
+                    payload,
+                }),
+            })
+            .await
+    }
+}
+
+pub struct ReplyErrBuilder<'a, 'res, Config>
+where
+    Config: ZSessionConfig,
+{
+    pub(crate) session: &'a Session<'res, Config>,
+    pub(crate) rid: u32,
+    pub(crate) eid: u32,
+
+    pub(crate) ke: &'a keyexpr,
+    pub(crate) payload: &'a [u8],
+
+    pub(crate) encoding: Encoding<'a>,
+
+    pub(crate) reliability: Reliability,
+    pub(crate) priority: Priority,
+    pub(crate) congestion_control: CongestionControl,
+    pub(crate) express: bool,
+}
+
+impl<'a, 'res, Config> ReplyErrBuilder<'a, 'res, Config>
+where
+    Config: ZSessionConfig,
+{
+    pub(crate) fn new(
+        session: &'a Session<'res, Config>,
+        rid: u32,
+        eid: u32,
+        reliability: Reliability,
+        qos: QoS,
+        ke: &'a keyexpr,
+        payload: &'a [u8],
+    ) -> Self {
+        Self {
+            session,
+            rid,
+            eid,
+            ke,
+            payload,
+            encoding: Encoding::default(),
+            reliability,
+            priority: qos.priority(),
+            congestion_control: qos.congestion_control(),
+            express: qos.is_express(),
+        }
+    }
+
+    pub fn payload(mut self, payload: &'a [u8]) -> Self {
+        self.payload = payload;
+        self
+    }
+
+    pub fn encoding(mut self, encoding: Encoding<'a>) -> Self {
+        self.encoding = encoding;
+        self
+    }
+
+    pub fn reliability(mut self, reliability: Reliability) -> Self {
+        self.reliability = reliability;
+        self
+    }
+
+    pub fn priority(mut self, priority: Priority) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    pub fn congestion_control(mut self, congestion_control: CongestionControl) -> Self {
+        self.congestion_control = congestion_control;
+        self
+    }
+
+    pub fn express(mut self, express: bool) -> Self {
+        self.express = express;
+        self
+    }
+
+    /// Returns `false` if the reply has been dropped, under `CongestionControl::Drop` with the
+    /// TX batch full.
+    pub async fn finish(self) -> core::result::Result<bool, SessionError> {
+        let qos = QoS::new(self.priority, self.congestion_control, self.express);
+        let payload = ResponseBody::Err(Err {
+            encoding: self.encoding,
+            payload: self.payload,
+            ..Default::default()
+        });
+
+        self.session
+            .send(NetworkMessage {
+                reliability: self.reliability,
+                qos,
+                body: NetworkBody::Response(Response {
+                    rid: self.rid,
+                    wire_expr: self.session.wire_expr(self.ke).await,
+                    qos,
+                    timestamp: None,
+                    respid: Some(self.session.entity_global_id(self.eid)),
+                    payload,
+                }),
+            })
+            .await
+    }
+}
+
+impl<'res, Config> Session<'res, Config>
+where
+    Config: ZSessionConfig,
+{
+    /// Identifies an entity of this session, e.g. a queryable answering a query.
+    pub(crate) fn entity_global_id(&self, eid: u32) -> EntityGlobalId {
+        EntityGlobalId {
+            zid: self.driver.mine_zid(),
+            eid,
+        }
+    }
+}
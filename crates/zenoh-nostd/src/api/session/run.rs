@@ -1,10 +1,19 @@
-use zenoh_proto::{exts::Value, msgs::*, *};
+use embassy_futures::select::{Either3, select3};
+use zenoh_proto::{
+    exts::Value,
+    fields::{ConsolidationMode, WireExpr},
+    msgs::*,
+    *,
+};
 
 use crate::{
     api::{
         callbacks::{ZCallbacks, ZDynCallback},
+        consolidation::{self, ZConsolidations},
+        keyexprs::{ZKeyExprs, ZRemoteDeclarations, ZRemoteKeyExprs},
+        matching::{self, ZMatchings},
         query::QueryableQuery,
-        session::Session,
+        session::{Session, liveliness::TOKENS, matching::options},
     },
     config::ZSessionConfig,
     session::{GetResponse, Sample},
@@ -14,87 +23,456 @@ impl<'res, Config> Session<'res, Config>
 where
     Config: ZSessionConfig,
 {
+    /// Handles the messages of the peer until the transport is closed. A reconnecting session
+    /// connects again instead of returning.
     pub async fn run(&self) -> core::result::Result<(), SessionError> {
-        self.driver
-            .run(&self.state, async |_, state, msg, _| {
-                match msg.body {
-                    NetworkBody::Push(Push {
-                        wire_expr,
-                        payload: PushBody::Put(Put { payload, .. }),
-                        ..
-                    }) => {
-                        let ke = wire_expr.suffix;
-                        let ke = keyexpr::new(ke)?;
-                        let sample = Sample::new(ke, payload);
-
-                        for cb in state.sub_callbacks.intersects(ke) {
-                            cb.call_try_sync(&sample).await;
+        let event = |event| self.event(event);
+
+        loop {
+            let run = self
+                .driver
+                .run(&self.state, event, async |_, state, msg, _| {
+                    let NetworkMessage {
+                        reliability,
+                        qos,
+                        body,
+                    } = msg;
+
+                    match body {
+                        NetworkBody::Push(Push {
+                            wire_expr, payload, ..
+                        }) => {
+                            let Some(ke) =
+                                self.resolve(&mut state.remote_keyexprs, &wire_expr).await
+                            else {
+                                return Ok(());
+                            };
+                            let sample = Sample::from_push(ke, payload);
+
+                            for (_, cb) in state.sub_callbacks.intersects(ke) {
+                                cb.call_try_sync(&sample).await;
+                            }
                         }
-                    }
-                    NetworkBody::Response(Response {
-                        rid,
-                        wire_expr,
-                        payload,
-                        ..
-                    }) => {
-                        let ke = wire_expr.suffix;
-                        let ke = keyexpr::new(ke)?;
-                        let response = match payload {
-                            ResponseBody::Reply(Reply {
-                                payload: PushBody::Put(Put { payload, .. }),
-                                ..
-                            }) => GetResponse::Ok(Sample::new(ke, payload)),
-                            ResponseBody::Err(Err { payload, .. }) => {
-                                GetResponse::Err(Sample::new(ke, payload))
+                        NetworkBody::Response(Response {
+                            rid,
+                            wire_expr,
+                            payload,
+                            ..
+                        }) => {
+                            let Some(ke) =
+                                self.resolve(&mut state.remote_keyexprs, &wire_expr).await
+                            else {
+                                return Ok(());
+                            };
+                            let response = match payload {
+                                ResponseBody::Reply(Reply { payload, .. }) => {
+                                    GetResponse::Ok(Sample::from_push(ke, payload))
+                                }
+                                ResponseBody::Err(Err {
+                                    encoding,
+                                    sinfo,
+                                    payload,
+                                }) => GetResponse::Err(
+                                    Sample::new(ke, payload)
+                                        .with_encoding(encoding)
+                                        .with_source_info(sinfo),
+                                ),
+                            };
+
+                            if let GetResponse::Ok(sample) = &response
+                                && !consolidation::consolidate(
+                                    &mut state.consolidations,
+                                    rid,
+                                    sample,
+                                )
+                            {
+                                return Ok(());
                             }
-                        };
 
-                        if let Some(cb) = state.get_callbacks.get(rid) {
-                            cb.call_try_sync(&response).await;
+                            if let Some(cb) = state.get_callbacks.get(rid) {
+                                cb.call_try_sync(&response).await;
+                            }
                         }
-                    }
-                    NetworkBody::ResponseFinal(ResponseFinal { rid, .. }) => {
-                        state.get_callbacks.remove(rid)?;
-                        // TODO: also close channels
-                    }
-                    NetworkBody::Request(Request {
-                        id,
-                        wire_expr,
-                        payload:
-                            RequestBody::Query(Query {
-                                parameters, body, ..
-                            }),
-                        ..
-                    }) => {
-                        let ke = wire_expr.suffix;
-                        let ke = keyexpr::new(ke)?;
-                        let query = QueryableQuery::new(
-                            self,
+                        NetworkBody::ResponseFinal(ResponseFinal { rid, .. }) => {
+                            if state.consolidations.mode(rid) == Some(ConsolidationMode::Latest)
+                                && let Some(cb) = state.get_callbacks.get(rid)
+                            {
+                                for sample in state.consolidations.replies(rid) {
+                                    cb.call_try_sync(&GetResponse::Ok(sample)).await;
+                                }
+                            }
+                            let _ = state.consolidations.remove(rid);
+                            let _ = state.get_callbacks.remove(rid);
+                            self.complete_gets();
+                        }
+                        NetworkBody::Request(Request {
                             id,
-                            ke,
-                            if parameters.is_empty() {
-                                None
-                            } else {
-                                Some(parameters)
-                            },
-                            match body {
-                                Some(Value { payload, .. }) => Some(payload),
-                                None => None,
-                            },
-                        );
-
-                        let count = state.queryable_callbacks.intersects(ke).count();
-                        state.queryable_callbacks.set_counter(id, count)?;
-                        for cb in state.queryable_callbacks.intersects(ke) {
-                            cb.call(&query).await;
+                            wire_expr,
+                            payload:
+                                RequestBody::Query(Query {
+                                    parameters, body, ..
+                                }),
+                            ..
+                        }) => {
+                            let Some(ke) =
+                                self.resolve(&mut state.remote_keyexprs, &wire_expr).await
+                            else {
+                                return Ok(());
+                            };
+                            let mut query = QueryableQuery::new(
+                                self,
+                                id,
+                                reliability,
+                                qos,
+                                ke,
+                                if parameters.is_empty() {
+                                    None
+                                } else {
+                                    Some(parameters)
+                                },
+                                match body {
+                                    Some(Value { payload, .. }) => Some(payload),
+                                    None => None,
+                                },
+                            );
+
+                            let count = state.queryable_callbacks.intersects(ke).count();
+                            state.queryable_callbacks.set_counter(id, count)?;
+                            for (queryable, cb) in state.queryable_callbacks.intersects(ke) {
+                                query.set_queryable(queryable);
+                                cb.call(&query).await;
+                            }
+                        }
+                        NetworkBody::Declare(Declare {
+                            body: DeclareBody::DeclareKeyExpr(DeclareKeyExpr { id, wire_expr }),
+                            ..
+                        }) => {
+                            let local = self.local_keyexpr(&wire_expr).await;
+                            if state
+                                .remote_keyexprs
+                                .declare(id, &wire_expr, local.map(|ke| ke.as_str()))
+                                .is_err()
+                            {
+                                zenoh_proto::warn!(
+                                    "{}: Couldn't store keyexpr {}",
+                                    zenoh_proto::zctx!(),
+                                    id
+                                );
+                            }
+                        }
+                        NetworkBody::Declare(Declare {
+                            body: DeclareBody::UndeclareKeyExpr(UndeclareKeyExpr { id }),
+                            ..
+                        }) => {
+                            let _ = state.remote_keyexprs.undeclare(id);
                         }
+                        NetworkBody::Declare(Declare {
+                            id: interest,
+                            body:
+                                DeclareBody::DeclareSubscriber(DeclareSubscriber { id, wire_expr }),
+                            ..
+                        }) => {
+                            let Some(ke) =
+                                self.resolve(&mut state.remote_keyexprs, &wire_expr).await
+                            else {
+                                return Ok(());
+                            };
+
+                            // Untracked subscribers could never be undeclared.
+                            if state.remote_subscribers.declare(id, ke).is_err() {
+                                zenoh_proto::warn!(
+                                    "{}: Couldn't store subscriber {}",
+                                    zenoh_proto::zctx!(),
+                                    id
+                                );
+                                return Ok(());
+                            }
+
+                            matching::update(&mut state.matchings, interest, false, ke, true);
+                        }
+                        NetworkBody::Declare(Declare {
+                            id: interest,
+                            body:
+                                DeclareBody::DeclareQueryable(DeclareQueryable {
+                                    id, wire_expr, ..
+                                }),
+                            ..
+                        }) => {
+                            let Some(ke) =
+                                self.resolve(&mut state.remote_keyexprs, &wire_expr).await
+                            else {
+                                return Ok(());
+                            };
+
+                            if state.remote_queryables.declare(id, ke).is_err() {
+                                zenoh_proto::warn!(
+                                    "{}: Couldn't store queryable {}",
+                                    zenoh_proto::zctx!(),
+                                    id
+                                );
+                                return Ok(());
+                            }
+
+                            matching::update(&mut state.matchings, interest, true, ke, true);
+                        }
+                        NetworkBody::Declare(Declare {
+                            body: DeclareBody::UndeclareSubscriber(UndeclareSubscriber { id, .. }),
+                            ..
+                        }) => {
+                            let Some(ke) = state.remote_subscribers.undeclare(id) else {
+                                zenoh_proto::warn!(
+                                    "{}: Unknown subscriber {}",
+                                    zenoh_proto::zctx!(),
+                                    id
+                                );
+                                return Ok(());
+                            };
+
+                            matching::update(&mut state.matchings, None, false, ke, false);
+                        }
+                        NetworkBody::Declare(Declare {
+                            body: DeclareBody::UndeclareQueryable(UndeclareQueryable { id, .. }),
+                            ..
+                        }) => {
+                            let Some(ke) = state.remote_queryables.undeclare(id) else {
+                                zenoh_proto::warn!(
+                                    "{}: Unknown queryable {}",
+                                    zenoh_proto::zctx!(),
+                                    id
+                                );
+                                return Ok(());
+                            };
+
+                            matching::update(&mut state.matchings, None, true, ke, false);
+                        }
+                        NetworkBody::Declare(Declare {
+                            id: interest,
+                            body:
+                                DeclareBody::DeclareToken(DeclareToken {
+                                    id: token,
+                                    wire_expr,
+                                }),
+                            ..
+                        }) => {
+                            let Some(ke) =
+                                self.resolve(&mut state.remote_keyexprs, &wire_expr).await
+                            else {
+                                return Ok(());
+                            };
+
+                            // Tokens only listed for a liveliness get are never undeclared to us.
+                            let listed =
+                                interest.is_some_and(|id| state.get_callbacks.get(id).is_some());
+                            if !listed && state.remote_tokens.declare(token, ke).is_err() {
+                                zenoh_proto::warn!(
+                                    "{}: Couldn't store token {}",
+                                    zenoh_proto::zctx!(),
+                                    token
+                                );
+                            }
+
+                            // Tokens declared in reply to an interest only go to its issuer.
+                            match interest {
+                                Some(id) => {
+                                    if let Some(cb) = state.get_callbacks.get(id) {
+                                        cb.call_try_sync(&GetResponse::ok(ke, &[])).await;
+                                    } else if let Some(cb) = state.liveliness_callbacks.get(id) {
+                                        cb.call_try_sync(&Sample::new(ke, &[])).await;
+                                    }
+                                }
+                                None => {
+                                    for (_, cb) in state.liveliness_callbacks.intersects(ke) {
+                                        cb.call_try_sync(&Sample::new(ke, &[])).await;
+                                    }
+                                }
+                            }
+                        }
+                        NetworkBody::Declare(Declare {
+                            body: DeclareBody::UndeclareToken(UndeclareToken { id, .. }),
+                            ..
+                        }) => {
+                            let Some(ke) = state.remote_tokens.undeclare(id) else {
+                                zenoh_proto::warn!(
+                                    "{}: Unknown token {}",
+                                    zenoh_proto::zctx!(),
+                                    id
+                                );
+                                return Ok(());
+                            };
+
+                            for (_, cb) in state.liveliness_callbacks.intersects(ke) {
+                                cb.call_try_sync(&Sample::delete(ke)).await;
+                            }
+                        }
+                        NetworkBody::Declare(Declare {
+                            id: Some(id),
+                            body: DeclareBody::DeclareFinal(_),
+                            ..
+                        }) => {
+                            // The peer has sent all the current tokens of a liveliness get.
+                            let _ = state.get_callbacks.remove(id);
+                            self.complete_gets();
+                        }
+                        _ => {}
                     }
-                    _ => {}
+
+                    Ok::<(), SessionError>(())
+                });
+
+            let dropped = self.undeclare_dropped_tokens();
+            let cancelled = self.cancel_gets();
+
+            let res = match select3(run, dropped, cancelled).await {
+                Either3::First(res) => res,
+                Either3::Second(never) | Either3::Third(never) => match never {},
+            };
+
+            let e = match res {
+                Ok(()) => return Ok(()),
+                core::result::Result::Err(EitherError::A(e)) => e,
+                core::result::Result::Err(EitherError::B(e)) => {
+                    return core::result::Result::Err(e);
+                }
+            };
+
+            if self.driver.closed() {
+                return Ok(());
+            }
+
+            let Some((config, endpoint)) = self.reconnect.clone() else {
+                return core::result::Result::Err(e.into());
+            };
+
+            zenoh_proto::warn!(
+                "{}: Transport lost ({}), reconnecting",
+                zenoh_proto::zctx!(),
+                e
+            );
+            if let core::result::Result::Err(e) = self.reconnect(config, endpoint).await {
+                zenoh_proto::warn!(
+                    "{}: Couldn't declare the session again ({})",
+                    zenoh_proto::zctx!(),
+                    e
+                );
+            }
+        }
+    }
+
+    /// Replaces the transport with a new connection to `endpoint`. In-flight gets are completed
+    /// and the entities of the session are declared again to the new peer.
+    async fn reconnect(
+        &self,
+        config: &'res Config,
+        endpoint: Endpoint<'res>,
+    ) -> core::result::Result<(), SessionError> {
+        // Retries until connected, so the state is only locked afterwards.
+        let transport = config.transports().reconnect(endpoint, config.buff()).await;
+        let resolution = transport.transport().resolution;
+
+        let mut state = self.state().await;
+        let state = &mut *state;
+
+        // The replies kept so far are the latest ones the get will see. The get then completes
+        // as if it had timed out, the events tell why.
+        for (rid, _, cb) in state.get_callbacks.iter_mut() {
+            if state.consolidations.mode(rid) == Some(ConsolidationMode::Latest) {
+                for sample in state.consolidations.replies(rid) {
+                    cb.call_try_sync(&GetResponse::Ok(sample)).await;
                 }
+            }
+        }
+        state.get_callbacks = Config::GetCallbacks::empty();
+        self.complete_gets();
+        state.consolidations = Config::Consolidations::empty();
+        state.remote_keyexprs = Config::RemoteKeyExprs::empty();
+        state.remote_subscribers = Config::RemoteDeclarations::empty();
+        state.remote_queryables = Config::RemoteDeclarations::empty();
+        state.remote_tokens = Config::RemoteDeclarations::empty();
+
+        self.driver.replace(transport).await;
+        state.set_resolution(resolution);
+        zenoh_proto::info!("{}: Reconnected", zenoh_proto::zctx!());
+
+        let keyexprs = self.keyexprs.lock().await;
+        for (id, ke) in keyexprs.iter() {
+            self.send_declare_keyexpr(id, ke).await?;
+        }
+
+        // Publishers keep sending their puts with the id they were given.
+        for (_, ke, matching) in state.matchings.iter_mut() {
+            if let Some(scope) = matching.scope() {
+                self.send_declare_keyexpr(scope, ke).await?;
+            }
+        }
+
+        for (id, ke, _) in state.sub_callbacks.iter_mut() {
+            self.send_declare(DeclareBody::DeclareSubscriber(DeclareSubscriber {
+                id,
+                wire_expr: keyexprs.wire_expr(ke),
+            }))
+            .await?;
+        }
+
+        for (id, ke, _) in state.queryable_callbacks.iter_mut() {
+            self.send_declare(DeclareBody::DeclareQueryable(DeclareQueryable {
+                id,
+                wire_expr: keyexprs.wire_expr(ke),
+                ..Default::default()
+            }))
+            .await?;
+        }
+        drop(keyexprs);
+
+        for (id, ke) in state.tokens.iter() {
+            self.send_declare_token(id, ke).await?;
+        }
+
+        // Subscribers also get the tokens alive on the new peer, some may have appeared while
+        // disconnected.
+        for (id, ke, _) in state.liveliness_callbacks.iter_mut() {
+            self.send_interest(id, InterestMode::CurrentFuture, TOKENS, ke)
+                .await?;
+        }
+
+        for (id, ke, matching) in state.matchings.iter_mut() {
+            matching.reset();
+            self.send_interest(
+                id,
+                InterestMode::CurrentFuture,
+                options(matching.querier()),
+                ke,
+            )
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Rebuilds the key expression of `wire_expr`, or returns `None` if it refers to an unknown
+    /// declaration or is not a valid key expression.
+    async fn resolve<'a>(
+        &self,
+        remote: &'a mut Config::RemoteKeyExprs,
+        wire_expr: &WireExpr<'a>,
+    ) -> Option<&'a keyexpr> {
+        let local = self.local_keyexpr(wire_expr).await;
+
+        let Ok(ke) = remote.resolve(wire_expr, local.map(|ke| ke.as_str())) else {
+            zenoh_proto::warn!(
+                "{}: Unknown keyexpr scope {}",
+                zenoh_proto::zctx!(),
+                wire_expr.scope
+            );
+
+            return None;
+        };
+
+        let Ok(ke) = keyexpr::new(ke) else {
+            zenoh_proto::warn!("{}: Invalid keyexpr {}", zenoh_proto::zctx!(), ke);
+
+            return None;
+        };
 
-                Ok::<(), SessionError>(())
-            })
-            .await
-            .map_err(|e| e.flatten_map())
+        Some(ke)
     }
 }
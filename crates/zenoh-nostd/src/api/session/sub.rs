@@ -21,7 +21,8 @@ pub type FixedCapacitySubCallbacks<
     const CAPACITY: usize,
     Callback = RawOrBox<16>,
     Future = RawOrBox<128>,
-> = FixedCapacityCallbacks<'a, SampleRef, CAPACITY, Callback, Future>;
+    const MAX_KEYEXPR: usize = 128,
+> = FixedCapacityCallbacks<'a, SampleRef, CAPACITY, Callback, Future, MAX_KEYEXPR>;
 
 #[cfg(feature = "alloc")]
 pub type AllocSubCallbacks<'a, Callback = RawOrBox<16>, Future = RawOrBox<128>> =
@@ -32,7 +33,7 @@ where
     Config: ZSessionConfig,
 {
     id: u32,
-    ke: &'static keyexpr,
+    ke: &'a keyexpr,
     session: &'a Session<'res, Config>,
     receiver: Option<DynamicReceiver<'res, OwnedSample>>,
 }
@@ -42,30 +43,21 @@ impl<'a, 'res, Config, OwnedSample, const CHANNEL: bool>
 where
     Config: ZSessionConfig,
 {
-    #[allow(dead_code)]
-    async fn undeclare(self) -> core::result::Result<(), SessionError> {
-        let msg = Declare {
-            body: DeclareBody::UndeclareSubscriber(UndeclareSubscriber {
-                id: self.id,
-                ..Default::default()
-            }),
-            ..Default::default()
-        };
-
+    /// Undeclares the subscriber and frees its callback slot. Samples still pending in the
+    /// channel, if any, are discarded.
+    pub async fn undeclare(self) -> core::result::Result<(), SessionError> {
         self.session.state().await.sub_callbacks.remove(self.id)?;
 
+        if let Some(receiver) = &self.receiver {
+            while receiver.try_receive().is_ok() {}
+        }
+
         self.session
-            .driver
-            .tx()
-            .await
-            .send(core::iter::once(NetworkMessage {
-                reliability: Reliability::default(),
-                qos: exts::QoS::default(),
-                body: NetworkBody::Declare(msg),
+            .send_declare(DeclareBody::UndeclareSubscriber(UndeclareSubscriber {
+                id: self.id,
+                ..Default::default()
             }))
-            .await?;
-
-        todo!("Also stop the channel if any")
+            .await
     }
 
     pub fn keyexpr(&self) -> &keyexpr {
@@ -81,8 +73,9 @@ where
         self.receiver.as_ref().unwrap().try_receive().ok()
     }
 
+    /// The next sample, or `None` once the session is closed and the channel is empty.
     pub async fn recv(&self) -> Option<OwnedSample> {
-        Some(self.receiver.as_ref().unwrap().receive().await)
+        self.session.recv(self.receiver.as_ref().unwrap()).await
     }
 }
 
@@ -103,7 +96,7 @@ pub struct SubscriberBuilder<
     Config: ZSessionConfig,
 {
     session: &'a Session<'res, Config>,
-    ke: &'static keyexpr,
+    ke: &'a keyexpr,
     callback: Option<
         DynCallback<'res, CallbackStorage<'res, Config>, FutureStorage<'res, Config>, SampleRef>,
     >,
@@ -114,7 +107,7 @@ impl<'a, 'res, Config> SubscriberBuilder<'a, 'res, Config, (), false, false>
 where
     Config: ZSessionConfig,
 {
-    pub(crate) fn new(session: &'a Session<'res, Config>, ke: &'static keyexpr) -> Self {
+    pub(crate) fn new(session: &'a Session<'res, Config>, ke: &'a keyexpr) -> Self {
         Self {
             session,
             ke,
@@ -194,7 +187,7 @@ where
         let msg = Declare {
             body: DeclareBody::DeclareSubscriber(DeclareSubscriber {
                 id,
-                wire_expr: WireExpr::from(self.ke),
+                wire_expr: self.session.wire_expr(self.ke).await,
             }),
             ..Default::default()
         };
@@ -202,7 +195,7 @@ where
         self.session
             .driver
             .tx()
-            .await
+            .await?
             .send(core::iter::once(NetworkMessage {
                 reliability: Reliability::default(),
                 qos: QoS::default(),
@@ -223,7 +216,10 @@ impl<'res, Config> Session<'res, Config>
 where
     Config: ZSessionConfig,
 {
-    pub fn declare_subscriber(&self, ke: &'static keyexpr) -> SubscriberBuilder<'_, 'res, Config> {
+    pub fn declare_subscriber<'a>(
+        &'a self,
+        ke: &'a keyexpr,
+    ) -> SubscriberBuilder<'a, 'res, Config> {
         SubscriberBuilder::new(self, ke)
     }
 }
@@ -2,20 +2,65 @@ use crate::{
     api::{
         arg::{GetResponseRef, QueryableQueryRef, SampleRef},
         callbacks::ZCallbacks,
+        consolidation::ZConsolidations,
+        keyexprs::{ZKeyExprs, ZRemoteDeclarations, ZRemoteKeyExprs},
+        matching::ZMatchings,
+        pending::ZPending,
     },
     io::{link::ZLinkManager, transport::TransportLinkManager},
 };
 
+/// The buffers, links and collections of a session.
+///
+/// Each collection comes as a `FixedCapacity*` type bounded by const generics, and as an
+/// `Alloc*` type under the `alloc` feature. A configuration written before the consolidation,
+/// liveliness, matching and remote declaration support can start from these, sized for 8 get
+/// callbacks, 4 liveliness tokens and key expressions of up to 128 bytes:
+///
+/// ```ignore
+/// type Consolidations = FixedCapacityConsolidations<4, 8, 128, 128>;
+/// type LivelinessCallbacks<'res> = FixedCapacitySubCallbacks<'res, 4>;
+/// type KeyExprs<'res> = FixedCapacityKeyExprs<'res, 8>;
+/// type Tokens<'res> = FixedCapacityKeyExprs<'res, 4>;
+/// type Matchings<'res> = FixedCapacityMatchings<'res, 8, 128>;
+/// type DroppedTokens = FixedCapacityPending<u16, 4>;
+/// type CancelledGets = FixedCapacityPending<(u32, bool), 8>;
+/// type RemoteKeyExprs = FixedCapacityRemoteKeyExprs<8, 128>;
+/// type RemoteDeclarations = FixedCapacityRemoteDeclarations<16, 128>;
+/// ```
+///
+/// The capacities of the maps must be powers of two. The `Alloc*` counterparts take no
+/// capacity, e.g. `AllocConsolidations` or `AllocPending<u16>`.
 pub trait ZSessionConfig: Sized {
     type Buff: AsMut<[u8]> + AsRef<[u8]> + Clone;
     type LinkManager: ZLinkManager;
 
     type SubCallbacks<'res>: ZCallbacks<'res, SampleRef>;
     type GetCallbacks<'res>: ZCallbacks<'res, GetResponseRef>;
+    /// Replies kept to consolidate the queries with `Monotonic` or `Latest`.
+    type Consolidations: ZConsolidations;
     type QueryableCallbacks<'res>: ZCallbacks<'res, QueryableQueryRef<'res, Self>>
     where
         Self: 'res;
 
+    type LivelinessCallbacks<'res>: ZCallbacks<'res, SampleRef>;
+
+    type KeyExprs<'res>: ZKeyExprs<'res>;
+    /// Liveliness tokens declared by this session.
+    type Tokens<'res>: ZKeyExprs<'res>;
+    /// Matching status of the publishers and queriers.
+    type Matchings<'res>: ZMatchings<'res>;
+    /// Liveliness tokens dropped and not undeclared yet. None is lost when it holds as many ids
+    /// as `Tokens`.
+    type DroppedTokens: ZPending<u16>;
+    /// Gets cancelled and not dropped yet, with whether they are interests. None is lost when it
+    /// holds as many as `GetCallbacks`.
+    type CancelledGets: ZPending<(u32, bool)>;
+    type RemoteKeyExprs: ZRemoteKeyExprs;
+    /// Subscribers, queryables and liveliness tokens declared by the peer, each kind in its own
+    /// collection.
+    type RemoteDeclarations: ZRemoteDeclarations;
+
     fn transports(&self) -> &TransportLinkManager<Self::LinkManager>;
     fn buff(&self) -> Self::Buff;
 }
@@ -1,26 +1,34 @@
-use core::ops::DerefMut;
-use embassy_futures::select::{Either3, select3};
-use embassy_sync::{
-    blocking_mutex::raw::NoopRawMutex,
-    mutex::{Mutex, MutexGuard},
-};
+use core::{cell::Cell, ops::DerefMut};
+use embassy_futures::select::{Either4, select4};
+use embassy_sync::{blocking_mutex::raw::NoopRawMutex, mutex::Mutex};
 use embassy_time::{Duration, Instant, Timer};
-use zenoh_proto::{EitherError, TransportLinkError, fields::ZenohIdProto, msgs::NetworkMessage};
+use zenoh_proto::{
+    EitherError, TransportLinkError,
+    fields::{CongestionControl, ZenohIdProto},
+    msgs::{Close, NetworkMessage},
+};
 
 use crate::{
-    io::transport::{
-        TransportLink, TransportLinkRx, TransportLinkTx, ZTransportLinkRx, ZTransportLinkTx,
-    },
+    api::events::TransportEvent,
+    io::transport::{TransportLink, TransportLinkRx, ZTransportLinkRx, ZTransportLinkTx},
+    notify::Notify,
     platform::ZLink,
+    resources::{TransportSlot, TxGuard},
 };
 
 pub struct Driver<'res, Link, Buff>
 where
     Link: ZLink + 'res,
 {
-    zid: ZenohIdProto,
-    tx: Mutex<NoopRawMutex, TransportLinkTx<'res, Link::Tx<'res>, Buff>>,
-    rx: Mutex<NoopRawMutex, TransportLinkRx<'res, Link::Rx<'res>, Buff>>,
+    zid: Cell<ZenohIdProto>,
+    mine_zid: Cell<ZenohIdProto>,
+    closed: Cell<bool>,
+    closing: Notify,
+    /// Senders waiting for the TX side
+    waiting: Cell<usize>,
+    /// Notified when the last waiting sender gives up, leaving the pending batch to `run`
+    flushing: Notify,
+    transport: TransportSlot<'res, Link, Buff>,
 }
 
 impl<'res, Link, Buff> Driver<'res, Link, Buff>
@@ -29,30 +37,122 @@ where
 {
     pub fn new(transport: &'res mut TransportLink<Link, Buff>) -> Self {
         let zid = transport.transport().other_zid;
-
-        let (tx, rx) = transport.split();
+        let mine_zid = transport.transport().mine_zid;
 
         Self {
-            zid,
-            tx: Mutex::new(tx),
-            rx: Mutex::new(rx),
+            zid: Cell::new(zid),
+            mine_zid: Cell::new(mine_zid),
+            closed: Cell::new(false),
+            closing: Notify::new(),
+            waiting: Cell::new(0),
+            flushing: Notify::new(),
+            transport: TransportSlot::new(transport),
         }
     }
 
     #[allow(dead_code)]
     pub fn zid(&self) -> ZenohIdProto {
-        self.zid
+        self.zid.get()
+    }
+
+    /// The zid this side of the transport identifies itself with.
+    pub fn mine_zid(&self) -> ZenohIdProto {
+        self.mine_zid.get()
+    }
+
+    /// Swaps in a new transport, e.g. after reconnecting, once the TX and RX sides are free.
+    pub async fn replace(&self, transport: TransportLink<Link, Buff>) {
+        let zid = transport.transport().other_zid;
+        let mine_zid = transport.transport().mine_zid;
+
+        self.transport.replace(transport).await;
+
+        self.zid.set(zid);
+        self.mine_zid.set(mine_zid);
+    }
+
+    /// Whether the transport has been closed with `close`.
+    pub fn closed(&self) -> bool {
+        self.closed.get()
     }
 
+    /// Waits for `close` to be called.
+    pub async fn wait_closed(&self) {
+        let epoch = self.closing.epoch();
+        if !self.closed() {
+            self.closing.changed(epoch).await;
+        }
+    }
+
+    /// Sends a `Close` with `reason` to the peer. Sending anything afterwards fails.
+    pub async fn close(&self, reason: u8) -> core::result::Result<(), TransportLinkError> {
+        let mut tx = self.transport.tx().await;
+        self.closed.set(true);
+        self.closing.notify();
+
+        if tx.transport().closed() {
+            return Ok(());
+        }
+
+        tx.close(reason).await
+    }
+
+    /// The TX side, unless the transport is closed.
     pub async fn tx(
         &self,
-    ) -> MutexGuard<'_, NoopRawMutex, TransportLinkTx<'res, Link::Tx<'res>, Buff>> {
-        self.tx.lock().await
+    ) -> core::result::Result<TxGuard<'_, 'res, Link, Buff>, TransportLinkError> {
+        Self::open(self.transport.tx().await)
+    }
+
+    fn open(
+        tx: TxGuard<'_, 'res, Link, Buff>,
+    ) -> core::result::Result<TxGuard<'_, 'res, Link, Buff>, TransportLinkError> {
+        if tx.transport().closed() {
+            return Err(TransportLinkError::TransportClosed);
+        }
+
+        Ok(tx)
+    }
+
+    /// Sends `msg` once the TX side is free. Unless `msg` is express, it is left pending while
+    /// other senders wait for the TX side, so that their messages are batched with it, and the
+    /// last of them sends the batch, or `run` if it gives up waiting. Under
+    /// `CongestionControl::Drop`, `msg` is dropped instead of waiting for the pending batch to
+    /// be sent when it has no room left, and `false` is returned.
+    pub async fn send(
+        &self,
+        msg: NetworkMessage<'_>,
+    ) -> core::result::Result<bool, TransportLinkError>
+    where
+        Buff: AsMut<[u8]> + AsRef<[u8]>,
+    {
+        let waiting = Waiting::new(&self.waiting, &self.flushing);
+        let tx = self.tx().await;
+        waiting.acquired();
+
+        let mut tx = tx?;
+        let express = msg.qos.is_express();
+
+        let batched = if msg.qos.congestion_control() == CongestionControl::Drop
+            && !tx.transport().is_empty()
+        {
+            tx.transport_mut().try_batch(msg.as_ref())
+        } else {
+            tx.batch(core::iter::once(msg)).await?;
+            true
+        };
+
+        if express || self.waiting.get() == 0 {
+            tx.flush().await?;
+        }
+
+        Ok(batched)
     }
 
     pub async fn run<State, E, Update>(
         &self,
         state: &Mutex<NoopRawMutex, State>,
+        mut event: impl FnMut(TransportEvent),
         mut update: Update,
     ) -> core::result::Result<(), EitherError<TransportLinkError, E>>
     where
@@ -64,49 +164,97 @@ where
             &'any [u8],
         ) -> core::result::Result<(), E>,
     {
-        let mut rx = self.rx.lock().await;
+        let mut rx = self.transport.rx().await;
+        let zid = self.zid.get();
+        event(TransportEvent::Opened { zid });
 
         let start = Instant::now();
 
         loop {
+            let flushing = self.flushing.epoch();
             let (write_lease, read_lease) = self.sync(start, start.elapsed(), &mut rx).await;
             if rx.transport().closed() {
+                if let Some(close) = rx.transport().peer_close() {
+                    event(TransportEvent::ClosedByPeer {
+                        reason: close.reason,
+                        behaviour: close.behaviour,
+                    });
+                }
+
                 return Err(EitherError::A(TransportLinkError::TransportClosed));
             }
 
-            match select3(write_lease, read_lease, rx.recv()).await {
-                Either3::First(_) => {
-                    let mut tx_guard = self.tx.lock().await;
+            match select4(
+                write_lease,
+                read_lease,
+                rx.recv(),
+                self.flushing.changed(flushing),
+            )
+            .await
+            {
+                Either4::First(_) => {
+                    let mut tx_guard = self.transport.tx().await;
                     let tx = tx_guard.deref_mut();
 
+                    if let Err(e) = tx.flush().await {
+                        event(TransportEvent::LinkLost);
+                        break Err(EitherError::A(e));
+                    }
+
                     if tx.transport().should_close(start.elapsed().into()) {
-                        // TODO: send Close msg
+                        let _ = tx.close(Close::EXPIRED).await;
+                        event(TransportEvent::LeaseExpired);
                         break Err(EitherError::A(TransportLinkError::TransportClosed));
                     }
 
                     if tx.transport().should_send_keepalive(start.elapsed().into()) {
                         zenoh_proto::trace!("Sending Keepalive");
-                        tx.keepalive().await?;
+                        if let Err(e) = tx.keepalive().await {
+                            event(TransportEvent::LinkLost);
+                            break Err(EitherError::A(e));
+                        }
                     }
 
                     continue;
                 }
-                Either3::Third(res) => {
-                    let mut state = state.lock().await;
+                Either4::Third(res) => {
+                    let msgs = match res {
+                        Ok(msgs) => msgs,
+                        Err(e) => {
+                            event(TransportEvent::LinkLost);
+                            break Err(EitherError::A(e));
+                        }
+                    };
 
-                    for msg in res? {
-                        update(self.zid, &mut state, msg.0, msg.1)
+                    let mut state = state.lock().await;
+                    for msg in msgs {
+                        update(zid, &mut state, msg.0, msg.1)
                             .await
                             .map_err(EitherError::B)?;
                     }
 
+                    let missed = rx.transport_mut().take_missed();
+                    if missed != 0 {
+                        event(TransportEvent::SnGap { missed });
+                    }
+
+                    continue;
+                }
+                // The sender that was to send the pending batch gave up waiting.
+                Either4::Fourth(()) => {
+                    if let Err(e) = self.transport.tx().await.flush().await {
+                        event(TransportEvent::LinkLost);
+                        break Err(EitherError::A(e));
+                    }
+
                     continue;
                 }
                 _ => {}
             }
 
             if rx.transport().should_close(start.elapsed().into()) {
-                // TODO: Try send Close msg
+                let _ = self.transport.tx().await.close(Close::EXPIRED).await;
+                event(TransportEvent::LeaseExpired);
                 break Err(EitherError::A(TransportLinkError::TransportClosed));
             }
         }
@@ -118,7 +266,7 @@ where
         now: Duration,
         rx: &mut TransportLinkRx<'res, Link::Rx<'res>, Buff>,
     ) -> (Timer, Timer) {
-        let mut tx_guard = self.tx.lock().await;
+        let mut tx_guard = self.transport.tx().await;
         let tx = tx_guard.deref_mut();
 
         rx.transport_mut().sync(Some(tx.transport()), now.into());
@@ -130,3 +278,38 @@ where
         (Timer::at(write_lease), Timer::at(read_lease))
     }
 }
+
+/// Counts a sender waiting for the TX side, until dropped. The pending batch may have been left
+/// to this sender, so the last one to give up waiting, e.g. because its future is dropped, has
+/// `run` send it.
+struct Waiting<'a> {
+    waiting: &'a Cell<usize>,
+    flushing: &'a Notify,
+    acquired: bool,
+}
+
+impl<'a> Waiting<'a> {
+    fn new(waiting: &'a Cell<usize>, flushing: &'a Notify) -> Self {
+        waiting.set(waiting.get() + 1);
+        Self {
+            waiting,
+            flushing,
+            acquired: false,
+        }
+    }
+
+    /// The sender got the TX side, it sends the pending batch itself if needed.
+    fn acquired(mut self) {
+        self.acquired = true;
+    }
+}
+
+impl Drop for Waiting<'_> {
+    fn drop(&mut self) {
+        self.waiting.set(self.waiting.get() - 1);
+
+        if !self.acquired && self.waiting.get() == 0 {
+            self.flushing.notify();
+        }
+    }
+}
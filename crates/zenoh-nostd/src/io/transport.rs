@@ -1,11 +1,11 @@
 use core::time::Duration;
 
-use embassy_time::with_timeout;
+use embassy_time::{Instant, Timer, with_timeout};
 use zenoh_proto::{
     Endpoint, TransportLinkError,
     fields::{Resolution, ZenohIdProto},
 };
-use zenoh_sansio::{Transport, ZTransportRx, ZTransportTx};
+use zenoh_sansio::{Transport, TransportBuilder, UsrPwd, ZTransportRx, ZTransportTx};
 
 use crate::io::link::EmbeddedIOLink;
 
@@ -83,6 +83,14 @@ pub struct TransportLinkManager<LinkManager> {
     zid: ZenohIdProto,
     lease: Duration,
     resolution: Resolution,
+    batch_size: Option<u16>,
+    qos: bool,
+    usrpwd: Option<UsrPwd>,
+    usrpwd_dictionary: &'static [UsrPwd],
+    #[cfg(feature = "compression")]
+    compression: bool,
+    defrag: bool,
+    reconnect_backoff: (Duration, Duration),
 }
 
 impl<LinkManager> From<LinkManager> for TransportLinkManager<LinkManager> {
@@ -111,6 +119,97 @@ impl<LinkManager> TransportLinkManager<LinkManager> {
             zid,
             lease,
             resolution,
+            batch_size: None,
+            qos: false,
+            usrpwd: None,
+            usrpwd_dictionary: &[],
+            #[cfg(feature = "compression")]
+            compression: false,
+            defrag: false,
+            reconnect_backoff: (Duration::from_secs(1), Duration::from_secs(8)),
+        }
+    }
+
+    /// Caps the batch size advertised to the peer.
+    pub fn with_batch_size(mut self, batch_size: u16) -> Self {
+        self.batch_size = Some(batch_size);
+        self
+    }
+
+    /// Advertises QoS support so that each priority gets its own sequence numbers.
+    pub fn with_qos(mut self, qos: bool) -> Self {
+        self.qos = qos;
+        self
+    }
+
+    /// Authenticates with `usrpwd` when connecting to a router that requires it.
+    pub fn with_usrpwd(mut self, usrpwd: UsrPwd) -> Self {
+        self.usrpwd = Some(usrpwd);
+        self
+    }
+
+    /// Only accepts the peers that authenticate with one of these credentials when listening.
+    pub fn with_usrpwd_dictionary(mut self, dictionary: &'static [UsrPwd]) -> Self {
+        self.usrpwd_dictionary = dictionary;
+        self
+    }
+
+    /// Negotiates the LZ4 compression of the batches. A clone of the session buffer is used as
+    /// scratch buffer to compress and decompress them.
+    #[cfg(feature = "compression")]
+    pub fn with_compression(mut self, compression: bool) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// Reassembles the fragmented messages in a clone of the session buffer, which bounds their
+    /// size. Otherwise, fragmented messages are dropped.
+    pub fn with_defrag(mut self, defrag: bool) -> Self {
+        self.defrag = defrag;
+        self
+    }
+
+    /// Waits `min` after a failed reconnection attempt, doubling the delay up to `max`.
+    pub fn with_reconnect_backoff(mut self, min: Duration, max: Duration) -> Self {
+        self.reconnect_backoff = (min, max);
+        self
+    }
+
+    fn builder<Buff>(&self, buff: Buff) -> TransportBuilder<Buff>
+    where
+        Buff: AsRef<[u8]> + Clone,
+    {
+        #[cfg(feature = "compression")]
+        let scratch = self.compression.then(|| buff.clone());
+        let defrag = self.defrag.then(|| buff.clone());
+
+        let builder = Transport::builder(buff)
+            .with_zid(self.zid)
+            .with_lease(self.lease)
+            .with_resolution(self.resolution)
+            .with_qos(self.qos)
+            .with_clock(|| Duration::from_micros(Instant::now().as_micros()))
+            .with_usrpwd_dictionary(self.usrpwd_dictionary);
+
+        let builder = match self.usrpwd {
+            Some(usrpwd) => builder.with_usrpwd(usrpwd),
+            None => builder,
+        };
+
+        #[cfg(feature = "compression")]
+        let builder = match scratch {
+            Some(scratch) => builder.with_compression(scratch),
+            None => builder,
+        };
+
+        let builder = match defrag {
+            Some(defrag) => builder.with_defrag(defrag),
+            None => builder,
+        };
+
+        match self.batch_size {
+            Some(batch_size) => builder.with_batch_size(batch_size),
+            None => builder,
         }
     }
 
@@ -125,10 +224,7 @@ impl<LinkManager> TransportLinkManager<LinkManager> {
     {
         let connect = async || {
             let streamed = link.is_streamed();
-            Transport::builder(buff)
-                .with_zid(self.zid)
-                .with_lease(self.lease)
-                .with_resolution(self.resolution)
+            self.builder(buff)
                 .connect_async(
                     &mut link,
                     async |link, bytes| {
@@ -164,10 +260,7 @@ impl<LinkManager> TransportLinkManager<LinkManager> {
     {
         let connect = async || {
             let streamed = link.is_streamed();
-            Transport::builder(buff)
-                .with_zid(self.zid)
-                .with_lease(self.lease)
-                .with_resolution(self.resolution)
+            self.builder(buff)
                 .listen_async(
                     &mut link,
                     async |link, bytes| {
@@ -205,10 +298,7 @@ impl<LinkManager> TransportLinkManager<LinkManager> {
 
         let connect = async || {
             let streamed = link.is_streamed();
-            Transport::builder(buff)
-                .with_zid(self.zid)
-                .with_lease(self.lease)
-                .with_resolution(self.resolution)
+            self.builder(buff)
                 .connect_async(
                     &mut link,
                     async |link, bytes| {
@@ -233,6 +323,36 @@ impl<LinkManager> TransportLinkManager<LinkManager> {
         Ok(TransportLink::new(link, transport))
     }
 
+    /// Connects to `endpoint`, retrying with backoff until it succeeds.
+    pub async fn reconnect<Buff>(
+        &self,
+        endpoint: Endpoint<'_>,
+        buff: Buff,
+    ) -> TransportLink<LinkManager::Link<'_>, Buff>
+    where
+        LinkManager: ZLinkManager,
+        Buff: AsMut<[u8]> + AsRef<[u8]> + Clone,
+    {
+        let (mut backoff, max) = self.reconnect_backoff;
+
+        loop {
+            match self.connect(endpoint.clone(), buff.clone()).await {
+                Ok(transport) => return transport,
+                Err(e) => {
+                    zenoh_proto::warn!(
+                        "{}: Couldn't reconnect ({}), retrying in {}ms",
+                        zenoh_proto::zctx!(),
+                        e,
+                        backoff.as_millis() as u64
+                    );
+                }
+            }
+
+            Timer::after(backoff.try_into().unwrap()).await;
+            backoff = (backoff * 2).min(max);
+        }
+    }
+
     pub async fn listen<Buff>(
         &self,
         endpoint: Endpoint<'_>,
@@ -245,10 +365,7 @@ impl<LinkManager> TransportLinkManager<LinkManager> {
         let mut link = self.link_manager.listen(endpoint).await?;
         let listen = async || {
             let streamed = link.is_streamed();
-            Transport::builder(buff)
-                .with_zid(self.zid)
-                .with_lease(self.lease)
-                .with_resolution(self.resolution)
+            self.builder(buff)
                 .listen_async(
                     &mut link,
                     async |link, bytes| {
@@ -14,14 +14,32 @@ pub trait ZTransportLinkTx {
         msgs: impl Iterator<Item = NetworkMessage<'a>>,
     ) -> impl Future<Output = core::result::Result<(), zenoh_proto::TransportLinkError>> {
         let (link, transport) = self.tx();
-        transport.encode(msgs);
 
         async move {
-            if let Some(bytes) = transport.flush(link.is_streamed()) {
-                link.write_all(bytes).await.map_err(|e| e.into())
-            } else {
-                Ok(())
-            }
+            let streamed = link.is_streamed();
+
+            transport
+                .encode_with_async(msgs, streamed, async |bytes| link.write_all(bytes).await)
+                .await
+                .map_err(|e| e.flatten_map::<TransportLinkError>())
+        }
+    }
+
+    /// Same as `send`, but leaves the last batch pending, unless the last message is express,
+    /// so that the next messages can be added to it.
+    fn batch<'a>(
+        &mut self,
+        msgs: impl Iterator<Item = NetworkMessage<'a>>,
+    ) -> impl Future<Output = core::result::Result<(), zenoh_proto::TransportLinkError>> {
+        let (link, transport) = self.tx();
+
+        async move {
+            let streamed = link.is_streamed();
+
+            transport
+                .batch_with_async(msgs, streamed, async |bytes| link.write_all(bytes).await)
+                .await
+                .map_err(|e| e.flatten_map::<TransportLinkError>())
         }
     }
 
@@ -56,6 +74,22 @@ pub trait ZTransportLinkTx {
             }
         }
     }
+
+    fn close(
+        &mut self,
+        reason: u8,
+    ) -> impl Future<Output = core::result::Result<(), zenoh_proto::TransportLinkError>> {
+        let (link, transport) = self.tx();
+        transport.close(reason);
+
+        async move {
+            if let Some(bytes) = transport.flush(link.is_streamed()) {
+                link.write_all(bytes).await.map_err(|e| e.into())
+            } else {
+                Ok(())
+            }
+        }
+    }
 }
 
 pub trait ZTransportLinkRx {
@@ -1,3 +1,4 @@
+use zenoh_proto::TransportLinkError;
 use zenoh_sansio::{TransportTx, ZTransportTx};
 
 use super::{ZLinkTx, ZTransportLinkTx};
@@ -21,6 +22,24 @@ impl<'transport, LinkTx, Buff> TransportLinkTx<'transport, LinkTx, Buff> {
     }
 }
 
+impl<'transport, LinkTx, Buff> TransportLinkTx<'transport, LinkTx, Buff>
+where
+    LinkTx: ZLinkTx,
+    Buff: AsMut<[u8]> + AsRef<[u8]>,
+{
+    /// Sends the pending batch, if any.
+    pub async fn flush(&mut self) -> core::result::Result<(), TransportLinkError> {
+        if self.transport.is_empty() {
+            return Ok(());
+        }
+
+        match self.transport.flush(self.link.is_streamed()) {
+            Some(bytes) => self.link.write_all(bytes).await.map_err(|e| e.into()),
+            None => Ok(()),
+        }
+    }
+}
+
 impl<'transport, LinkTx, Buff> ZTransportLinkTx for TransportLinkTx<'transport, LinkTx, Buff>
 where
     LinkTx: ZLinkTx,
@@ -7,20 +7,38 @@ mod api;
 
 mod config;
 mod io;
+mod notify;
 mod resources;
 
+#[cfg(test)]
+mod tests;
+
 pub mod session {
     pub use super::config::ZSessionConfig;
     pub use super::io::transport::TransportLinkManager;
     pub use super::resources::Resources;
-    pub use zenoh_proto::{Endpoint, Error};
+    pub use zenoh_proto::{
+        Endpoint, Error,
+        exts::{QueryTarget, SourceInfo},
+        fields::{ConsolidationMode, Encoding, Timestamp},
+        msgs::CloseBehaviour,
+    };
+    pub use zenoh_sansio::UsrPwd;
 
     pub use super::api::{
+        consolidation::*,
+        events::*,
+        keyexprs::*,
+        matching::*,
+        pending::*,
         query::*,
         response::*,
         sample::*,
         session::Session,
-        session::{get::*, r#pub::*, put::*, querier::*, queryable::*, sub::*},
+        session::{
+            delete::*, get::*, liveliness::*, r#pub::*, put::*, querier::*, queryable::*, reply::*,
+            sub::*,
+        },
     };
 
     pub mod zenoh {
@@ -28,10 +46,14 @@ pub mod session {
         pub use super::super::api::session::{
             session_connect as connect,
             session_connect_ignore_invalid_sn as connect_ignore_invalid_sn,
-            session_listen as listen, session_listen_ignore_invalid_sn as listen_ignore_invalid_sn,
+            session_connect_reconnecting as connect_reconnecting, session_listen as listen,
+            session_listen_ignore_invalid_sn as listen_ignore_invalid_sn,
         };
 
-        pub use crate::{__session_connect as connect, __session_listen as listen};
+        pub use crate::{
+            __session_connect as connect, __session_connect_reconnecting as connect_reconnecting,
+            __session_listen as listen,
+        };
 
         pub use zenoh_proto::{debug, error, info, keyexpr, trace, warn, zbail};
 
@@ -44,6 +66,7 @@ pub mod broker {
     pub use super::config::ZBrokerConfig;
     pub use super::io::transport::TransportLinkManager;
     pub use zenoh_proto::{Endpoint, Error};
+    pub use zenoh_sansio::UsrPwd;
 
     pub use super::api::broker::Broker;
 
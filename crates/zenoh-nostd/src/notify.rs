@@ -0,0 +1,49 @@
+use core::{
+    cell::{Cell, RefCell},
+    task::Poll,
+};
+
+use embassy_sync::waitqueue::MultiWakerRegistration;
+
+/// How many waiting tasks are tracked one by one. Past that, all of them are woken each time one
+/// more waits, so that none misses a notification.
+const WAKERS: usize = 8;
+
+/// Wakes the tasks waiting for something to change, e.g. a get to complete. Waiters compare a
+/// counter instead of receiving the changes, so that none can be lost.
+pub(crate) struct Notify {
+    epoch: Cell<u32>,
+    wakers: RefCell<MultiWakerRegistration<WAKERS>>,
+}
+
+impl Notify {
+    pub(crate) fn new() -> Self {
+        Self {
+            epoch: Cell::new(0),
+            wakers: RefCell::new(MultiWakerRegistration::new()),
+        }
+    }
+
+    /// The current epoch. Read it before checking what may change, then wait with `changed`.
+    pub(crate) fn epoch(&self) -> u32 {
+        self.epoch.get()
+    }
+
+    pub(crate) fn notify(&self) {
+        self.epoch.set(self.epoch.get().wrapping_add(1));
+        self.wakers.borrow_mut().wake();
+    }
+
+    /// Waits for a notification after `epoch`.
+    pub(crate) async fn changed(&self, epoch: u32) {
+        core::future::poll_fn(|cx| {
+            if self.epoch.get() != epoch {
+                return Poll::Ready(());
+            }
+
+            self.wakers.borrow_mut().register(cx.waker());
+            Poll::Pending
+        })
+        .await
+    }
+}
@@ -1,6 +1,15 @@
-use core::hint::unreachable_unchecked;
+use core::{cell::UnsafeCell, hint::unreachable_unchecked};
 
-use crate::{config::ZSessionConfig, io::transport::TransportLink, platform::ZLinkManager};
+use embassy_sync::{
+    blocking_mutex::raw::NoopRawMutex,
+    mutex::{MappedMutexGuard, Mutex, MutexGuard},
+};
+
+use crate::{
+    config::ZSessionConfig,
+    io::transport::{TransportLink, TransportLinkRx, TransportLinkTx},
+    platform::{ZLink, ZLinkManager},
+};
 
 pub struct Resources<'res, Config>
 where
@@ -50,3 +59,82 @@ where
         }
     }
 }
+
+pub(crate) type TxGuard<'a, 'res, Link, Buff> =
+    MappedMutexGuard<'a, NoopRawMutex, TransportLinkTx<'res, <Link as ZLink>::Tx<'res>, Buff>>;
+
+pub(crate) type RxGuard<'a, 'res, Link, Buff> =
+    MappedMutexGuard<'a, NoopRawMutex, TransportLinkRx<'res, <Link as ZLink>::Rx<'res>, Buff>>;
+
+/// The transport kept in `Resources`, lent out as its TX and RX halves. `replace` moves another
+/// transport in once both halves are released.
+pub(crate) struct TransportSlot<'res, Link, Buff>
+where
+    Link: ZLink + 'res,
+{
+    transport: &'res UnsafeCell<TransportLink<Link, Buff>>,
+    // Only `None` while `replace` swaps the transport.
+    tx: Mutex<NoopRawMutex, Option<TransportLinkTx<'res, Link::Tx<'res>, Buff>>>,
+    rx: Mutex<NoopRawMutex, Option<TransportLinkRx<'res, Link::Rx<'res>, Buff>>>,
+}
+
+impl<'res, Link, Buff> TransportSlot<'res, Link, Buff>
+where
+    Link: ZLink,
+{
+    pub(crate) fn new(transport: &'res mut TransportLink<Link, Buff>) -> Self {
+        let transport: &'res UnsafeCell<_> = UnsafeCell::from_mut(transport);
+        let (tx, rx) = Self::split(transport);
+
+        Self {
+            transport,
+            tx: Mutex::new(Some(tx)),
+            rx: Mutex::new(Some(rx)),
+        }
+    }
+
+    pub(crate) async fn tx(&self) -> TxGuard<'_, 'res, Link, Buff> {
+        Self::some(self.tx.lock().await)
+    }
+
+    pub(crate) async fn rx(&self) -> RxGuard<'_, 'res, Link, Buff> {
+        Self::some(self.rx.lock().await)
+    }
+
+    /// Drops the current transport for `transport`, once the TX and RX sides are free.
+    pub(crate) async fn replace(&self, transport: TransportLink<Link, Buff>) {
+        let mut tx = self.tx.lock().await;
+        let mut rx = self.rx.lock().await;
+
+        *tx = None;
+        *rx = None;
+
+        // SAFETY: the halves borrowing the previous transport have just been dropped, and both
+        // locks are held until the new ones are in place.
+        unsafe { *self.transport.get() = transport };
+
+        let (new_tx, new_rx) = Self::split(self.transport);
+        *tx = Some(new_tx);
+        *rx = Some(new_rx);
+    }
+
+    fn split(
+        transport: &'res UnsafeCell<TransportLink<Link, Buff>>,
+    ) -> (
+        TransportLinkTx<'res, Link::Tx<'res>, Buff>,
+        TransportLinkRx<'res, Link::Rx<'res>, Buff>,
+    ) {
+        // SAFETY: the transport is only reached through the halves, which never leave the slot,
+        // and `replace` drops them before reaching it again.
+        unsafe { &mut *transport.get() }.split()
+    }
+
+    fn some<T>(
+        guard: MutexGuard<'_, NoopRawMutex, Option<T>>,
+    ) -> MappedMutexGuard<'_, NoopRawMutex, T> {
+        MutexGuard::map(guard, |inner| match inner {
+            Some(inner) => inner,
+            None => unreachable!(),
+        })
+    }
+}
@@ -0,0 +1,3 @@
+mod consolidation;
+mod reconnect;
+mod sample;
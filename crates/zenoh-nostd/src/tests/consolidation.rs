@@ -0,0 +1,41 @@
+use zenoh_proto::{CollectionError, fields::ConsolidationMode, keyexpr};
+
+use crate::api::{
+    consolidation::{FixedCapacityConsolidations, ZConsolidations},
+    sample::Sample,
+};
+
+#[test]
+fn consolidation_latest_needs_capacity() {
+    let mut consolidations = FixedCapacityConsolidations::<4, 8, 32, 0>::empty();
+    assert!(matches!(
+        consolidations.insert(1, ConsolidationMode::Latest),
+        Err(CollectionError::CollectionTooSmall)
+    ));
+    assert!(consolidations.mode(1).is_none());
+    assert!(
+        consolidations
+            .insert(1, ConsolidationMode::Monotonic)
+            .is_ok()
+    );
+
+    let mut consolidations = FixedCapacityConsolidations::<4, 0, 32, 32>::empty();
+    assert!(matches!(
+        consolidations.insert(1, ConsolidationMode::Latest),
+        Err(CollectionError::CollectionTooSmall)
+    ));
+}
+
+#[test]
+fn consolidation_latest_keeps_replies() {
+    let ke = keyexpr::new("demo/example").unwrap();
+
+    let mut consolidations = FixedCapacityConsolidations::<4, 8, 32, 32>::empty();
+    consolidations.insert(1, ConsolidationMode::Latest).unwrap();
+    consolidations.keep(1, &Sample::new(ke, b"first")).unwrap();
+    consolidations.keep(1, &Sample::new(ke, b"second")).unwrap();
+
+    let mut replies = consolidations.replies(1);
+    assert_eq!(replies.next().unwrap().payload(), b"second");
+    assert!(replies.next().is_none());
+}
@@ -0,0 +1,285 @@
+extern crate std;
+
+use core::{cell::Cell, convert::Infallible, time::Duration};
+use std::{sync::mpsc, vec::Vec};
+
+use embassy_futures::{
+    join::join,
+    select::{Either, select},
+};
+use embassy_sync::{blocking_mutex::raw::NoopRawMutex, channel::Channel, pipe::Pipe};
+use zenoh_proto::keyexpr;
+
+use crate::{
+    platform::{Endpoint, LinkError, ZLink, ZLinkInfo, ZLinkManager, ZLinkRx, ZLinkTx},
+    session::{zenoh::storage::RawOrBox, *},
+};
+
+const BUFF_SIZE: usize = 1024;
+
+/// Both directions of an in-memory link.
+struct Wire {
+    connector: Pipe<NoopRawMutex, 4096>,
+    listener: Pipe<NoopRawMutex, 4096>,
+}
+
+impl Wire {
+    fn new() -> Self {
+        Self {
+            connector: Pipe::new(),
+            listener: Pipe::new(),
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+struct LoopbackLink<'a> {
+    tx: &'a Pipe<NoopRawMutex, 4096>,
+    rx: &'a Pipe<NoopRawMutex, 4096>,
+}
+
+impl ZLinkInfo for LoopbackLink<'_> {
+    fn mtu(&self) -> u16 {
+        BUFF_SIZE as u16
+    }
+
+    fn is_streamed(&self) -> bool {
+        true
+    }
+}
+
+impl ZLinkTx for LoopbackLink<'_> {
+    async fn write_all(&mut self, buffer: &[u8]) -> core::result::Result<(), LinkError> {
+        let mut written = 0;
+        while written < buffer.len() {
+            written += self.tx.write(&buffer[written..]).await;
+        }
+
+        Ok(())
+    }
+}
+
+impl ZLinkRx for LoopbackLink<'_> {
+    async fn read(&mut self, buffer: &mut [u8]) -> core::result::Result<usize, LinkError> {
+        Ok(self.rx.read(buffer).await)
+    }
+
+    async fn read_exact(&mut self, buffer: &mut [u8]) -> core::result::Result<(), LinkError> {
+        let mut read = 0;
+        while read < buffer.len() {
+            read += self.rx.read(&mut buffer[read..]).await;
+        }
+
+        Ok(())
+    }
+}
+
+impl<'a> ZLink for LoopbackLink<'a> {
+    type Tx<'link>
+        = LoopbackLink<'a>
+    where
+        Self: 'link;
+
+    type Rx<'link>
+        = LoopbackLink<'a>
+    where
+        Self: 'link;
+
+    fn split(&mut self) -> (Self::Tx<'_>, Self::Rx<'_>) {
+        (*self, *self)
+    }
+}
+
+/// Opens a link on each wire in turn, whatever the endpoint.
+struct LoopbackLinkManager<'w> {
+    wires: &'w [Wire],
+    next: Cell<usize>,
+}
+
+impl LoopbackLinkManager<'_> {
+    fn take(&self) -> core::result::Result<&Wire, LinkError> {
+        let wire = self
+            .wires
+            .get(self.next.get())
+            .ok_or(LinkError::CouldNotConnect)?;
+        self.next.set(self.next.get() + 1);
+
+        Ok(wire)
+    }
+}
+
+impl ZLinkManager for LoopbackLinkManager<'_> {
+    type Link<'a>
+        = LoopbackLink<'a>
+    where
+        Self: 'a;
+
+    async fn connect(&self, _: Endpoint<'_>) -> core::result::Result<Self::Link<'_>, LinkError> {
+        let wire = self.take()?;
+
+        Ok(LoopbackLink {
+            tx: &wire.connector,
+            rx: &wire.listener,
+        })
+    }
+
+    async fn listen(&self, _: Endpoint<'_>) -> core::result::Result<Self::Link<'_>, LinkError> {
+        let wire = self.take()?;
+
+        Ok(LoopbackLink {
+            tx: &wire.listener,
+            rx: &wire.connector,
+        })
+    }
+}
+
+struct TestConfig<'w> {
+    transports: TransportLinkManager<LoopbackLinkManager<'w>>,
+}
+
+impl<'w> TestConfig<'w> {
+    fn new(wires: &'w [Wire]) -> Self {
+        Self {
+            transports: TransportLinkManager::from(LoopbackLinkManager {
+                wires,
+                next: Cell::new(0),
+            })
+            .with_reconnect_backoff(Duration::from_millis(10), Duration::from_millis(100)),
+        }
+    }
+}
+
+impl<'w> ZSessionConfig for TestConfig<'w> {
+    type Buff = [u8; BUFF_SIZE];
+    type LinkManager = LoopbackLinkManager<'w>;
+
+    type SubCallbacks<'res> = FixedCapacitySubCallbacks<'res, 4, RawOrBox<64>, RawOrBox<2048>>;
+    type GetCallbacks<'res> = FixedCapacityGetCallbacks<'res, 4, RawOrBox<1>, RawOrBox<32>>;
+    type Consolidations = FixedCapacityConsolidations<4, 8, 128, 128>;
+    type QueryableCallbacks<'res>
+        = FixedCapacityQueryableCallbacks<'res, Self, 4, RawOrBox<32>, RawOrBox<952>>
+    where
+        Self: 'res;
+
+    type LivelinessCallbacks<'res> =
+        FixedCapacitySubCallbacks<'res, 4, RawOrBox<64>, RawOrBox<2048>>;
+
+    type KeyExprs<'res> = FixedCapacityKeyExprs<'res, 8>;
+    type Tokens<'res> = FixedCapacityKeyExprs<'res, 4>;
+    type Matchings<'res> = FixedCapacityMatchings<'res, 8, 128>;
+    type DroppedTokens = FixedCapacityPending<u16, 4>;
+    type CancelledGets = FixedCapacityPending<(u32, bool), 4>;
+    type RemoteKeyExprs = FixedCapacityRemoteKeyExprs<8, 128>;
+    type RemoteDeclarations = FixedCapacityRemoteDeclarations<8, 128>;
+
+    fn transports(&self) -> &TransportLinkManager<Self::LinkManager> {
+        &self.transports
+    }
+
+    fn buff(&self) -> Self::Buff {
+        [0; BUFF_SIZE]
+    }
+}
+
+/// A publisher declared before its peer goes away puts with the id it was given, which the
+/// next peer resolves once the session reconnects. Returns the payload received there.
+async fn publisher_scope_after_reconnect() -> core::result::Result<Option<Vec<u8>>, Error> {
+    let wires = [Wire::new(), Wire::new()];
+    let endpoint = Endpoint::try_from("tcp/127.0.0.1:7447")?;
+
+    let a_config = TestConfig::new(&wires);
+    let b1_config = TestConfig::new(&wires[..1]);
+    let b2_config = TestConfig::new(&wires[1..]);
+
+    let channel = Channel::<NoopRawMutex, FixedCapacitySample<128, 128>, 4>::new();
+
+    let mut a_resources = Resources::default();
+    let mut b1_resources = Resources::default();
+    let mut b2_resources = Resources::default();
+
+    let (a, b1) = join(
+        zenoh::connect_reconnecting(&mut a_resources, &a_config, endpoint.clone()),
+        zenoh::listen(&mut b1_resources, &b1_config, endpoint.clone()),
+    )
+    .await;
+    let (a, b1) = (a?, b1?);
+
+    let ke = keyexpr::new("test/reconnect")?;
+
+    let runs = async {
+        let _ = join(a.run(), b1.run()).await;
+        core::future::pending::<Infallible>().await
+    };
+
+    let scenario = async {
+        let publisher = a.declare_publisher(ke).finish().await?;
+
+        // `a` reconnects to `b2` once `b1` is gone.
+        let (closed, b2) = join(
+            b1.close(),
+            zenoh::listen(&mut b2_resources, &b2_config, endpoint),
+        )
+        .await;
+        let (_, b2) = (closed?, b2?);
+
+        let subscriber = b2
+            .declare_subscriber(ke)
+            .channel(channel.dyn_sender(), channel.dyn_receiver())
+            .finish()
+            .await?;
+
+        let b2_run = async {
+            let _ = b2.run().await;
+            core::future::pending::<Infallible>().await
+        };
+
+        let received = async {
+            for _ in 0..50 {
+                // The puts fail or are dropped by `b2` until `a` has declared the publisher
+                // again.
+                let _ = publisher.put(b"scoped").finish().await;
+
+                let timeout = embassy_time::Duration::from_millis(100);
+                if let Ok(Some(sample)) =
+                    embassy_time::with_timeout(timeout, subscriber.recv()).await
+                {
+                    return Some(sample.payload().to_vec());
+                }
+            }
+
+            None
+        };
+
+        match select(b2_run, received).await {
+            Either::First(never) => match never {},
+            Either::Second(received) => Ok::<_, Error>(received),
+        }
+    };
+
+    match select(runs, scenario).await {
+        Either::First(never) => match never {},
+        Either::Second(received) => received,
+    }
+}
+
+#[embassy_executor::task]
+async fn reconnect_task(result: mpsc::Sender<Option<Vec<u8>>>) {
+    let _ = result.send(publisher_scope_after_reconnect().await.unwrap());
+}
+
+#[test]
+fn reconnect_declares_publisher_scope() {
+    let (sender, receiver) = mpsc::channel();
+
+    // The executor never returns, it is left running once the test is over.
+    std::thread::spawn(move || {
+        let executor =
+            std::boxed::Box::leak(std::boxed::Box::new(embassy_executor::Executor::new()));
+        executor.run(|spawner| spawner.spawn(reconnect_task(sender)).unwrap());
+    });
+
+    let received = receiver
+        .recv_timeout(Duration::from_secs(30))
+        .expect("the session did not reconnect");
+    assert_eq!(received.as_deref(), Some(&b"scoped"[..]));
+}
@@ -0,0 +1,44 @@
+use zenoh_proto::{CollectionError, fields::Encoding, keyexpr};
+
+use crate::api::sample::{FixedCapacitySample, Sample};
+
+fn sample<'a>(ke: &'a keyexpr) -> Sample<'a> {
+    Sample::new(ke, b"payload")
+        .with_encoding(Encoding {
+            id: 7,
+            schema: Some(&b"schema"[..]),
+        })
+        .with_attachment(Some(&b"attachment"[..]))
+}
+
+#[test]
+fn sample_default_capacities() {
+    let ke = keyexpr::new("demo/example").unwrap();
+    let sample = sample(ke);
+
+    let owned = FixedCapacitySample::<32, 32>::try_from(&sample).unwrap();
+    assert_eq!(owned.keyexpr(), ke);
+    assert_eq!(owned.payload(), b"payload");
+    assert_eq!(owned.encoding().id, 7);
+    assert!(owned.encoding().schema.is_none());
+    assert!(owned.attachment().is_none());
+}
+
+#[test]
+fn sample_optional_capacities() {
+    let ke = keyexpr::new("demo/example").unwrap();
+    let sample = sample(ke);
+
+    let owned = FixedCapacitySample::<32, 32, 16, 8>::try_from(&sample).unwrap();
+    assert_eq!(owned.encoding().schema, Some(&b"schema"[..]));
+    assert_eq!(owned.attachment(), Some(&b"attachment"[..]));
+
+    assert!(matches!(
+        FixedCapacitySample::<32, 32, 4, 8>::try_from(&sample),
+        Err(CollectionError::CollectionTooSmall)
+    ));
+    assert!(matches!(
+        FixedCapacitySample::<32, 4>::try_from(&sample),
+        Err(CollectionError::CollectionTooSmall)
+    ));
+}
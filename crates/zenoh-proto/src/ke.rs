@@ -1,7 +1,15 @@
 use core::ops::Deref;
 
+mod format;
+mod tree;
+
+pub use format::*;
+pub use tree::*;
+
 const DELIMITER: u8 = b'/';
 const SINGLE_WILD: u8 = b'*';
+const DOUBLE_WILD: &[u8] = b"**";
+const STAR_DSL: &[u8] = b"$*";
 
 #[allow(non_camel_case_types)]
 #[repr(transparent)]
@@ -102,6 +110,46 @@ impl keyexpr {
         Ok(keyexpr::from_str_unchecked(v))
     }
 
+    /// Canonizes `v` in place, then validates the canonical form. `**/**` collapses into `**`,
+    /// `**/*` becomes `*/**`, a chunk made of `$*` only becomes `*` and `$*$*` becomes `$*`.
+    pub fn autocanonize(v: &mut str) -> core::result::Result<&'_ Self, crate::KeyexprError> {
+        // Only whole ASCII sequences are dropped or moved, the rest stays valid UTF-8.
+        let len = canonize(unsafe { v.as_bytes_mut() });
+        keyexpr::new(&v[..len])
+    }
+
+    /// Writes `self/other` into `buf` and canonizes the result.
+    pub fn join<'b>(
+        &self,
+        other: &str,
+        buf: &'b mut [u8],
+    ) -> core::result::Result<&'b Self, crate::KeyexprError> {
+        keyexpr::autocanonize(write_into(buf, &[self.as_str(), "/", other])?)
+    }
+
+    /// Writes `self` immediately followed by `other` into `buf`. Wildcards at the boundary are
+    /// refused, as merging them would silently change the meaning of both sides.
+    pub fn concat<'b>(
+        &self,
+        other: &str,
+        buf: &'b mut [u8],
+    ) -> core::result::Result<&'b Self, crate::KeyexprError> {
+        if self.ends_with('*') && other.starts_with('*') {
+            crate::zbail!(
+                crate::KeyexprError::StarInChunk,
+                "concatenating '{}' and '{}' would merge their wildcards",
+                self,
+                other
+            );
+        }
+
+        keyexpr::new(write_into(buf, &[self.as_str(), other])?)
+    }
+
+    pub fn is_wild(&self) -> bool {
+        self.is_wild_impl()
+    }
+
     pub(crate) fn is_wild_impl(&self) -> bool {
         self.0.contains(SINGLE_WILD as char)
     }
@@ -164,7 +212,54 @@ impl PartialEq<keyexpr> for str {
 #[derive(PartialEq, Eq, Hash, PartialOrd, Ord, Debug)]
 pub struct nonwild_keyexpr(keyexpr);
 
-impl nonwild_keyexpr {}
+impl nonwild_keyexpr {
+    pub fn new(v: &str) -> core::result::Result<&'_ Self, crate::KeyexprError> {
+        keyexpr::new(v)?.try_into()
+    }
+
+    pub const fn as_keyexpr(&self) -> &keyexpr {
+        &self.0
+    }
+
+    pub const fn as_str(&self) -> &str {
+        self.0.as_str()
+    }
+
+    pub const fn from_str_unchecked(s: &str) -> &Self {
+        unsafe { core::mem::transmute(s) }
+    }
+}
+
+impl core::fmt::Display for nonwild_keyexpr {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for nonwild_keyexpr {
+    fn format(&self, fmt: defmt::Formatter) {
+        defmt::write!(fmt, "ke`{}`", self.as_str())
+    }
+}
+
+impl AsRef<keyexpr> for nonwild_keyexpr {
+    fn as_ref(&self) -> &keyexpr {
+        &self.0
+    }
+}
+
+impl AsRef<str> for nonwild_keyexpr {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl<'a> From<&'a nonwild_keyexpr> for &'a keyexpr {
+    fn from(v: &'a nonwild_keyexpr) -> Self {
+        &v.0
+    }
+}
 
 impl Deref for nonwild_keyexpr {
     type Target = keyexpr;
@@ -226,6 +321,181 @@ impl keyexpr {
             _ => it_intersect::<true>(left, right),
         }
     }
+
+    /// Whether every key matched by `other` is also matched by `self`.
+    pub fn includes(&self, other: &Self) -> bool {
+        let left = self.as_bytes();
+        let right = other.as_bytes();
+
+        left == right || it_includes(left, right)
+    }
+}
+
+fn it_includes(mut left: &[u8], mut right: &[u8]) -> bool {
+    loop {
+        let (lchunk, lrest) = next(left);
+        if lchunk == DOUBLE_WILD {
+            if lrest.is_empty() && !has_verbatim(right) {
+                return true;
+            }
+            if !lrest.is_empty() && it_includes(lrest, right) {
+                return true;
+            }
+            if has_direct_verbatim(right) {
+                return false;
+            }
+
+            right = next(right).1;
+            if right.is_empty() {
+                return false;
+            }
+        } else {
+            let (rchunk, rrest) = next(right);
+            if rchunk.is_empty() || rchunk == DOUBLE_WILD || !chunk_includes(lchunk, rchunk) {
+                return false;
+            }
+            if lrest.is_empty() {
+                return rrest.is_empty();
+            }
+
+            left = lrest;
+            right = rrest;
+        }
+    }
+}
+
+fn chunk_includes(lchunk: &[u8], rchunk: &[u8]) -> bool {
+    if lchunk == rchunk {
+        return true;
+    }
+    if has_direct_verbatim(lchunk) || has_direct_verbatim(rchunk) {
+        return false;
+    }
+    if lchunk == b"*" {
+        return true;
+    }
+
+    let Some(star) = find(lchunk, STAR_DSL) else {
+        return false;
+    };
+
+    let (prefix, mut lchunk) = (&lchunk[..star], &lchunk[star + 2..]);
+    let Some(mut rchunk) = rchunk.strip_prefix(prefix) else {
+        return false;
+    };
+
+    // Every literal between two `$*` must appear in order, the last one as a suffix.
+    while let Some(star) = find(lchunk, STAR_DSL) {
+        let Some(position) = find(rchunk, &lchunk[..star]) else {
+            return false;
+        };
+
+        rchunk = &rchunk[position + star..];
+        lchunk = &lchunk[star + 2..];
+    }
+
+    rchunk.ends_with(lchunk)
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    if needle.is_empty() {
+        return Some(0);
+    }
+
+    haystack.windows(needle.len()).position(|w| w == needle)
+}
+
+fn write_into<'b>(
+    buf: &'b mut [u8],
+    parts: &[&str],
+) -> core::result::Result<&'b mut str, crate::KeyexprError> {
+    let mut len = 0;
+    for part in parts {
+        let Some(dst) = buf.get_mut(len..len + part.len()) else {
+            crate::zbail!(
+                crate::KeyexprError::KeyexprTooLong,
+                "buffer of {} bytes is too small",
+                buf.len()
+            );
+        };
+
+        dst.copy_from_slice(part.as_bytes());
+        len += part.len();
+    }
+
+    // The buffer is made of whole `str`s.
+    Ok(unsafe { core::str::from_utf8_unchecked_mut(&mut buf[..len]) })
+}
+
+/// Rewrites `bytes` into its canonical form and returns its new length. Expressions with empty
+/// chunks are left untouched for the validation to reject them.
+fn canonize(bytes: &mut [u8]) -> usize {
+    if bytes.is_empty()
+        || bytes.starts_with(b"/")
+        || bytes.ends_with(b"/")
+        || bytes.windows(2).any(|w| w == b"//")
+    {
+        return bytes.len();
+    }
+
+    fn push(bytes: &mut [u8], w: &mut usize, chunk: &[u8]) {
+        if *w != 0 {
+            bytes[*w] = DELIMITER;
+            *w += 1;
+        }
+
+        bytes[*w..*w + chunk.len()].copy_from_slice(chunk);
+        *w += chunk.len();
+    }
+
+    // Chunks are only ever written at or before the position they are read from.
+    let mut w = 0;
+    let mut r = 0;
+    let mut double_wild = false;
+    while r < bytes.len() {
+        let end = bytes[r..]
+            .iter()
+            .position(|c| *c == DELIMITER)
+            .map_or(bytes.len(), |i| r + i);
+
+        let chunk = &bytes[r..end];
+        if chunk == DOUBLE_WILD {
+            double_wild = true;
+        } else if chunk == b"*" || chunk.chunks(2).all(|c| c == STAR_DSL) {
+            push(bytes, &mut w, b"*");
+        } else {
+            if double_wild {
+                push(bytes, &mut w, DOUBLE_WILD);
+                double_wild = false;
+            }
+
+            if w != 0 {
+                bytes[w] = DELIMITER;
+                w += 1;
+            }
+
+            let start = w;
+            let mut i = r;
+            while i < end {
+                if bytes[i..end].starts_with(STAR_DSL) && bytes[start..w].ends_with(STAR_DSL) {
+                    i += 2;
+                    continue;
+                }
+
+                bytes[w] = bytes[i];
+                w += 1;
+                i += 1;
+            }
+        }
+
+        r = end + 1;
+    }
+
+    if double_wild {
+        push(bytes, &mut w, DOUBLE_WILD);
+    }
+
+    w
 }
 
 #[cold]
@@ -0,0 +1,450 @@
+use crate::{KeyexprError, keyexpr};
+
+/// Declares key expression formats as constants, checking their spec at compile time.
+///
+/// ```ignore
+/// zenoh_proto::kedefine!(pub SENSOR: "robot/${id:*}/sensor/${kind:*}");
+/// ```
+#[macro_export]
+macro_rules! kedefine {
+    ($($vis:vis $name:ident: $spec:literal),* $(,)?) => {
+        $(
+            $vis const $name: $crate::KeFormat<'static, { $crate::ke_format_fields($spec) }> =
+                $crate::KeFormat::new($spec);
+        )*
+    };
+}
+
+/// A key expression format with `N` fields, such as `robot/${id:*}/sensor/${kind:*}`. A field
+/// `${name:pattern}` spans whole chunks and takes any value its pattern includes, `**` also
+/// taking no chunk at all. `${name:pattern#default}` gives it a value when none is set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeFormat<'s, const N: usize> {
+    spec: &'s str,
+}
+
+impl<'s, const N: usize> KeFormat<'s, N> {
+    /// Panics if `spec` is not a format with `N` fields, which happens at compile time when it
+    /// is evaluated in a `const`, see [`kedefine!`](crate::kedefine).
+    pub const fn new(spec: &'s str) -> Self {
+        if ke_format_fields(spec) != N {
+            panic!("wrong number of fields in key expression format");
+        }
+
+        Self { spec }
+    }
+
+    pub const fn as_str(&self) -> &'s str {
+        self.spec
+    }
+
+    pub fn formatter<'v>(&self) -> KeFormatter<'s, 'v, N> {
+        KeFormatter {
+            format: *self,
+            values: [None; N],
+        }
+    }
+
+    /// Splits `ke` into the values of the fields of this format.
+    pub fn parse<'k>(
+        &self,
+        ke: &'k keyexpr,
+    ) -> core::result::Result<KeParsed<'s, 'k, N>, KeyexprError> {
+        let mut values = [""; N];
+        if !parse(self.spec, ke.as_str(), &mut values, 0) {
+            crate::zbail!(
+                KeyexprError::FormatMismatch,
+                "keyexpr '{}' does not match format '{}'",
+                ke,
+                self.spec
+            );
+        }
+
+        Ok(KeParsed {
+            format: *self,
+            values,
+        })
+    }
+
+    fn field(&self, name: &str) -> core::result::Result<(usize, Field<'s>), KeyexprError> {
+        match fields(self.spec)
+            .enumerate()
+            .find(|(_, field)| field.name == name)
+        {
+            Some(field) => Ok(field),
+            None => crate::zbail!(
+                KeyexprError::UnknownField,
+                "format '{}' has no field '{}'",
+                self.spec,
+                name
+            ),
+        }
+    }
+}
+
+/// Builds a key expression out of a [`KeFormat`] and the values of its fields.
+#[derive(Debug, Clone, Copy)]
+pub struct KeFormatter<'s, 'v, const N: usize> {
+    format: KeFormat<'s, N>,
+    values: [Option<&'v str>; N],
+}
+
+impl<'s, 'v, const N: usize> KeFormatter<'s, 'v, N> {
+    /// Sets the field `name`, `value` must be included in its pattern.
+    pub fn set(mut self, name: &str, value: &'v str) -> core::result::Result<Self, KeyexprError> {
+        let (index, field) = self.format.field(name)?;
+        if !field.accepts(value) {
+            crate::zbail!(
+                KeyexprError::FormatMismatch,
+                "'{}' does not match field '{}' of format '{}'",
+                value,
+                name,
+                self.format.spec
+            );
+        }
+
+        self.values[index] = Some(value);
+        Ok(self)
+    }
+
+    /// Writes the key expression into `buf`.
+    pub fn build<'b>(&self, buf: &'b mut [u8]) -> core::result::Result<&'b keyexpr, KeyexprError> {
+        let mut len = 0;
+        let mut index = 0;
+        for segment in segments(self.format.spec) {
+            let value = match segment.field {
+                None => segment.pattern,
+                Some(field) => {
+                    let value = match (self.values[index], field.default) {
+                        (Some(value), _) => value,
+                        (None, Some(default)) if field.accepts(default) => default,
+                        _ => crate::zbail!(
+                            KeyexprError::MissingField,
+                            "field '{}' of format '{}' has no value",
+                            field.name,
+                            self.format.spec
+                        ),
+                    };
+
+                    index += 1;
+                    value
+                }
+            };
+
+            if value.is_empty() {
+                continue;
+            }
+
+            let separator = if len == 0 { "" } else { "/" };
+            for part in [separator, value] {
+                let Some(dst) = buf.get_mut(len..len + part.len()) else {
+                    crate::zbail!(
+                        KeyexprError::KeyexprTooLong,
+                        "buffer of {} bytes is too small",
+                        buf.len()
+                    );
+                };
+
+                dst.copy_from_slice(part.as_bytes());
+                len += part.len();
+            }
+        }
+
+        // The buffer is made of whole `str`s.
+        keyexpr::autocanonize(unsafe { core::str::from_utf8_unchecked_mut(&mut buf[..len]) })
+    }
+}
+
+/// The values of the fields of a [`KeFormat`] in a key expression.
+#[derive(Debug, Clone, Copy)]
+pub struct KeParsed<'s, 'k, const N: usize> {
+    format: KeFormat<'s, N>,
+    values: [&'k str; N],
+}
+
+impl<'s, 'k, const N: usize> KeParsed<'s, 'k, N> {
+    /// The value of the field `name`, `None` if there is no such field or if it took no chunk.
+    pub fn get(&self, name: &str) -> Option<&'k keyexpr> {
+        let (index, _) = self.format.field(name).ok()?;
+        let value = self.values[index];
+
+        (!value.is_empty()).then(|| keyexpr::from_str_unchecked(value))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&'s str, Option<&'k keyexpr>)> {
+        fields(self.format.spec)
+            .zip(self.values)
+            .map(|(field, value)| {
+                let value = (!value.is_empty()).then(|| keyexpr::from_str_unchecked(value));
+                (field.name, value)
+            })
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Field<'s> {
+    name: &'s str,
+    pattern: &'s str,
+    default: Option<&'s str>,
+}
+
+impl Field<'_> {
+    fn accepts(&self, value: &str) -> bool {
+        if value.is_empty() {
+            return self.pattern == "**";
+        }
+
+        keyexpr::new(value).is_ok_and(|value| pattern_includes(self.pattern, value))
+    }
+}
+
+/// A field, or the literal chunks between two fields.
+struct Segment<'s> {
+    pattern: &'s str,
+    field: Option<Field<'s>>,
+}
+
+fn pattern_includes(pattern: &str, value: &keyexpr) -> bool {
+    // Patterns are checked when the format is created.
+    keyexpr::from_str_unchecked(pattern).includes(value)
+}
+
+/// Splits the first segment off `spec`.
+fn split(spec: &str) -> Option<(Segment<'_>, &str)> {
+    if spec.is_empty() {
+        return None;
+    }
+
+    let (segment, rest) = match spec.strip_prefix("${") {
+        Some(field) => {
+            let close = field.find('}').unwrap_or(field.len());
+            let (name, pattern) = field[..close].split_once(':').unwrap_or(("", ""));
+            let (pattern, default) = match pattern.split_once('#') {
+                Some((pattern, default)) => (pattern, Some(default)),
+                None => (pattern, None),
+            };
+
+            let segment = Segment {
+                pattern,
+                field: Some(Field {
+                    name,
+                    pattern,
+                    default,
+                }),
+            };
+
+            (segment, field.get(close + 1..).unwrap_or(""))
+        }
+        None => {
+            let end = spec.find("/${").unwrap_or(spec.len());
+            let segment = Segment {
+                pattern: &spec[..end],
+                field: None,
+            };
+
+            (segment, &spec[end..])
+        }
+    };
+
+    Some((segment, rest.strip_prefix('/').unwrap_or(rest)))
+}
+
+fn segments(mut spec: &str) -> impl Iterator<Item = Segment<'_>> {
+    core::iter::from_fn(move || {
+        let (segment, rest) = split(spec)?;
+        spec = rest;
+        Some(segment)
+    })
+}
+
+fn fields(spec: &str) -> impl Iterator<Item = Field<'_>> {
+    segments(spec).filter_map(|segment| segment.field)
+}
+
+/// Matches the segments of `spec` against the chunks of `ke`, trying every split of `ke` for
+/// the segments that can span several chunks.
+fn parse<'k, const N: usize>(
+    spec: &str,
+    ke: &'k str,
+    values: &mut [&'k str; N],
+    index: usize,
+) -> bool {
+    let Some((segment, rest)) = split(spec) else {
+        return ke.is_empty();
+    };
+
+    let ends = ke
+        .match_indices('/')
+        .map(|(end, _)| end)
+        .chain(core::iter::once(ke.len()));
+    let ends = core::iter::once(0).chain(ends);
+
+    for end in ends {
+        let (value, ke_rest) = match end {
+            0 => ("", ke),
+            end if end == ke.len() => (ke, ""),
+            end => (&ke[..end], &ke[end + 1..]),
+        };
+
+        let matched = match value.is_empty() {
+            true => segment.pattern == "**",
+            false => pattern_includes(segment.pattern, keyexpr::from_str_unchecked(value)),
+        };
+
+        if !matched {
+            continue;
+        }
+
+        let next = match segment.field {
+            Some(_) => {
+                values[index] = value;
+                index + 1
+            }
+            None => index,
+        };
+
+        if parse(rest, ke_rest, values, next) {
+            return true;
+        }
+    }
+
+    false
+}
+
+const MAX_FIELDS: usize = 32;
+
+/// Checks `spec` and returns its number of fields, panicking if it is not a valid format. Used
+/// by [`kedefine!`](crate::kedefine) to size the [`KeFormat`].
+#[doc(hidden)]
+pub const fn ke_format_fields(spec: &str) -> usize {
+    let bytes = spec.as_bytes();
+    if bytes.is_empty() {
+        panic!("empty key expression format");
+    }
+
+    let mut names = [(0, 0); MAX_FIELDS];
+    let mut fields = 0;
+
+    let mut i = 0;
+    loop {
+        if i == bytes.len() {
+            panic!("empty chunk in key expression format");
+        }
+
+        if bytes[i] == b'$' && i + 1 < bytes.len() && bytes[i + 1] == b'{' {
+            let close = find(bytes, i + 2, bytes.len(), b'}');
+            let colon = find(bytes, i + 2, close, b':');
+            if close == bytes.len() {
+                panic!("unclosed field in key expression format");
+            }
+            if colon == close || colon == i + 2 {
+                panic!("field without a name and a pattern in key expression format");
+            }
+
+            let hash = find(bytes, colon + 1, close, b'#');
+            check_pattern(bytes, colon + 1, hash);
+            if hash != close && hash + 1 != close {
+                check_pattern(bytes, hash + 1, close);
+            }
+
+            if fields == MAX_FIELDS {
+                panic!("too many fields in key expression format");
+            }
+
+            let mut field = 0;
+            while field < fields {
+                let (start, end) = names[field];
+                if eq(bytes, start, end, i + 2, colon) {
+                    panic!("duplicate field in key expression format");
+                }
+                field += 1;
+            }
+
+            names[fields] = (i + 2, colon);
+            fields += 1;
+            i = close + 1;
+        } else {
+            let end = find(bytes, i, bytes.len(), b'/');
+            check_chunk(bytes, i, end);
+            i = end;
+        }
+
+        if i == bytes.len() {
+            return fields;
+        }
+        if bytes[i] != b'/' {
+            panic!("field not spanning whole chunks in key expression format");
+        }
+
+        i += 1;
+    }
+}
+
+const fn find(bytes: &[u8], mut start: usize, end: usize, c: u8) -> usize {
+    while start < end && bytes[start] != c {
+        start += 1;
+    }
+
+    start
+}
+
+const fn eq(bytes: &[u8], mut a: usize, a_end: usize, mut b: usize, b_end: usize) -> bool {
+    if a_end - a != b_end - b {
+        return false;
+    }
+
+    while a < a_end {
+        if bytes[a] != bytes[b] {
+            return false;
+        }
+
+        a += 1;
+        b += 1;
+    }
+
+    true
+}
+
+const fn check_pattern(bytes: &[u8], mut start: usize, end: usize) {
+    if start == end {
+        panic!("empty pattern in key expression format");
+    }
+
+    while start <= end {
+        let chunk_end = find(bytes, start, end, b'/');
+        check_chunk(bytes, start, chunk_end);
+        start = chunk_end + 1;
+    }
+}
+
+/// A lighter `keyexpr::new`, for the chunks of a format.
+const fn check_chunk(bytes: &[u8], start: usize, end: usize) {
+    match end - start {
+        0 => panic!("empty chunk in key expression format"),
+        1 if bytes[start] == b'*' => return,
+        2 if bytes[start] == b'*' && bytes[start + 1] == b'*' => return,
+        2 if bytes[start] == b'$' && bytes[start + 1] == b'*' => {
+            panic!("lone '$*' in key expression format")
+        }
+        _ => {}
+    }
+
+    let mut i = start;
+    while i < end {
+        match bytes[i] {
+            b'*' if i == start || bytes[i - 1] != b'$' => {
+                panic!("'*' in middle of chunk in key expression format")
+            }
+            b'$' if i + 1 == end || bytes[i + 1] != b'*' => {
+                panic!("unbound '$' in key expression format")
+            }
+            b'$' if i + 2 < end && bytes[i + 2] == b'$' => {
+                panic!("'$' after '$' in key expression format")
+            }
+            b'#' | b'?' => panic!("'#' or '?' in key expression format"),
+            _ => {}
+        }
+
+        i += 1;
+    }
+}
@@ -0,0 +1,142 @@
+use core::cmp::Ordering;
+
+use crate::keyexpr;
+
+/// An entry of a [`KeTree`].
+pub trait KeTreeEntry {
+    fn keyexpr(&self) -> &keyexpr;
+}
+
+impl KeTreeEntry for &keyexpr {
+    fn keyexpr(&self) -> &keyexpr {
+        self
+    }
+}
+
+impl keyexpr {
+    /// Orders key expressions chunk by chunk, a key expression before the ones it prefixes and
+    /// wildcard chunks before verbatim ones. This is the order of the entries of a [`KeTree`].
+    pub fn tree_cmp(&self, other: &keyexpr) -> Ordering {
+        let mut left = self.split('/');
+        let mut right = other.split('/');
+        loop {
+            match (left.next(), right.next()) {
+                (None, None) => return Ordering::Equal,
+                (None, Some(_)) => return Ordering::Less,
+                (Some(_), None) => return Ordering::Greater,
+                (Some(left), Some(right)) => match chunk_cmp(left, right) {
+                    Ordering::Equal => continue,
+                    ordering => return ordering,
+                },
+            }
+        }
+    }
+}
+
+fn chunk_cmp(left: &str, right: &str) -> Ordering {
+    is_wild(right)
+        .cmp(&is_wild(left))
+        .then_with(|| left.cmp(right))
+}
+
+fn is_wild(chunk: &str) -> bool {
+    chunk.contains('*')
+}
+
+fn chunk(ke: &keyexpr, depth: usize) -> Option<&str> {
+    ke.split('/').nth(depth)
+}
+
+/// A key expression tree laid out in a slice sorted with [`keyexpr::tree_cmp`]: the entries
+/// sharing their first chunks are contiguous, so each chunk of an incoming key expression
+/// only leads to the verbatim branch equal to it and to the wildcard branches.
+pub struct KeTree<'t, T> {
+    entries: &'t [T],
+}
+
+impl<'t, T> KeTree<'t, T>
+where
+    T: KeTreeEntry,
+{
+    /// `entries` must be sorted with [`keyexpr::tree_cmp`].
+    pub fn new(entries: &'t [T]) -> Self {
+        Self { entries }
+    }
+
+    /// Where an entry on `ke` goes to keep the tree sorted.
+    pub fn position(&self, ke: &keyexpr) -> usize {
+        self.entries
+            .partition_point(|entry| entry.keyexpr().tree_cmp(ke) != Ordering::Greater)
+    }
+
+    /// Calls `f` with the index of every entry intersecting `ke`, in increasing order.
+    pub fn intersecting(&self, ke: &keyexpr, mut f: impl FnMut(usize)) {
+        let mut candidate = |index: usize| {
+            if self.entries[index].keyexpr().intersects(ke) {
+                f(index)
+            }
+        };
+
+        self.visit(0, self.entries.len(), 0, ke.as_str(), &mut candidate);
+    }
+
+    /// Visits the entries in `lo..hi`, which share their first `depth` chunks, against the
+    /// chunks of the incoming key expression left in `rest`. Branches that may match are
+    /// handed over whole, the caller checks them.
+    fn visit<F: FnMut(usize)>(&self, lo: usize, hi: usize, depth: usize, rest: &str, f: &mut F) {
+        if lo == hi {
+            return;
+        }
+
+        let entries = &self.entries[lo..hi];
+        let ended = lo + entries.partition_point(|entry| chunk(entry.keyexpr(), depth).is_none());
+        let wild = ended
+            + self.entries[ended..hi]
+                .partition_point(|entry| chunk(entry.keyexpr(), depth).is_some_and(is_wild));
+
+        let (current, rest) = match rest.split_once('/') {
+            Some((current, rest)) => (current, rest),
+            None => (rest, ""),
+        };
+
+        if current.is_empty() {
+            // Only the entries ending here, or going on with `**`, may match.
+            (lo..wild).for_each(f);
+            return;
+        }
+
+        if is_wild(current) {
+            (lo..hi).for_each(f);
+            return;
+        }
+
+        let mut start = ended;
+        while start < wild {
+            let branch = chunk(self.entries[start].keyexpr(), depth);
+            let end = start
+                + self.entries[start..wild]
+                    .partition_point(|entry| chunk(entry.keyexpr(), depth) == branch);
+
+            match branch {
+                Some("**") => (start..end).for_each(&mut *f),
+                Some(branch)
+                    if super::chunk_intersect::<true>(branch.as_bytes(), current.as_bytes()) =>
+                {
+                    self.visit(start, end, depth + 1, rest, f)
+                }
+                _ => {}
+            }
+
+            start = end;
+        }
+
+        let verbatim = &self.entries[wild..hi];
+        let start =
+            wild + verbatim.partition_point(|entry| chunk(entry.keyexpr(), depth) < Some(current));
+        let end = start
+            + self.entries[start..hi]
+                .partition_point(|entry| chunk(entry.keyexpr(), depth) == Some(current));
+
+        self.visit(start, end, depth + 1, rest, f);
+    }
+}
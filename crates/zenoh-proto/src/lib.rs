@@ -6,6 +6,7 @@ mod bytes;
 mod codec;
 mod endpoint;
 mod ke;
+mod parameters;
 mod zerror;
 
 pub mod logging;
@@ -16,6 +17,7 @@ pub use codec::*;
 pub use endpoint::*;
 pub use ke::*;
 pub use msgs::{exts, fields};
+pub use parameters::*;
 pub use zerror::*;
 
 #[cfg(test)]
@@ -1,6 +1,7 @@
 pub mod exts;
 pub mod fields;
 
+mod del;
 mod err;
 mod put;
 mod query;
@@ -13,11 +14,13 @@ mod request;
 mod response;
 
 mod close;
+mod fragment;
 mod frame;
 mod init;
 mod keepalive;
 mod open;
 
+pub use del::*;
 pub use err::*;
 pub use put::*;
 pub use query::*;
@@ -30,6 +33,7 @@ pub use request::*;
 pub use response::*;
 
 pub use close::*;
+pub use fragment::*;
 pub use frame::*;
 pub use init::*;
 pub use keepalive::*;
@@ -154,6 +158,7 @@ pub struct NetworkMessageRef<'a> {
 #[derive(ZEnum, Debug, PartialEq)]
 pub enum TransportMessage<'a> {
     Close(Close),
+    Fragment(Fragment<'a>),
     InitSyn(InitSyn<'a>),
     InitAck(InitAck<'a>),
     KeepAlive(KeepAlive),
@@ -165,6 +170,7 @@ impl TransportMessage<'_> {
     pub fn as_ref(&self) -> TransportMessageRef<'_> {
         match self {
             TransportMessage::Close(x) => TransportMessageRef::Close(x),
+            TransportMessage::Fragment(x) => TransportMessageRef::Fragment(x),
             TransportMessage::InitSyn(x) => TransportMessageRef::InitSyn(x),
             TransportMessage::InitAck(x) => TransportMessageRef::InitAck(x),
             TransportMessage::KeepAlive(x) => TransportMessageRef::KeepAlive(x),
@@ -177,6 +183,7 @@ impl TransportMessage<'_> {
 #[derive(Debug, PartialEq)]
 pub enum TransportMessageRef<'a> {
     Close(&'a Close),
+    Fragment(&'a Fragment<'a>),
     InitSyn(&'a InitSyn<'a>),
     InitAck(&'a InitAck<'a>),
     KeepAlive(&'a KeepAlive),
@@ -188,6 +195,7 @@ impl<'a> crate::ZBodyLen for TransportMessageRef<'a> {
     fn z_body_len(&self) -> usize {
         match self {
             Self::Close(x) => <Close as crate::ZBodyLen>::z_body_len(x),
+            Self::Fragment(x) => <Fragment as crate::ZBodyLen>::z_body_len(x),
             Self::InitSyn(x) => <InitSyn as crate::ZBodyLen>::z_body_len(x),
             Self::InitAck(x) => <InitAck as crate::ZBodyLen>::z_body_len(x),
             Self::KeepAlive(x) => <KeepAlive as crate::ZBodyLen>::z_body_len(x),
@@ -210,6 +218,7 @@ impl<'a> crate::ZBodyEncode for TransportMessageRef<'a> {
     ) -> core::result::Result<(), crate::CodecError> {
         match self {
             Self::Close(x) => <Close as crate::ZBodyEncode>::z_body_encode(x, w),
+            Self::Fragment(x) => <Fragment as crate::ZBodyEncode>::z_body_encode(x, w),
             Self::InitSyn(x) => <InitSyn as crate::ZBodyEncode>::z_body_encode(x, w),
             Self::InitAck(x) => <InitAck as crate::ZBodyEncode>::z_body_encode(x, w),
             Self::KeepAlive(x) => <KeepAlive as crate::ZBodyEncode>::z_body_encode(x, w),
@@ -226,6 +235,7 @@ impl<'a> crate::ZEncode for TransportMessageRef<'a> {
     ) -> core::result::Result<(), crate::CodecError> {
         match self {
             Self::Close(x) => <Close as crate::ZEncode>::z_encode(x, w),
+            Self::Fragment(x) => <Fragment as crate::ZEncode>::z_encode(x, w),
             Self::InitSyn(x) => <InitSyn as crate::ZEncode>::z_encode(x, w),
             Self::InitAck(x) => <InitAck as crate::ZEncode>::z_encode(x, w),
             Self::KeepAlive(x) => <KeepAlive as crate::ZEncode>::z_encode(x, w),
@@ -9,6 +9,17 @@ pub struct Close {
     pub behaviour: CloseBehaviour,
 }
 
+impl Close {
+    pub const GENERIC: u8 = 0x00;
+    pub const UNSUPPORTED: u8 = 0x01;
+    pub const INVALID: u8 = 0x02;
+    pub const MAX_SESSIONS: u8 = 0x03;
+    pub const MAX_LINKS: u8 = 0x04;
+    pub const EXPIRED: u8 = 0x05;
+    pub const UNRESPONSIVE: u8 = 0x06;
+    pub const CONNECTION_TO_SELF: u8 = 0x07;
+}
+
 #[repr(u8)]
 #[derive(ZRU8, Default, Debug, Clone, Copy, PartialEq)]
 pub enum CloseBehaviour {
@@ -2,7 +2,7 @@ use core::time::Duration;
 
 use crate::{fields::*, *};
 
-#[derive(ZExt, Debug, PartialEq, Default)]
+#[derive(ZExt, Debug, PartialEq, Default, Clone, Copy)]
 #[zenoh(header = "ID:4|_:4")]
 pub struct EntityGlobalId {
     #[zenoh(size = header(ID))]
@@ -11,7 +11,7 @@ pub struct EntityGlobalId {
     pub eid: u32,
 }
 
-#[derive(ZExt, Debug, PartialEq, Default)]
+#[derive(ZExt, Debug, PartialEq, Default, Clone, Copy)]
 pub struct SourceInfo {
     pub id: EntityGlobalId,
     pub sn: u32,
@@ -43,6 +43,7 @@ impl Default for QoS {
 }
 
 impl QoS {
+    const P_MASK: u8 = 0b00000111;
     const D_FLAG: u8 = 0b00001000;
     const E_FLAG: u8 = 0b00010000;
 
@@ -64,6 +65,22 @@ impl QoS {
         }
         Self { inner }
     }
+
+    pub fn priority(&self) -> Priority {
+        Priority::try_from(self.inner & Self::P_MASK).unwrap_or_default()
+    }
+
+    pub fn congestion_control(&self) -> CongestionControl {
+        if self.inner & Self::D_FLAG != 0 {
+            CongestionControl::Block
+        } else {
+            CongestionControl::Drop
+        }
+    }
+
+    pub fn is_express(&self) -> bool {
+        self.inner & Self::E_FLAG != 0
+    }
 }
 
 #[derive(ZExt, Debug, PartialEq, Default)]
@@ -229,6 +246,27 @@ pub struct Auth<'a> {
     pub payload: &'a [u8],
 }
 
+/// The `usrpwd` method of the [`Auth`] extension, offered in `InitSyn` and accepted in
+/// `OpenAck`.
+#[derive(ZExt, Debug, PartialEq, Default)]
+pub struct HasUsrPwd {}
+
+/// The `usrpwd` challenge sent by the listener in `InitAck`.
+#[derive(ZExt, Debug, PartialEq, Default)]
+pub struct UsrPwdNonce {
+    pub nonce: u64,
+}
+
+/// The `usrpwd` response sent in `OpenSyn`: the HMAC-SHA256 of the password, keyed with the
+/// nonce.
+#[derive(ZExt, Debug, PartialEq, Default)]
+pub struct UsrPwdOpenSyn<'a> {
+    #[zenoh(size = prefixed)]
+    pub user: &'a [u8],
+    #[zenoh(size = prefixed)]
+    pub hmac: &'a [u8],
+}
+
 #[derive(ZExt, Debug, PartialEq, Default)]
 pub struct MultiLink<'a> {
     #[zenoh(size = remain)]
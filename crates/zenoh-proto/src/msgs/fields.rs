@@ -12,13 +12,24 @@ pub enum Reliability {
 }
 
 #[repr(u8)]
-#[derive(Debug, Default, PartialEq)]
+#[derive(ZRU8, Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub enum Priority {
+    Control = 0,
+    RealTime = 1,
+    InteractiveHigh = 2,
+    InteractiveLow = 3,
+    DataHigh = 4,
     #[default]
     Data = 5,
+    DataLow = 6,
+    Background = 7,
 }
 
-#[derive(Debug, Default, PartialEq)]
+impl Priority {
+    pub const NUM: usize = 8;
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
 #[repr(u8)]
 pub enum CongestionControl {
     #[default]
@@ -282,6 +293,17 @@ pub enum Bits {
     U64 = 0b0000_0011,
 }
 
+impl Bits {
+    pub const fn mask(&self) -> u64 {
+        match self {
+            Bits::U8 => u8::MAX as u64,
+            Bits::U16 => u16::MAX as u64,
+            Bits::U32 => u32::MAX as u64,
+            Bits::U64 => u64::MAX,
+        }
+    }
+}
+
 #[repr(u8)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Field {
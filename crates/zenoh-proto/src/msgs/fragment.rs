@@ -0,0 +1,25 @@
+use crate::{exts::*, fields::*, *};
+
+#[derive(ZStruct, Debug, PartialEq, Default)]
+#[zenoh(header = "Z|M|R|ID:5=0x06")]
+pub struct Fragment<'a> {
+    #[zenoh(header = R)]
+    pub reliability: Reliability,
+    #[zenoh(header = M)]
+    pub more: FragmentMore,
+    pub sn: u32,
+
+    #[zenoh(ext = 0x1, default = QoS::default())]
+    pub qos: QoS,
+
+    #[zenoh(size = remain)]
+    pub payload: &'a [u8],
+}
+
+#[repr(u8)]
+#[derive(ZRU8, Default, Debug, Clone, Copy, PartialEq)]
+pub enum FragmentMore {
+    #[default]
+    Last = 0,
+    More = 1,
+}
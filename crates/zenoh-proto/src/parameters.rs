@@ -0,0 +1,249 @@
+use core::fmt;
+
+use crate::{KeyexprError, ParametersError, keyexpr};
+
+mod time;
+
+pub use time::*;
+
+pub const LIST_SEPARATOR: char = ';';
+pub const FIELD_SEPARATOR: char = '=';
+pub const VALUE_SEPARATOR: char = '|';
+pub const SELECTOR_SEPARATOR: char = '?';
+
+/// The reserved parameter restricting a query to a [`TimeRange`].
+pub const TIME_RANGE_KEY: &str = "_time";
+
+/// The `key=value;...` parameters of a query, read in place. A key without `=` has an empty
+/// value, empty entries are skipped and the first entry wins when a key is repeated. Zenoh has
+/// no escape sequence: keys can't hold `;` or `=`, values can't hold `;`.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Parameters<'a>(&'a str);
+
+impl<'a> Parameters<'a> {
+    pub const fn new(s: &'a str) -> Self {
+        Self(s)
+    }
+
+    pub fn as_str(&self) -> &'a str {
+        self.0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.iter().next().is_none()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&'a str, &'a str)> + Clone + use<'a> {
+        self.0
+            .split(LIST_SEPARATOR)
+            .filter(|entry| !entry.is_empty())
+            .map(|entry| entry.split_once(FIELD_SEPARATOR).unwrap_or((entry, "")))
+    }
+
+    pub fn get(&self, key: &str) -> Option<&'a str> {
+        self.iter()
+            .find(|(entry, _)| *entry == key)
+            .map(|(_, value)| value)
+    }
+
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// The `|` separated values of `key`.
+    pub fn values(&self, key: &str) -> impl Iterator<Item = &'a str> + use<'a> {
+        self.get(key)
+            .into_iter()
+            .flat_map(|value| value.split(VALUE_SEPARATOR))
+    }
+
+    /// The range of the reserved `_time` parameter, if any.
+    pub fn time_range(&self) -> core::result::Result<Option<TimeRange>, ParametersError> {
+        self.get(TIME_RANGE_KEY).map(TimeRange::parse).transpose()
+    }
+}
+
+impl<'a> From<&'a str> for Parameters<'a> {
+    fn from(value: &'a str) -> Self {
+        Self::new(value)
+    }
+}
+
+impl AsRef<str> for Parameters<'_> {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl fmt::Display for Parameters<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.0)
+    }
+}
+
+impl fmt::Debug for Parameters<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{self}")
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for Parameters<'_> {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(f, "{}", self.0)
+    }
+}
+
+/// Writes parameters into `buf`, checking that each entry reads back the way it was written.
+pub struct ParametersBuilder<'b> {
+    buf: &'b mut [u8],
+    len: usize,
+}
+
+impl<'b> ParametersBuilder<'b> {
+    pub fn new(buf: &'b mut [u8]) -> Self {
+        Self { buf, len: 0 }
+    }
+
+    /// Adds `key=value`, or a lone `key` when `value` is empty.
+    pub fn insert(mut self, key: &str, value: &str) -> core::result::Result<Self, ParametersError> {
+        if value.contains(LIST_SEPARATOR) {
+            crate::zbail!(ParametersError::InvalidParameter);
+        }
+
+        self.key(key)?;
+        if !value.is_empty() {
+            self.push(&[FIELD_SEPARATOR as u8])?;
+            self.push(value.as_bytes())?;
+        }
+
+        Ok(self)
+    }
+
+    /// Adds `key` with `values` joined by `|`.
+    pub fn values<'v>(
+        mut self,
+        key: &str,
+        values: impl IntoIterator<Item = &'v str>,
+    ) -> core::result::Result<Self, ParametersError> {
+        self.key(key)?;
+        for (i, value) in values.into_iter().enumerate() {
+            if value.contains([LIST_SEPARATOR, VALUE_SEPARATOR]) {
+                crate::zbail!(ParametersError::InvalidParameter);
+            }
+
+            let separator = if i == 0 {
+                FIELD_SEPARATOR
+            } else {
+                VALUE_SEPARATOR
+            };
+            self.push(&[separator as u8])?;
+            self.push(value.as_bytes())?;
+        }
+
+        Ok(self)
+    }
+
+    /// Restricts the query to `range` through the reserved `_time` parameter.
+    pub fn time_range(mut self, range: &TimeRange) -> core::result::Result<Self, ParametersError> {
+        use core::fmt::Write;
+
+        self.key(TIME_RANGE_KEY)?;
+        write!(Writer(&mut self), "{FIELD_SEPARATOR}{range}")
+            .map_err(|_| ParametersError::ParametersTooLong)?;
+
+        Ok(self)
+    }
+
+    pub fn build(self) -> Parameters<'b> {
+        let Self { buf, len } = self;
+
+        // Only whole `str`s are ever pushed.
+        Parameters::new(core::str::from_utf8(&buf[..len]).unwrap_or_default())
+    }
+
+    fn parameters(&self) -> Parameters<'_> {
+        Parameters::new(core::str::from_utf8(&self.buf[..self.len]).unwrap_or_default())
+    }
+
+    fn key(&mut self, key: &str) -> core::result::Result<(), ParametersError> {
+        if key.is_empty() || key.contains([LIST_SEPARATOR, FIELD_SEPARATOR]) {
+            crate::zbail!(ParametersError::InvalidParameter);
+        }
+
+        if self.parameters().contains_key(key) {
+            crate::zbail!(ParametersError::DuplicateParameter);
+        }
+
+        if self.len > 0 {
+            self.push(&[LIST_SEPARATOR as u8])?;
+        }
+
+        self.push(key.as_bytes())
+    }
+
+    fn push(&mut self, bytes: &[u8]) -> core::result::Result<(), ParametersError> {
+        let end = self.len + bytes.len();
+        self.buf
+            .get_mut(self.len..end)
+            .ok_or(ParametersError::ParametersTooLong)?
+            .copy_from_slice(bytes);
+        self.len = end;
+
+        Ok(())
+    }
+}
+
+struct Writer<'w, 'b>(&'w mut ParametersBuilder<'b>);
+
+impl fmt::Write for Writer<'_, '_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.0.push(s.as_bytes()).map_err(|_| fmt::Error)
+    }
+}
+
+/// A key expression followed by the parameters of a query: `ke?key=value;...`.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Selector<'a> {
+    keyexpr: &'a keyexpr,
+    parameters: Parameters<'a>,
+}
+
+impl<'a> Selector<'a> {
+    pub fn new(keyexpr: &'a keyexpr, parameters: Parameters<'a>) -> Self {
+        Self {
+            keyexpr,
+            parameters,
+        }
+    }
+
+    pub fn parse(s: &'a str) -> core::result::Result<Self, KeyexprError> {
+        let (ke, parameters) = s.split_once(SELECTOR_SEPARATOR).unwrap_or((s, ""));
+
+        Ok(Self::new(keyexpr::new(ke)?, Parameters::new(parameters)))
+    }
+
+    pub fn keyexpr(&self) -> &'a keyexpr {
+        self.keyexpr
+    }
+
+    pub fn parameters(&self) -> Parameters<'a> {
+        self.parameters
+    }
+}
+
+impl fmt::Display for Selector<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.parameters.as_str().is_empty() {
+            write!(f, "{}", self.keyexpr)
+        } else {
+            write!(f, "{}{SELECTOR_SEPARATOR}{}", self.keyexpr, self.parameters)
+        }
+    }
+}
+
+impl fmt::Debug for Selector<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{self}")
+    }
+}
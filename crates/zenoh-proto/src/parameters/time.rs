@@ -0,0 +1,298 @@
+use core::{fmt, time::Duration};
+
+use uhlc::NTP64;
+
+use crate::ParametersError;
+
+const RANGE_SEPARATOR: &str = "..";
+const SECS_PER_DAY: i64 = 86_400;
+
+/// A point in time a [`TimeRange`] is bounded by.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TimeExpr {
+    /// An RFC 3339 date, such as `2024-05-17T09:30:00Z`.
+    Fixed(NTP64),
+    /// `now(offset)`, such as `now(-1h)`: a time relative to when the range is checked.
+    Now { offset_secs: f64 },
+}
+
+impl TimeExpr {
+    /// The time this expression stands for when it is `now`.
+    pub fn resolve(&self, now: NTP64) -> NTP64 {
+        match *self {
+            Self::Fixed(time) => time,
+            Self::Now { offset_secs } if offset_secs < 0.0 => {
+                NTP64(now.0.saturating_sub(offset(-offset_secs).0))
+            }
+            Self::Now { offset_secs } => NTP64(now.0.saturating_add(offset(offset_secs).0)),
+        }
+    }
+
+    fn parse(s: &str) -> core::result::Result<Self, ParametersError> {
+        match s
+            .strip_prefix("now(")
+            .and_then(|offset| offset.strip_suffix(')'))
+        {
+            Some("") => Ok(Self::Now { offset_secs: 0.0 }),
+            Some(offset) => Ok(Self::Now {
+                offset_secs: parse_duration(offset)?,
+            }),
+            None => parse_rfc3339(s)
+                .map(Self::Fixed)
+                .ok_or(ParametersError::InvalidTimeRange),
+        }
+    }
+}
+
+impl fmt::Display for TimeExpr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::Fixed(time) => write_rfc3339(f, time),
+            Self::Now { offset_secs: 0.0 } => f.write_str("now()"),
+            Self::Now { offset_secs } => write!(f, "now({offset_secs}s)"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TimeBound {
+    Inclusive(TimeExpr),
+    Exclusive(TimeExpr),
+    Unbounded,
+}
+
+/// The range of the reserved `_time` parameter: `[start..end]`, where a `]` opening or a `[`
+/// closing the range excludes its bound, and an empty bound leaves that side open.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TimeRange {
+    pub start: TimeBound,
+    pub end: TimeBound,
+}
+
+impl TimeRange {
+    pub fn parse(s: &str) -> core::result::Result<Self, ParametersError> {
+        let inner = s
+            .get(1..s.len().saturating_sub(1))
+            .ok_or(ParametersError::InvalidTimeRange)?;
+        let (start, end) = inner
+            .split_once(RANGE_SEPARATOR)
+            .ok_or(ParametersError::InvalidTimeRange)?;
+
+        let bound = |expr: &str, inclusive: bool| -> core::result::Result<_, ParametersError> {
+            Ok(match expr {
+                "" => TimeBound::Unbounded,
+                expr if inclusive => TimeBound::Inclusive(TimeExpr::parse(expr)?),
+                expr => TimeBound::Exclusive(TimeExpr::parse(expr)?),
+            })
+        };
+
+        let start = match s.as_bytes()[0] {
+            b'[' => bound(start, true)?,
+            b']' => bound(start, false)?,
+            _ => crate::zbail!(ParametersError::InvalidTimeRange),
+        };
+        let end = match s.as_bytes()[s.len() - 1] {
+            b']' => bound(end, true)?,
+            b'[' => bound(end, false)?,
+            _ => crate::zbail!(ParametersError::InvalidTimeRange),
+        };
+
+        Ok(Self { start, end })
+    }
+
+    /// Whether `time` is in the range, `now` standing for the time the range is checked at.
+    pub fn contains(&self, time: NTP64, now: NTP64) -> bool {
+        let after_start = match &self.start {
+            TimeBound::Inclusive(start) => time >= start.resolve(now),
+            TimeBound::Exclusive(start) => time > start.resolve(now),
+            TimeBound::Unbounded => true,
+        };
+        let before_end = match &self.end {
+            TimeBound::Inclusive(end) => time <= end.resolve(now),
+            TimeBound::Exclusive(end) => time < end.resolve(now),
+            TimeBound::Unbounded => true,
+        };
+
+        after_start && before_end
+    }
+}
+
+impl fmt::Display for TimeRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.start {
+            TimeBound::Inclusive(start) => write!(f, "[{start}")?,
+            TimeBound::Exclusive(start) => write!(f, "]{start}")?,
+            TimeBound::Unbounded => f.write_str("[")?,
+        }
+        f.write_str(RANGE_SEPARATOR)?;
+        match &self.end {
+            TimeBound::Inclusive(end) => write!(f, "{end}]"),
+            TimeBound::Exclusive(end) => write!(f, "{end}["),
+            TimeBound::Unbounded => f.write_str("]"),
+        }
+    }
+}
+
+fn offset(secs: f64) -> NTP64 {
+    Duration::try_from_secs_f64(secs)
+        .ok()
+        .and_then(ntp64)
+        .unwrap_or(NTP64(u64::MAX))
+}
+
+/// `NTP64` only counts seconds up to `u32::MAX`.
+fn ntp64(duration: Duration) -> Option<NTP64> {
+    (duration.as_secs() <= u64::from(u32::MAX)).then(|| NTP64::from(duration))
+}
+
+/// Seconds in a duration such as `-1.5h`: `s` is the default unit, followed by `ms`, `us`, `m`,
+/// `h`, `d` and `w`.
+fn parse_duration(s: &str) -> core::result::Result<f64, ParametersError> {
+    let (number, unit) = s.split_at(s.find(|c: char| c.is_alphabetic()).unwrap_or(s.len()));
+    let number = number
+        .parse::<f64>()
+        .ok()
+        .filter(|number| number.is_finite())
+        .ok_or(ParametersError::InvalidTimeRange)?;
+
+    let scale = match unit {
+        "" | "s" => 1.0,
+        "ms" => 1e-3,
+        "u" | "us" | "µs" => 1e-6,
+        "m" => 60.0,
+        "h" => 3_600.0,
+        "d" => 86_400.0,
+        "w" => 604_800.0,
+        _ => crate::zbail!(ParametersError::InvalidTimeRange),
+    };
+
+    Ok(number * scale)
+}
+
+/// `YYYY-MM-DDTHH:MM:SS[.fraction](Z|±HH:MM)`, no earlier than the UNIX epoch.
+fn parse_rfc3339(s: &str) -> Option<NTP64> {
+    let b = s.as_bytes();
+    let number = |range: core::ops::Range<usize>| -> Option<i64> {
+        let digits = b.get(range)?;
+        digits.iter().all(u8::is_ascii_digit).then(|| {
+            digits
+                .iter()
+                .fold(0, |acc, digit| acc * 10 + i64::from(digit - b'0'))
+        })
+    };
+
+    if b.len() < 20
+        || b[4] != b'-'
+        || b[7] != b'-'
+        || !matches!(b[10], b'T' | b't' | b' ')
+        || b[13] != b':'
+        || b[16] != b':'
+    {
+        return None;
+    }
+
+    let (year, month, day) = (number(0..4)?, number(5..7)?, number(8..10)?);
+    let (hour, minute, second) = (number(11..13)?, number(14..16)?, number(17..19)?);
+    if !(1..=12).contains(&month)
+        || !(1..=days_in_month(year, month)).contains(&day)
+        || hour > 23
+        || minute > 59
+        || second > 59
+    {
+        return None;
+    }
+
+    let mut rest = &s[19..];
+    let mut nanos = 0;
+    if let Some(fraction) = rest.strip_prefix('.') {
+        let len = fraction
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(fraction.len());
+        if len == 0 {
+            return None;
+        }
+
+        nanos = fraction[..len]
+            .bytes()
+            .chain(core::iter::repeat(b'0'))
+            .take(9)
+            .fold(0, |acc, digit| acc * 10 + u32::from(digit - b'0'));
+        rest = &fraction[len..];
+    }
+
+    let zone = match rest.as_bytes() {
+        b"Z" | b"z" => 0,
+        [sign @ (b'+' | b'-'), h1, h2, b':', m1, m2] => {
+            let digit = |d: &u8| d.is_ascii_digit().then(|| i64::from(d - b'0'));
+            let (hours, minutes) = (digit(h1)? * 10 + digit(h2)?, digit(m1)? * 10 + digit(m2)?);
+            if hours > 23 || minutes > 59 {
+                return None;
+            }
+
+            let zone = hours * 3_600 + minutes * 60;
+            if *sign == b'-' { -zone } else { zone }
+        }
+        _ => return None,
+    };
+
+    let secs =
+        days_from_civil(year, month, day) * SECS_PER_DAY + hour * 3_600 + minute * 60 + second
+            - zone;
+
+    ntp64(Duration::new(u64::try_from(secs).ok()?, nanos))
+}
+
+fn write_rfc3339(f: &mut fmt::Formatter<'_>, time: NTP64) -> fmt::Result {
+    let time = time.to_duration();
+    let secs = i64::try_from(time.as_secs()).map_err(|_| fmt::Error)?;
+    let (year, month, day) = civil_from_days(secs.div_euclid(SECS_PER_DAY));
+    let secs = secs.rem_euclid(SECS_PER_DAY);
+
+    write!(
+        f,
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}",
+        secs / 3_600,
+        secs / 60 % 60,
+        secs % 60
+    )?;
+    if time.subsec_nanos() != 0 {
+        write!(f, ".{:09}", time.subsec_nanos())?;
+    }
+
+    f.write_str("Z")
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Days since the UNIX epoch of a proleptic Gregorian date.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+
+    era * 146_097 + day_of_era - 719_468
+}
+
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = (month_index + 2) % 12 + 1;
+    let year = era * 400 + year_of_era + i64::from(month <= 2);
+
+    (year, month, day)
+}
@@ -2,5 +2,6 @@ mod codec;
 mod ext;
 mod ke;
 mod msgs;
+mod parameters;
 mod random;
 mod r#struct;
@@ -1,4 +1,4 @@
-use crate::{KeyexprError, keyexpr};
+use crate::{KeTree, KeyexprError, keyexpr, nonwild_keyexpr};
 
 fn intersect(left: &str, right: &str) -> bool {
     let left = keyexpr::new(left).unwrap();
@@ -7,6 +7,27 @@ fn intersect(left: &str, right: &str) -> bool {
     left.intersects(right)
 }
 
+fn include(left: &str, right: &str) -> bool {
+    let left = keyexpr::new(left).unwrap();
+    let right = keyexpr::new(right).unwrap();
+
+    left.includes(right)
+}
+
+fn canon(ke: &str) -> core::result::Result<([u8; 64], usize), KeyexprError> {
+    let mut buf = [0u8; 64];
+    buf[..ke.len()].copy_from_slice(ke.as_bytes());
+
+    let ke = core::str::from_utf8_mut(&mut buf[..ke.len()]).unwrap();
+    let len = keyexpr::autocanonize(ke)?.len();
+
+    Ok((buf, len))
+}
+
+fn canonized(ke: &str, expected: &str) -> bool {
+    matches!(canon(ke), Ok((buf, len)) if &buf[..len] == expected.as_bytes())
+}
+
 fn err(ke: &str, err: KeyexprError) -> bool {
     let ke = keyexpr::new(ke);
     matches!(ke, Err(e) if e == err)
@@ -119,3 +140,189 @@ fn keyexpr_validation() {
     assert!(ok("demo/example$*-$*/test"));
     assert!(ok("demo/example$*"));
 }
+
+#[test]
+fn keyexpr_include() {
+    assert!(include("a", "a"));
+    assert!(include("*", "a"));
+    assert!(!include("a", "*"));
+    assert!(include("**", "a/b/c"));
+    assert!(include("**", "a/*/**"));
+    assert!(include("a/**", "a"));
+    assert!(include("a/**", "a/b/c"));
+    assert!(!include("a/*", "a/**"));
+    assert!(include("a/**/c", "a/b/*/c"));
+    assert!(!include("a/*/c", "a/**/c"));
+    assert!(include("a/**/c", "a/c"));
+    assert!(include("ab$*", "abc"));
+    assert!(include("ab$*", "abc$*"));
+    assert!(!include("abc$*", "ab$*"));
+    assert!(include("a$*d$*g", "abcdefg"));
+    assert!(include("a$*d$*g", "a$*d$*g"));
+    assert!(include("a$*g", "a$*d$*g"));
+    assert!(!include("a$*d$*g", "a$*g"));
+    assert!(!include("ab$*ba", "aba"));
+    assert!(include("*", "ab$*"));
+    assert!(!include("ab$*", "*"));
+
+    assert!(!include("*", "@a"));
+    assert!(!include("**", "@a"));
+    assert!(!include("a/**", "a/@b/c"));
+    assert!(include("a/**/@b/**", "a/x/@b/c"));
+    assert!(include("@a/**", "@a/b"));
+    assert!(!include("@a/**", "@a/@b"));
+}
+
+#[test]
+fn keyexpr_canonize() {
+    assert!(canonized("a/b", "a/b"));
+    assert!(canonized("a/**/**/b", "a/**/b"));
+    assert!(canonized("**/**/**", "**"));
+    assert!(canonized("a/**/*", "a/*/**"));
+    assert!(canonized("**/*/**/*/b", "*/*/**/b"));
+    assert!(canonized("a/$*", "a/*"));
+    assert!(canonized("a/$*$*/b", "a/*/b"));
+    assert!(canonized("a/b$*$*$*c", "a/b$*c"));
+    assert!(canonized("a/$*b$*$*", "a/$*b$*"));
+
+    assert!(matches!(canon("a//$*"), Err(KeyexprError::EmptyChunk)));
+}
+
+#[test]
+fn keyexpr_join() {
+    let ke = keyexpr::new("a/**").unwrap();
+    let mut buf = [0u8; 16];
+
+    assert_eq!(ke.join("**/b", &mut buf).unwrap().as_str(), "a/**/b");
+    assert_eq!(ke.join("*", &mut buf).unwrap().as_str(), "a/*/**");
+    assert_eq!(
+        ke.join("b/c/d/e/f/g/h", &mut buf),
+        Err(KeyexprError::KeyexprTooLong)
+    );
+
+    let ke = keyexpr::new("a/b").unwrap();
+    assert_eq!(ke.concat("$*", &mut buf).unwrap().as_str(), "a/b$*");
+    assert!(ke.concat("//c", &mut buf).is_err());
+
+    let ke = keyexpr::new("a/*").unwrap();
+    assert!(ke.concat("*", &mut buf).is_err());
+}
+
+#[test]
+fn keyexpr_nonwild() {
+    let ke = nonwild_keyexpr::new("a/b").unwrap();
+    assert_eq!(ke.as_str(), "a/b");
+    assert!(keyexpr::new("a/*").unwrap().includes(ke));
+
+    assert_eq!(nonwild_keyexpr::new("a/*"), Err(KeyexprError::WildChunk));
+    assert_eq!(nonwild_keyexpr::new("a/$*b"), Err(KeyexprError::WildChunk));
+    assert_eq!(nonwild_keyexpr::new("a//b"), Err(KeyexprError::EmptyChunk));
+}
+
+crate::kedefine!(
+    SENSOR: "robot/${id:*}/sensor/${kind:*}",
+    TREE: "tree/${path:**}/leaf/${name:$*-leaf#default-leaf}",
+);
+
+#[test]
+fn keyexpr_format() {
+    let ke = keyexpr::new("robot/r2d2/sensor/temp").unwrap();
+    let parsed = SENSOR.parse(ke).unwrap();
+    assert_eq!(parsed.get("id").unwrap().as_str(), "r2d2");
+    assert_eq!(parsed.get("kind").unwrap().as_str(), "temp");
+    assert!(parsed.get("other").is_none());
+
+    let ke = keyexpr::new("robot/r2d2/sensor/temp/extra").unwrap();
+    assert!(matches!(
+        SENSOR.parse(ke),
+        Err(KeyexprError::FormatMismatch)
+    ));
+
+    let ke = keyexpr::new("tree/a/b/leaf/my-leaf").unwrap();
+    let parsed = TREE.parse(ke).unwrap();
+    assert_eq!(parsed.get("path").unwrap().as_str(), "a/b");
+    assert_eq!(parsed.get("name").unwrap().as_str(), "my-leaf");
+
+    let ke = keyexpr::new("tree/leaf/my-leaf").unwrap();
+    let parsed = TREE.parse(ke).unwrap();
+    assert!(parsed.get("path").is_none());
+    assert_eq!(parsed.iter().count(), 2);
+
+    let ke = keyexpr::new("tree/a/leaf/leaf").unwrap();
+    assert!(TREE.parse(ke).is_err());
+}
+
+#[test]
+fn keyexpr_formatter() {
+    let mut buf = [0u8; 64];
+
+    let formatter = SENSOR.formatter().set("id", "r2d2").unwrap();
+    assert!(matches!(
+        formatter.build(&mut buf),
+        Err(KeyexprError::MissingField)
+    ));
+
+    let formatter = formatter.set("kind", "temp").unwrap();
+    assert_eq!(
+        formatter.build(&mut buf).unwrap().as_str(),
+        "robot/r2d2/sensor/temp"
+    );
+
+    assert!(matches!(
+        SENSOR.formatter().set("id", "a/b"),
+        Err(KeyexprError::FormatMismatch)
+    ));
+    assert!(matches!(
+        SENSOR.formatter().set("unknown", "a"),
+        Err(KeyexprError::UnknownField)
+    ));
+
+    let formatter = TREE.formatter().set("path", "").unwrap();
+    assert_eq!(
+        formatter.build(&mut buf).unwrap().as_str(),
+        "tree/leaf/default-leaf"
+    );
+
+    let formatter = TREE.formatter().set("path", "a/*").unwrap();
+    assert!(matches!(
+        formatter.build(&mut [0u8; 8]),
+        Err(KeyexprError::KeyexprTooLong)
+    ));
+}
+
+#[test]
+fn keyexpr_tree() {
+    const KEYEXPRS: &[&str] = &[
+        "a", "a/b", "a/b/c", "a/*", "a/**", "a/*/c", "a/**/c", "a/b$*", "a/$*b", "a/c/d", "**",
+        "*/b", "b/**", "@a/b", "a/@b/**", "x/y/z",
+    ];
+
+    let mut tree: [&keyexpr; KEYEXPRS.len()] = [keyexpr::new("a").unwrap(); KEYEXPRS.len()];
+    for (len, ke) in KEYEXPRS.iter().enumerate() {
+        let ke = keyexpr::new(ke).unwrap();
+        let position = KeTree::new(&tree[..len]).position(ke);
+        tree.copy_within(position..len, position + 1);
+        tree[position] = ke;
+    }
+
+    assert!(tree.is_sorted_by(|a, b| a.tree_cmp(b).is_le()));
+
+    let incoming = KEYEXPRS.iter().chain(&[
+        "a/b/c/d", "a/bb", "a/xb", "a/c", "b", "c", "@a", "@a/b", "a/@b", "a/@b/c", "a/b/**",
+        "*/*", "a/$*c", "x/*/z", "**/c",
+    ]);
+
+    for ke in incoming {
+        let ke = keyexpr::new(ke).unwrap();
+        let mut expected = tree.iter().enumerate().filter(|(_, e)| e.intersects(ke));
+
+        let mut last = None;
+        KeTree::new(&tree[..]).intersecting(ke, |index| {
+            assert!(last < Some(index));
+            assert_eq!(expected.next().map(|(i, _)| i), Some(index), "{ke}");
+            last = Some(index);
+        });
+
+        assert!(expected.next().is_none(), "{ke}");
+    }
+}
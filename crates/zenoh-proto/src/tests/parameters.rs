@@ -0,0 +1,199 @@
+use uhlc::NTP64;
+
+use crate::{
+    Parameters, ParametersBuilder, ParametersError, Selector, TimeBound, TimeExpr, TimeRange,
+    keyexpr,
+};
+
+fn secs(secs: u64) -> NTP64 {
+    NTP64::from(core::time::Duration::from_secs(secs))
+}
+
+fn range(s: &str) -> TimeRange {
+    TimeRange::parse(s).unwrap()
+}
+
+#[test]
+fn parameters_iter() {
+    let parameters = Parameters::new(";a=1;b;;c=x=y;a=2;d=1|2|3;");
+
+    let mut iter = parameters.iter();
+    assert_eq!(iter.next(), Some(("a", "1")));
+    assert_eq!(iter.next(), Some(("b", "")));
+    assert_eq!(iter.next(), Some(("c", "x=y")));
+    assert_eq!(iter.next(), Some(("a", "2")));
+    assert_eq!(iter.next(), Some(("d", "1|2|3")));
+    assert_eq!(iter.next(), None);
+
+    assert_eq!(parameters.get("a"), Some("1"));
+    assert_eq!(parameters.get("b"), Some(""));
+    assert_eq!(parameters.get("c"), Some("x=y"));
+    assert_eq!(parameters.get("e"), None);
+    assert!(parameters.contains_key("b"));
+    assert!(!parameters.contains_key("e"));
+
+    let mut values = parameters.values("d");
+    assert_eq!(values.next(), Some("1"));
+    assert_eq!(values.next(), Some("2"));
+    assert_eq!(values.next(), Some("3"));
+    assert_eq!(values.next(), None);
+    assert_eq!(parameters.values("e").next(), None);
+
+    assert!(Parameters::new(";;").is_empty());
+    assert!(!parameters.is_empty());
+}
+
+#[test]
+fn parameters_builder() {
+    let mut buf = [0u8; 64];
+    let parameters = ParametersBuilder::new(&mut buf)
+        .insert("a", "1")
+        .unwrap()
+        .insert("b", "")
+        .unwrap()
+        .values("c", ["x", "y"])
+        .unwrap()
+        .build();
+
+    assert_eq!(parameters.as_str(), "a=1;b;c=x|y");
+    assert_eq!(parameters.get("b"), Some(""));
+
+    let mut buf = [0u8; 64];
+    let builder = ParametersBuilder::new(&mut buf).insert("a", "1").unwrap();
+    assert!(matches!(
+        builder.insert("a", "2"),
+        Err(ParametersError::DuplicateParameter)
+    ));
+
+    for (key, value) in [("", "1"), ("a;b", "1"), ("a=b", "1"), ("b", "1;2")] {
+        let mut buf = [0u8; 64];
+        assert!(matches!(
+            ParametersBuilder::new(&mut buf).insert(key, value),
+            Err(ParametersError::InvalidParameter)
+        ));
+    }
+
+    let mut buf = [0u8; 64];
+    assert!(matches!(
+        ParametersBuilder::new(&mut buf).values("a", ["1|2"]),
+        Err(ParametersError::InvalidParameter)
+    ));
+
+    let mut buf = [0u8; 4];
+    assert!(matches!(
+        ParametersBuilder::new(&mut buf).insert("key", "value"),
+        Err(ParametersError::ParametersTooLong)
+    ));
+}
+
+#[test]
+fn parameters_time_range() {
+    assert_eq!(
+        range("[..]"),
+        TimeRange {
+            start: TimeBound::Unbounded,
+            end: TimeBound::Unbounded,
+        }
+    );
+    assert_eq!(
+        range("[now(-1h)..now()["),
+        TimeRange {
+            start: TimeBound::Inclusive(TimeExpr::Now {
+                offset_secs: -3600.0
+            }),
+            end: TimeBound::Exclusive(TimeExpr::Now { offset_secs: 0.0 }),
+        }
+    );
+    assert_eq!(
+        range("]1970-01-02T00:00:00Z..2000-03-01T01:02:03.5+01:00]"),
+        TimeRange {
+            start: TimeBound::Exclusive(TimeExpr::Fixed(secs(86_400))),
+            end: TimeBound::Inclusive(TimeExpr::Fixed(
+                secs(951_868_923) + NTP64::from(core::time::Duration::from_millis(500))
+            )),
+        }
+    );
+
+    for s in [
+        "[now(-1.5m)..]",
+        "[now(-90s)..]",
+        "[now(-90)..]",
+        "[now(-90000ms)..]",
+        "[now(-90000000us)..]",
+        "[now(-0.025h)..]",
+    ] {
+        let TimeBound::Inclusive(TimeExpr::Now { offset_secs }) = range(s).start else {
+            panic!("{s}");
+        };
+
+        assert!((offset_secs + 90.0).abs() < 1e-6, "{s}");
+    }
+
+    for invalid in [
+        "",
+        "[",
+        "[]",
+        "(..)",
+        "[now(1y)..]",
+        "[now(1h..]",
+        "[1970-13-01T00:00:00Z..]",
+        "[2023-02-29T00:00:00Z..]",
+        "[1970-01-01T00:00:00..]",
+        "[1969-12-31T23:59:59Z..]",
+    ] {
+        assert!(
+            matches!(
+                TimeRange::parse(invalid),
+                Err(ParametersError::InvalidTimeRange)
+            ),
+            "{invalid}"
+        );
+    }
+
+    let last_hour = range("[now(-1h)..now()]");
+    assert!(last_hour.contains(secs(10_000), secs(10_000)));
+    assert!(last_hour.contains(secs(6_400), secs(10_000)));
+    assert!(!last_hour.contains(secs(6_399), secs(10_000)));
+    assert!(!last_hour.contains(secs(10_001), secs(10_000)));
+    assert!(range("[now(-1h)..]").contains(secs(1_000), secs(1_000)));
+    assert!(!range("]1970-01-01T00:16:40Z..]").contains(secs(1_000), secs(0)));
+
+    let parameters = Parameters::new("a=1;_time=[now(-1.5m)..]");
+    assert_eq!(
+        parameters.time_range().unwrap(),
+        Some(TimeRange {
+            start: TimeBound::Inclusive(TimeExpr::Now { offset_secs: -90.0 }),
+            end: TimeBound::Unbounded,
+        })
+    );
+    assert_eq!(Parameters::new("a=1").time_range().unwrap(), None);
+    assert!(Parameters::new("_time=now").time_range().is_err());
+
+    for s in [
+        "[..]",
+        "[now(-90s)..now()[",
+        "]2000-03-01T00:02:03Z..2024-02-29T23:59:59.250000000Z]",
+    ] {
+        let mut buf = [0u8; 96];
+        let parameters = ParametersBuilder::new(&mut buf)
+            .time_range(&range(s))
+            .unwrap()
+            .build();
+
+        assert_eq!(parameters.get("_time"), Some(s));
+        assert_eq!(parameters.time_range().unwrap(), Some(range(s)));
+    }
+}
+
+#[test]
+fn parameters_selector() {
+    let selector = Selector::parse("a/*/c?x=1;y").unwrap();
+    assert_eq!(selector.keyexpr(), keyexpr::new("a/*/c").unwrap());
+    assert_eq!(selector.parameters().get("x"), Some("1"));
+    assert!(selector.parameters().contains_key("y"));
+
+    let selector = Selector::parse("a/b").unwrap();
+    assert!(selector.parameters().is_empty());
+
+    assert!(Selector::parse("a//b?x=1").is_err());
+}
@@ -136,6 +136,26 @@ impl<'a> Put<'a> {
         }
     }
 }
+impl<'a> Del<'a> {
+    #[cfg(test)]
+    pub(crate) fn rand(w: &mut impl crate::ZStoreable<'a>) -> Self {
+        let timestamp = thread_rng().gen_bool(0.5).then_some({
+            let time = uhlc::NTP64(thread_rng().r#gen());
+            let id = uhlc::ID::try_from(ZenohIdProto::default().as_le_bytes()).unwrap();
+            Timestamp::new(time, id)
+        });
+
+        let sinfo = thread_rng().gen_bool(0.5).then_some(SourceInfo::rand(w));
+        let attachment = thread_rng().gen_bool(0.5).then_some(Attachment::rand(w));
+
+        Self {
+            timestamp,
+            sinfo,
+            attachment,
+        }
+    }
+}
+
 impl<'a> Query<'a> {
     #[cfg(test)]
     pub(crate) fn rand(w: &mut impl crate::ZStoreable<'a>) -> Self {
@@ -175,10 +195,11 @@ impl<'a> PushBody<'a> {
     pub(crate) fn rand(w: &mut impl crate::ZStoreable<'a>) -> Self {
         use rand::seq::SliceRandom;
         let mut rng = rand::thread_rng();
-        let choices = [Put::ID];
+        let choices = [Put::ID, Del::ID];
 
         match *choices.choose(&mut rng).unwrap() {
             Put::ID => PushBody::Put(Put::rand(w)),
+            Del::ID => PushBody::Del(Del::rand(w)),
             _ => unreachable!(),
         }
     }
@@ -730,6 +751,38 @@ impl<'a> Auth<'a> {
     }
 }
 
+impl UsrPwdNonce {
+    #[cfg(test)]
+    pub(crate) fn rand<'a>(_: &mut impl crate::ZStoreable<'a>) -> Self {
+        Self {
+            nonce: thread_rng().r#gen(),
+        }
+    }
+}
+
+impl<'a> UsrPwdOpenSyn<'a> {
+    #[cfg(test)]
+    pub(crate) fn rand(w: &mut impl crate::ZStoreable<'a>) -> Self {
+        let user = unsafe {
+            w.store(thread_rng().gen_range(0..=32), |b: &mut [u8]| {
+                thread_rng().fill(b);
+                b.len()
+            })
+            .unwrap()
+        };
+
+        let hmac = unsafe {
+            w.store(32, |b: &mut [u8]| {
+                thread_rng().fill(b);
+                b.len()
+            })
+            .unwrap()
+        };
+
+        Self { user, hmac }
+    }
+}
+
 impl<'a> MultiLink<'a> {
     #[cfg(test)]
     pub(crate) fn rand(w: &mut impl crate::ZStoreable<'a>) -> Self {
@@ -798,6 +851,31 @@ impl FrameHeader {
     }
 }
 
+impl<'a> Fragment<'a> {
+    #[cfg(test)]
+    pub(crate) fn rand(w: &mut impl crate::ZStoreable<'a>) -> Self {
+        let reliability = Reliability::rand(w);
+        let more = FragmentMore::rand(w);
+        let sn = rand::thread_rng().r#gen();
+        let qos = QoS::rand(w);
+        let payload = unsafe {
+            w.store(thread_rng().gen_range(0..=64), |b: &mut [u8]| {
+                thread_rng().fill(b);
+                b.len()
+            })
+            .unwrap()
+        };
+
+        Self {
+            reliability,
+            more,
+            sn,
+            qos,
+            payload,
+        }
+    }
+}
+
 impl InitIdentifier {
     #[cfg(test)]
     pub(crate) fn rand<'a>(w: &mut impl crate::ZStoreable<'a>) -> Self {
@@ -67,7 +67,19 @@ crate::declare_zerror! {
         #[doc = "A wildcard chunk was found where it is not allowed."]
         #[err = "wildcard chunk not allowed"]
         WildChunk = 48,
-        // Reserved: 49-59 for future KeyexprError variants
+        #[doc = "The resulting expression does not fit in the destination buffer."]
+        #[err = "expression does not fit in destination buffer"]
+        KeyexprTooLong = 49,
+        #[doc = "An expression does not match a key expression format."]
+        #[err = "expression does not match format"]
+        FormatMismatch = 50,
+        #[doc = "A key expression format has no field with this name."]
+        #[err = "unknown format field"]
+        UnknownField = 51,
+        #[doc = "A field of a key expression format was left without a value."]
+        #[err = "missing format field value"]
+        MissingField = 52,
+        // Reserved: 53-59 for future KeyexprError variants
     }
 
     #[doc = "Errors related to zenoh endpoints."]
@@ -104,7 +116,13 @@ crate::declare_zerror! {
         #[doc = "Received an invalid attribute."]
         #[err = "invalid attribute in message"]
         InvalidAttribute = 83,
-        // Reserved: 84-99 for future TransportError variants
+        #[doc = "Received a cookie that was not sealed by this listener."]
+        #[err = "invalid cookie"]
+        InvalidCookie = 84,
+        #[doc = "The peer could not be authenticated with user and password."]
+        #[err = "usrpwd authentication failed"]
+        UsrPwdAuthFailed = 85,
+        // Reserved: 86-99 for future TransportError variants
     }
     #[doc = "Errors related to zenoh links."]
     enum LinkError: EndpointError {
@@ -175,6 +193,23 @@ crate::declare_zerror! {
     enum BrokerError: TransportLinkError + CollectionError + KeyexprError {
         // Reserved: 160-169 for future BrokerError variants
     }
+
+    #[doc = "Errors related to zenoh query parameters."]
+    pub enum ParametersError {
+        #[doc = "A parameter key or value holds a separator."]
+        #[err = "separator in parameter key or value"]
+        InvalidParameter = 170,
+        #[doc = "The parameters do not fit in the destination buffer."]
+        #[err = "parameters do not fit in destination buffer"]
+        ParametersTooLong = 171,
+        #[doc = "A parameter key was given twice."]
+        #[err = "duplicate parameter key"]
+        DuplicateParameter = 172,
+        #[doc = "Could not parse a time range."]
+        #[err = "could not parse time range"]
+        InvalidTimeRange = 173,
+        // Reserved: 174-179 for future ParametersError variants
+    }
 }
 
 #[derive(Debug)]
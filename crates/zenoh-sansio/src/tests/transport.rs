@@ -1,6 +1,44 @@
-use crate::{Transport, ZTransportRx, ZTransportTx, transport::establishment::State};
+use crate::{
+    Transport, UsrPwd, ZTransportRx, ZTransportTx,
+    transport::{establishment::State, sn::SeqNums},
+};
 use core::{cell::RefCell, time::Duration};
-use zenoh_proto::{exts::*, fields::*, keyexpr, msgs::*};
+use zenoh_proto::{EitherError, TransportError, exts::*, fields::*, keyexpr, msgs::*};
+
+/// Encodes `msg` in `buff` and decodes it back, so that it no longer borrows the state
+/// that produced it.
+macro_rules! relay {
+    ($buff:expr, $msg:expr) => {{
+        let msg = $msg;
+        let buff = &mut $buff[..];
+        let len = {
+            let total = buff.len();
+            let mut writer = &mut *buff;
+            match &msg {
+                TransportMessage::InitAck(x) => zenoh_proto::ZEncode::z_encode(x, &mut writer),
+                TransportMessage::OpenSyn(x) => zenoh_proto::ZEncode::z_encode(x, &mut writer),
+                TransportMessage::OpenAck(x) => zenoh_proto::ZEncode::z_encode(x, &mut writer),
+                _ => unreachable!(),
+            }
+            .unwrap();
+            total - writer.len()
+        };
+
+        let mut reader = &buff[..len];
+        match msg {
+            TransportMessage::InitAck(_) => TransportMessage::InitAck(
+                <InitAck as zenoh_proto::ZDecode>::z_decode(&mut reader).unwrap(),
+            ),
+            TransportMessage::OpenSyn(_) => TransportMessage::OpenSyn(
+                <OpenSyn as zenoh_proto::ZDecode>::z_decode(&mut reader).unwrap(),
+            ),
+            TransportMessage::OpenAck(_) => TransportMessage::OpenAck(
+                <OpenAck as zenoh_proto::ZDecode>::z_decode(&mut reader).unwrap(),
+            ),
+            _ => unreachable!(),
+        }
+    }};
+}
 
 #[test]
 fn transport_state_handshake() {
@@ -10,6 +48,12 @@ fn transport_state_handshake() {
         mine_batch_size: 512,
         mine_resolution: Resolution::default(),
         mine_lease: Duration::from_secs(30),
+        mine_qos: false,
+        mine_compression: false,
+        cookie_key: [7; 32],
+        rng: |bytes| bytes.fill(3),
+        clock: || Duration::ZERO,
+        usrpwd: &[],
     };
 
     let b_zid = ZenohIdProto::default();
@@ -18,6 +62,9 @@ fn transport_state_handshake() {
         mine_batch_size: 1025,
         mine_resolution: Resolution::default(),
         mine_lease: Duration::from_secs(37),
+        mine_qos: false,
+        mine_compression: false,
+        usrpwd: None,
     };
 
     let init = InitSyn {
@@ -32,37 +79,238 @@ fn transport_state_handshake() {
         ..Default::default()
     };
 
-    let mut buff = [0u8; 128];
+    let (mut ack_buff, mut open_buff, mut open_ack_buff) = ([0u8; 128], [0u8; 128], [0u8; 128]);
 
-    macro_rules! buff {
-        ($msg:expr) => {{
-            let mut writer = &mut buff[..];
-            <InitSyn as zenoh_proto::ZEncode>::z_encode($msg, &mut writer).unwrap();
-            let len = 128 - writer.len();
+    let (ack, _) = a.poll(TransportMessage::InitSyn(init)).unwrap();
+    let ack = relay!(ack_buff, ack.unwrap());
+    let (open, _) = b.poll(ack).unwrap();
+    let open = relay!(open_buff, open.unwrap());
+    let (open_ack, desc) = a.poll(open).unwrap();
+    let open_ack = relay!(open_ack_buff, open_ack.unwrap());
+    let (none, _) = b.poll(open_ack).unwrap();
 
-            &buff[..len]
-        }};
-    }
+    assert!(none.is_none());
+    assert!(desc.is_some());
+    assert!(a.description().is_some() && b.description().is_some());
+    assert_eq!(desc.unwrap().batch_size, 512);
+    assert_eq!(desc.unwrap().resolution, Resolution::default());
+    assert_eq!(desc.unwrap().other_zid, b_zid);
+    assert_eq!(b.description().unwrap().batch_size, 512);
+}
 
-    let mut buff = buff!(&init);
-    let mut next = Some(TransportMessage::InitSyn(init));
-    let mut desc = None;
-    let mut current = &mut a;
-    let mut other = &mut b;
+#[test]
+fn transport_state_handshake_qos() {
+    let a_zid = ZenohIdProto::default();
+    let mut a = State::WaitingInitSyn {
+        mine_zid: a_zid,
+        mine_batch_size: 512,
+        mine_resolution: Resolution::default(),
+        mine_lease: Duration::from_secs(30),
+        mine_qos: true,
+        mine_compression: true,
+        cookie_key: [7; 32],
+        rng: |bytes| bytes.fill(3),
+        clock: || Duration::ZERO,
+        usrpwd: &[],
+    };
 
-    for _ in 0..4 {
-        if let Some(response) = next {
-            (next, desc) = current.poll((response, buff));
-            core::mem::swap(&mut current, &mut other);
+    let b_zid = ZenohIdProto::default();
+    let mut b = State::WaitingInitAck {
+        mine_zid: b_zid,
+        mine_batch_size: 512,
+        mine_resolution: Resolution::default(),
+        mine_lease: Duration::from_secs(30),
+        mine_qos: true,
+        mine_compression: true,
+        usrpwd: None,
+    };
 
-            buff = &[];
-        }
+    let init = InitSyn {
+        identifier: InitIdentifier {
+            zid: b_zid,
+            ..Default::default()
+        },
+        resolution: InitResolution {
+            resolution: Resolution::default(),
+            batch_size: BatchSize(512),
+        },
+        qos: Some(HasQoS {}),
+        compression: Some(HasCompression {}),
+        ..Default::default()
+    };
+
+    let (mut ack_buff, mut open_buff, mut open_ack_buff) = ([0u8; 128], [0u8; 128], [0u8; 128]);
+
+    let (ack, _) = a.poll(TransportMessage::InitSyn(init)).unwrap();
+    let ack = relay!(ack_buff, ack.unwrap());
+    let (open, _) = b.poll(ack).unwrap();
+    let open = relay!(open_buff, open.unwrap());
+    let (open_ack, _) = a.poll(open).unwrap();
+    let open_ack = relay!(open_ack_buff, open_ack.unwrap());
+    b.poll(open_ack).unwrap();
+
+    assert!(a.description().unwrap().qos);
+    assert!(b.description().unwrap().qos);
+    assert!(a.description().unwrap().compression);
+    assert!(b.description().unwrap().compression);
+}
+
+#[test]
+fn transport_state_handshake_tampered_cookie() {
+    let mut a = State::WaitingInitSyn {
+        mine_zid: ZenohIdProto::default(),
+        mine_batch_size: 512,
+        mine_resolution: Resolution::default(),
+        mine_lease: Duration::from_secs(30),
+        mine_qos: false,
+        mine_compression: false,
+        cookie_key: [7; 32],
+        rng: |bytes| bytes.fill(3),
+        clock: || Duration::ZERO,
+        usrpwd: &[],
+    };
+
+    let b_zid = ZenohIdProto::default();
+    let mut b = State::WaitingInitAck {
+        mine_zid: b_zid,
+        mine_batch_size: 512,
+        mine_resolution: Resolution::default(),
+        mine_lease: Duration::from_secs(30),
+        mine_qos: false,
+        mine_compression: false,
+        usrpwd: None,
+    };
+
+    let init = InitSyn {
+        identifier: InitIdentifier {
+            zid: b_zid,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+
+    let (mut ack_buff, mut open_buff) = ([0u8; 128], [0u8; 128]);
+
+    let (ack, _) = a.poll(TransportMessage::InitSyn(init)).unwrap();
+    let ack = relay!(ack_buff, ack.unwrap());
+    let (open, _) = b.poll(ack).unwrap();
+    let TransportMessage::OpenSyn(open) = relay!(open_buff, open.unwrap()) else {
+        unreachable!()
+    };
+
+    for i in 0..open.cookie.len() {
+        let mut cookie = [0u8; 128];
+        let cookie = &mut cookie[..open.cookie.len()];
+        cookie.copy_from_slice(open.cookie);
+        cookie[i] ^= 0x01;
+
+        let mut a = a;
+        let ret = a.poll(TransportMessage::OpenSyn(OpenSyn {
+            lease: open.lease,
+            sn: open.sn,
+            cookie,
+            ..Default::default()
+        }));
+        assert!(matches!(ret, Err(TransportError::InvalidCookie)));
+        assert!(a.description().is_none());
     }
 
-    assert!(desc.is_some());
-    assert!(a.description().is_some() && b.description().is_some());
-    assert_eq!(desc.unwrap().batch_size, 512);
-    assert_eq!(desc.unwrap().resolution, Resolution::default());
+    let mut other = State::WaitingInitSyn {
+        mine_zid: ZenohIdProto::default(),
+        mine_batch_size: 512,
+        mine_resolution: Resolution::default(),
+        mine_lease: Duration::from_secs(30),
+        mine_qos: false,
+        mine_compression: false,
+        cookie_key: [8; 32],
+        rng: |bytes| bytes.fill(3),
+        clock: || Duration::ZERO,
+        usrpwd: &[],
+    };
+    let (ack, _) = other
+        .poll(TransportMessage::InitSyn(InitSyn {
+            identifier: InitIdentifier {
+                zid: b_zid,
+                ..Default::default()
+            },
+            ..Default::default()
+        }))
+        .unwrap();
+    assert!(ack.is_some());
+
+    let ret = other.poll(TransportMessage::OpenSyn(OpenSyn {
+        lease: open.lease,
+        sn: open.sn,
+        cookie: open.cookie,
+        ..Default::default()
+    }));
+    assert!(matches!(ret, Err(TransportError::InvalidCookie)));
+
+    let (response, desc) = a.poll(TransportMessage::OpenSyn(open)).unwrap();
+    assert!(response.is_some() && desc.is_some());
+    assert_eq!(desc.unwrap().other_zid, b_zid);
+}
+
+#[test]
+fn transport_state_handshake_expired_cookie() {
+    use core::sync::atomic::{AtomicU64, Ordering};
+
+    static NOW: AtomicU64 = AtomicU64::new(1_000);
+
+    let mut a = State::WaitingInitSyn {
+        mine_zid: ZenohIdProto::default(),
+        mine_batch_size: 512,
+        mine_resolution: Resolution::default(),
+        mine_lease: Duration::from_secs(30),
+        mine_qos: false,
+        mine_compression: false,
+        cookie_key: [7; 32],
+        rng: |bytes| bytes.fill(3),
+        clock: || Duration::from_millis(NOW.load(Ordering::Relaxed)),
+        usrpwd: &[],
+    };
+
+    let mut b = State::WaitingInitAck {
+        mine_zid: ZenohIdProto::default(),
+        mine_batch_size: 512,
+        mine_resolution: Resolution::default(),
+        mine_lease: Duration::from_secs(30),
+        mine_qos: false,
+        mine_compression: false,
+        usrpwd: None,
+    };
+
+    let init = InitSyn {
+        identifier: InitIdentifier {
+            zid: ZenohIdProto::default(),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+
+    let (mut ack_buff, mut open_buff) = ([0u8; 128], [0u8; 128]);
+
+    let (ack, _) = a.poll(TransportMessage::InitSyn(init)).unwrap();
+    let ack = relay!(ack_buff, ack.unwrap());
+    let (open, _) = b.poll(ack).unwrap();
+    let TransportMessage::OpenSyn(open) = relay!(open_buff, open.unwrap()) else {
+        unreachable!()
+    };
+
+    NOW.store(1_000 + 30_001, Ordering::Relaxed);
+    let mut expired = a;
+    let ret = expired.poll(TransportMessage::OpenSyn(OpenSyn {
+        lease: open.lease,
+        sn: open.sn,
+        cookie: open.cookie,
+        ..Default::default()
+    }));
+    assert!(matches!(ret, Err(TransportError::InvalidCookie)));
+    assert!(expired.description().is_none());
+
+    NOW.store(1_000 + 30_000, Ordering::Relaxed);
+    let (response, desc) = a.poll(TransportMessage::OpenSyn(open)).unwrap();
+    assert!(response.is_some() && desc.is_some());
 }
 
 #[test]
@@ -171,9 +419,97 @@ fn transport_handshake_streamed() {
         .open();
 }
 
+type Opened = core::result::Result<Option<Transport<[u8; 512]>>, TransportError>;
+
+fn usrpwd_handshake(listener: &'static [UsrPwd], connector: Option<UsrPwd>) -> (Opened, Opened) {
+    let socket = ([0u8; 512], 0usize, 0usize);
+    let socket_ref = RefCell::new(socket);
+
+    let a = Transport::builder([0u8; 512]).with_usrpwd_dictionary(listener);
+    let b = match connector {
+        Some(usrpwd) => Transport::builder([0u8; 512]).with_usrpwd(usrpwd),
+        None => Transport::builder([0u8; 512]),
+    };
+
+    let read = |socket: &mut &RefCell<([u8; 512], usize, usize)>,
+                bytes: &mut [u8]|
+     -> core::result::Result<usize, i32> {
+        let mut borrow_mut = socket.borrow_mut();
+
+        let to_read = bytes.len().min(borrow_mut.2);
+
+        let slice = &borrow_mut.0[borrow_mut.1..(to_read + borrow_mut.1)];
+        bytes[..slice.len()].copy_from_slice(slice);
+        borrow_mut.1 += to_read;
+
+        Ok(to_read)
+    };
+
+    let write = |socket: &mut &RefCell<([u8; 512], usize, usize)>,
+                 bytes: &[u8]|
+     -> core::result::Result<(), i32> {
+        let mut borrow_mut = socket.borrow_mut();
+        borrow_mut.0[..bytes.len()].copy_from_slice(bytes);
+        borrow_mut.1 = 0;
+        borrow_mut.2 = bytes.len();
+        Ok(())
+    };
+
+    let mut ha = a.listen(&socket_ref, &read, &write);
+    let mut hb = b.connect(&socket_ref, &read, &write);
+
+    macro_rules! poll {
+        ($h:expr) => {
+            $h.poll()
+                .map(|ready| ready.map(|ready| ready.open()))
+                .map_err(|e| match e {
+                    EitherError::A(e) => e,
+                    EitherError::B(_) => unreachable!(),
+                })
+        };
+    }
+
+    hb.poll().unwrap();
+
+    for _ in 0..2 {
+        if let Err(e) = poll!(ha) {
+            return (Err(e), Ok(None));
+        }
+        if let Err(e) = poll!(hb) {
+            return (Ok(None), Err(e));
+        }
+    }
+
+    (poll!(ha), poll!(hb))
+}
+
+#[test]
+fn transport_handshake_usrpwd() {
+    static DICTIONARY: [UsrPwd; 2] = [
+        UsrPwd::new("alice", "wonderland"),
+        UsrPwd::new("bob", "builder"),
+    ];
+
+    let (a, b) = usrpwd_handshake(&DICTIONARY, Some(UsrPwd::new("bob", "builder")));
+    assert!(a.unwrap().is_some() && b.unwrap().is_some());
+
+    let (a, _) = usrpwd_handshake(&DICTIONARY, Some(UsrPwd::new("bob", "wonderland")));
+    assert!(matches!(a, Err(TransportError::UsrPwdAuthFailed)));
+
+    let (a, _) = usrpwd_handshake(&DICTIONARY, Some(UsrPwd::new("carol", "builder")));
+    assert!(matches!(a, Err(TransportError::UsrPwdAuthFailed)));
+
+    let (a, _) = usrpwd_handshake(&DICTIONARY, None);
+    assert!(matches!(a, Err(TransportError::UsrPwdAuthFailed)));
+
+    // Credentials are only sent when the listener asks for them
+    let (a, b) = usrpwd_handshake(&[], Some(UsrPwd::new("bob", "builder")));
+    assert!(a.unwrap().is_some() && b.unwrap().is_some());
+}
+
 #[test]
 fn transport_streamed_codec() {
-    let mut transport = Transport::builder([0u8; 512]).codec();
+    let mut transport = Transport::builder([0u8; 512]).with_qos(true).codec();
 
     let msg = NetworkMessage {
         reliability: Reliability::Reliable,
@@ -200,3 +536,555 @@ fn transport_streamed_codec() {
     assert_eq!(flush.count(), 0);
     assert_eq!(m, msg);
 }
+
+#[test]
+fn transport_close() {
+    let mut transport = Transport::builder([0u8; 512]).codec();
+
+    transport.tx.close(Close::EXPIRED);
+    assert!(transport.tx.closed());
+
+    let bytes = transport.tx.flush_prefixed().unwrap();
+    let close = <Close as zenoh_proto::ZDecode>::z_decode(&mut &bytes[2..]).unwrap();
+
+    assert_eq!(close.reason, Close::EXPIRED);
+    assert_eq!(close.behaviour, CloseBehaviour::Session);
+}
+
+#[test]
+fn transport_peer_close() {
+    let mut transport = Transport::builder([0u8; 512]).codec();
+    assert!(transport.rx.peer_close().is_none());
+
+    transport.tx.close(Close::GENERIC);
+    transport
+        .rx
+        .decode_prefixed(transport.tx.flush_prefixed().unwrap())
+        .unwrap();
+
+    assert_eq!(transport.rx.flush().count(), 0);
+    assert!(transport.rx.closed());
+    assert_eq!(
+        transport.rx.peer_close(),
+        Some(&Close {
+            reason: Close::GENERIC,
+            behaviour: CloseBehaviour::Session,
+        })
+    );
+}
+
+#[cfg(feature = "compression")]
+#[test]
+fn transport_compressed_codec() {
+    let mut transport = Transport::builder([0u8; 512])
+        .with_qos(true)
+        .with_compression([0u8; 512])
+        .codec();
+
+    let payload = [b'a'; 128];
+    let msg = NetworkMessage {
+        reliability: Reliability::Reliable,
+        qos: QoS::declare(),
+        body: NetworkBody::Push(Push {
+            wire_expr: WireExpr::from(keyexpr::from_str_unchecked("abc/def")),
+            payload: PushBody::Put(Put {
+                payload: &payload,
+                ..Default::default()
+            }),
+            ..Default::default()
+        }),
+    };
+
+    for prefixed in [true, false] {
+        transport
+            .tx
+            .encode_ref(core::iter::repeat_n(msg.as_ref(), 3));
+
+        let bytes = transport.tx.flush(prefixed).unwrap();
+        assert!(bytes.len() < payload.len());
+
+        let mut batch = [0u8; 512];
+        batch[..bytes.len()].copy_from_slice(bytes);
+        transport
+            .rx
+            .decode(&batch[..bytes.len()], prefixed)
+            .unwrap();
+
+        let mut flush = transport.rx.flush();
+        for _ in 0..3 {
+            assert_eq!(flush.next().unwrap().0, msg);
+        }
+        assert_eq!(flush.count(), 0);
+    }
+
+    // Incompressible batches are sent as is
+    let payload = [0x5a, 0x13, 0xc7, 0x88, 0x01, 0xfe, 0x3b, 0x62];
+    let msg = NetworkMessage {
+        body: NetworkBody::Push(Push {
+            wire_expr: WireExpr::from(keyexpr::from_str_unchecked("abc/def")),
+            payload: PushBody::Put(Put {
+                payload: &payload,
+                ..Default::default()
+            }),
+            ..Default::default()
+        }),
+        ..msg
+    };
+
+    transport.tx.encode_ref(core::iter::once(msg.as_ref()));
+    let bytes = transport.tx.flush_raw().unwrap();
+    assert_eq!(bytes[0], 0);
+
+    let mut batch = [0u8; 512];
+    batch[..bytes.len()].copy_from_slice(bytes);
+    transport.rx.decode_raw(&batch[..bytes.len()]).unwrap();
+
+    let mut flush = transport.rx.flush();
+    assert_eq!(flush.next().unwrap().0, msg);
+    assert_eq!(flush.count(), 0);
+}
+
+#[test]
+fn transport_fragmented_codec() {
+    let mut transport = Transport::builder([0u8; 512])
+        .with_batch_size(64)
+        .with_defrag([0u8; 512])
+        .codec();
+
+    let payload = core::array::from_fn::<u8, 300, _>(|i| i as u8);
+    let msg = || NetworkMessage {
+        reliability: Reliability::Reliable,
+        qos: QoS::default(),
+        body: NetworkBody::Push(Push {
+            wire_expr: WireExpr::from(keyexpr::from_str_unchecked("abc/def")),
+            payload: PushBody::Put(Put {
+                payload: &payload,
+                ..Default::default()
+            }),
+            ..Default::default()
+        }),
+    };
+
+    let (tx, rx) = transport.split();
+    let mut batches = 0;
+    let mut received = 0;
+
+    tx.encode_with(core::iter::once(msg()), true, |bytes| {
+        assert!(bytes.len() <= 64);
+        batches += 1;
+
+        rx.decode_prefixed(bytes)?;
+        for (m, _) in rx.flush() {
+            assert_eq!(m, msg());
+            received += 1;
+        }
+
+        Ok::<_, TransportError>(())
+    })
+    .unwrap();
+
+    assert!(batches > 1);
+    assert_eq!(received, 1);
+}
+
+#[test]
+fn transport_fragmented_codec_default_batch_size() {
+    let mut transport = Transport::builder([0u8; 512])
+        .with_defrag([0u8; 512])
+        .codec();
+
+    // Just too large for a single batch, yet small enough for the reassembly buffer.
+    let payload = [0xCD; 498];
+    let msg = || NetworkMessage {
+        reliability: Reliability::Reliable,
+        qos: QoS::default(),
+        body: NetworkBody::Push(Push {
+            wire_expr: WireExpr::from(keyexpr::from_str_unchecked("abc/def")),
+            payload: PushBody::Put(Put {
+                payload: &payload,
+                ..Default::default()
+            }),
+            ..Default::default()
+        }),
+    };
+
+    let (tx, rx) = transport.split();
+    let mut batches = 0;
+    let mut received = 0;
+
+    tx.encode_with(core::iter::once(msg()), true, |bytes| {
+        batches += 1;
+
+        rx.decode_prefixed(bytes)?;
+        for (m, data) in rx.flush() {
+            assert_eq!(m, msg());
+            assert!(data.len() <= 512);
+            received += 1;
+        }
+
+        Ok::<_, TransportError>(())
+    })
+    .unwrap();
+
+    assert_eq!(batches, 2);
+    assert_eq!(received, 1);
+}
+
+#[test]
+fn transport_fragmented_codec_with_buff() {
+    let mut transport = Transport::builder([0u8; 256])
+        .with_defrag([0u8; 256])
+        .with_buff([0u8; 512])
+        .codec();
+
+    // Only fits the reassembly buffer cloned from the new batch buffer.
+    let payload = [0xCD; 400];
+    let msg = || NetworkMessage {
+        reliability: Reliability::Reliable,
+        qos: QoS::default(),
+        body: NetworkBody::Push(Push {
+            wire_expr: WireExpr::from(keyexpr::from_str_unchecked("abc/def")),
+            payload: PushBody::Put(Put {
+                payload: &payload,
+                ..Default::default()
+            }),
+            ..Default::default()
+        }),
+    };
+
+    let (tx, rx) = transport.split();
+    let mut batches = 0;
+    let mut received = 0;
+
+    tx.encode_with(core::iter::once(msg()), true, |bytes| {
+        batches += 1;
+
+        rx.decode_prefixed(bytes)?;
+        for (m, _) in rx.flush() {
+            assert_eq!(m, msg());
+            received += 1;
+        }
+
+        Ok::<_, TransportError>(())
+    })
+    .unwrap();
+
+    assert!(batches > 1);
+    assert_eq!(received, 1);
+}
+
+#[test]
+fn transport_fragmented_codec_overflow() {
+    let mut transport = Transport::builder([0u8; 256])
+        .with_batch_size(64)
+        .with_defrag([0u8; 256])
+        .codec();
+
+    let payload = [0xAB; 300];
+    let msg = |payload| NetworkMessage {
+        reliability: Reliability::Reliable,
+        qos: QoS::default(),
+        body: NetworkBody::Push(Push {
+            wire_expr: WireExpr::from(keyexpr::from_str_unchecked("abc/def")),
+            payload: PushBody::Put(Put {
+                payload,
+                ..Default::default()
+            }),
+            ..Default::default()
+        }),
+    };
+
+    let (tx, rx) = transport.split();
+    let mut received = 0;
+
+    tx.encode_with(
+        [msg(&payload[..]), msg(&payload[..16])].into_iter(),
+        true,
+        |bytes| {
+            rx.decode_prefixed(bytes)?;
+            for (m, _) in rx.flush() {
+                assert_eq!(m, msg(&payload[..16]));
+                received += 1;
+            }
+
+            Ok::<_, TransportError>(())
+        },
+    )
+    .unwrap();
+
+    assert_eq!(received, 1);
+}
+
+#[test]
+fn transport_priority_codec() {
+    let mut transport = Transport::builder([0u8; 512]).with_qos(true).codec();
+
+    let msg = |priority| NetworkMessage {
+        reliability: Reliability::Reliable,
+        qos: QoS::new(priority, CongestionControl::Drop, false),
+        body: NetworkBody::Push(Push {
+            wire_expr: WireExpr::from(keyexpr::from_str_unchecked("abc/def")),
+            payload: PushBody::Put(Put {
+                payload: &[1, 2, 3, 4],
+                ..Default::default()
+            }),
+            ..Default::default()
+        }),
+    };
+
+    let mut sns = [0u32; Priority::NUM];
+    for priority in [
+        Priority::Data,
+        Priority::Control,
+        Priority::Data,
+        Priority::Background,
+        Priority::Control,
+    ] {
+        transport
+            .tx
+            .encode_ref(core::iter::once(msg(priority).as_ref()));
+        let bytes = transport.tx.flush_prefixed().unwrap();
+
+        let header = <FrameHeader as zenoh_proto::ZDecode>::z_decode(&mut &bytes[2..]).unwrap();
+        assert_eq!(header.sn, sns[priority as usize]);
+        sns[priority as usize] += 1;
+
+        transport.rx.decode_prefixed(bytes).unwrap();
+
+        let mut flush = transport.rx.flush();
+        let m = flush.next().unwrap().0;
+
+        assert_eq!(flush.count(), 0);
+        assert_eq!(m, msg(priority));
+        assert_eq!(m.qos.priority(), priority);
+    }
+
+    // Without QoS, the frames carry the default one and share the same sequence numbers
+    let mut transport = Transport::builder([0u8; 512]).codec();
+    for (sn, priority) in [Priority::Control, Priority::Background]
+        .into_iter()
+        .enumerate()
+    {
+        transport
+            .tx
+            .encode_ref(core::iter::once(msg(priority).as_ref()));
+        let bytes = transport.tx.flush_prefixed().unwrap();
+
+        let header = <FrameHeader as zenoh_proto::ZDecode>::z_decode(&mut &bytes[2..]).unwrap();
+        assert_eq!(header.sn, sn as u32);
+        assert_eq!(header.qos, QoS::default());
+
+        transport.rx.decode_prefixed(bytes).unwrap();
+        assert_eq!(transport.rx.flush().next().unwrap().0.qos, QoS::default());
+    }
+}
+
+#[test]
+fn transport_express_codec() {
+    let mut transport = Transport::builder([0u8; 512]).with_qos(true).codec();
+
+    let msg = |express| NetworkMessage {
+        reliability: Reliability::BestEffort,
+        qos: QoS::new(Priority::Data, CongestionControl::Drop, express),
+        body: NetworkBody::Push(Push {
+            wire_expr: WireExpr::from(keyexpr::from_str_unchecked("abc/def")),
+            payload: PushBody::Put(Put {
+                payload: &[1, 2, 3],
+                ..Default::default()
+            }),
+            ..Default::default()
+        }),
+    };
+
+    let (tx, rx) = transport.split();
+
+    let mut batches = 0;
+    tx.encode_with([msg(false), msg(false)].into_iter(), true, |_| {
+        batches += 1;
+        Ok::<_, TransportError>(())
+    })
+    .unwrap();
+    assert_eq!(batches, 1);
+
+    let mut batches = 0;
+    let mut received = 0;
+    tx.encode_with([msg(true), msg(true)].into_iter(), true, |bytes| {
+        batches += 1;
+
+        rx.decode_prefixed(bytes)?;
+        for (m, _) in rx.flush() {
+            assert_eq!(m, msg(true));
+            assert!(m.qos.is_express());
+            received += 1;
+        }
+
+        Ok::<_, TransportError>(())
+    })
+    .unwrap();
+    assert_eq!(batches, 2);
+    assert_eq!(received, 2);
+}
+
+#[test]
+fn transport_batched_codec() {
+    let mut transport = Transport::builder([0u8; 64]).codec();
+
+    let msg = |express| NetworkMessage {
+        reliability: Reliability::Reliable,
+        qos: QoS::new(Priority::Data, CongestionControl::Drop, express),
+        body: NetworkBody::Push(Push {
+            wire_expr: WireExpr::from(keyexpr::from_str_unchecked("abc/def")),
+            payload: PushBody::Put(Put {
+                payload: &[1, 2, 3],
+                ..Default::default()
+            }),
+            ..Default::default()
+        }),
+    };
+
+    let (tx, rx) = transport.split();
+
+    let mut batches = 0;
+    tx.batch_with([msg(false), msg(false)].into_iter(), true, |_| {
+        batches += 1;
+        Ok::<_, TransportError>(())
+    })
+    .unwrap();
+    assert_eq!(batches, 0);
+    assert!(!tx.is_empty());
+
+    // An express message is sent right away, with the pending ones
+    tx.batch_with(core::iter::once(msg(true)), true, |bytes| {
+        batches += 1;
+
+        rx.decode_prefixed(bytes)?;
+        assert_eq!(rx.flush().count(), 3);
+
+        Ok::<_, TransportError>(())
+    })
+    .unwrap();
+    assert_eq!(batches, 1);
+    assert!(tx.is_empty());
+
+    let mut pending = 0;
+    while tx.try_batch(msg(false).as_ref()) {
+        pending += 1;
+    }
+    assert!(pending > 1);
+    assert_eq!(batches, 1);
+
+    // A message that does not fit leaves the batch untouched
+    rx.decode_prefixed(tx.flush_prefixed().unwrap()).unwrap();
+    assert_eq!(rx.flush().count(), pending);
+    assert_eq!(rx.take_missed(), 0);
+}
+
+#[test]
+fn transport_sn_wrapping() {
+    let mut resolution = Resolution::default();
+    resolution.set(Field::FrameSN, Bits::U8);
+
+    let qos = QoS::default();
+    let mut sns = SeqNums::new(250, false, resolution);
+
+    assert_eq!(sns.receive(Reliability::Reliable, &qos, 250), Some(0));
+    assert_eq!(sns.receive(Reliability::Reliable, &qos, 255), Some(4));
+    assert_eq!(sns.receive(Reliability::Reliable, &qos, 0), Some(0));
+    assert_eq!(sns.receive(Reliability::Reliable, &qos, 3), Some(2));
+    assert_eq!(sns.take_missed(), 6);
+    assert_eq!(sns.take_missed(), 0);
+
+    // Duplicates and late frames are rejected without touching the expected SN
+    assert_eq!(sns.receive(Reliability::Reliable, &qos, 3), None);
+    assert_eq!(sns.receive(Reliability::Reliable, &qos, 200), None);
+    assert_eq!(sns.get(Reliability::Reliable, &qos), 4);
+
+    let mut sns = SeqNums::new(0, false, resolution);
+    for _ in 0..300 {
+        sns.advance(Reliability::Reliable, &qos);
+    }
+    assert_eq!(sns.get(Reliability::Reliable, &qos), 300 % 256);
+}
+
+#[test]
+fn transport_reliability_codec() {
+    let mut transport = Transport::builder([0u8; 512]).codec();
+
+    let msg = |reliability| NetworkMessage {
+        reliability,
+        qos: QoS::default(),
+        body: NetworkBody::Push(Push {
+            wire_expr: WireExpr::from(keyexpr::from_str_unchecked("abc/def")),
+            payload: PushBody::Put(Put {
+                payload: &[1, 2, 3, 4],
+                ..Default::default()
+            }),
+            ..Default::default()
+        }),
+    };
+
+    let mut sns = [0u32; 2];
+    for reliability in [
+        Reliability::Reliable,
+        Reliability::BestEffort,
+        Reliability::BestEffort,
+        Reliability::Reliable,
+        Reliability::BestEffort,
+        Reliability::Reliable,
+    ] {
+        transport
+            .tx
+            .encode_ref(core::iter::once(msg(reliability).as_ref()));
+        let bytes = transport.tx.flush_prefixed().unwrap();
+
+        let header = <FrameHeader as zenoh_proto::ZDecode>::z_decode(&mut &bytes[2..]).unwrap();
+        assert_eq!(header.sn, sns[reliability as usize]);
+        sns[reliability as usize] += 1;
+
+        transport.rx.decode_prefixed(bytes).unwrap();
+
+        let mut flush = transport.rx.flush();
+        assert_eq!(flush.next().unwrap().0, msg(reliability));
+        assert_eq!(flush.count(), 0);
+    }
+
+    assert_eq!(transport.rx.take_missed(), 0);
+}
+
+#[test]
+fn transport_u8_resolution_codec() {
+    let mut resolution = Resolution::default();
+    resolution.set(Field::FrameSN, Bits::U8);
+
+    let mut transport = Transport::builder([0u8; 512])
+        .with_resolution(resolution)
+        .codec();
+
+    let msg = || NetworkMessage {
+        reliability: Reliability::Reliable,
+        qos: QoS::default(),
+        body: NetworkBody::Push(Push {
+            wire_expr: WireExpr::from(keyexpr::from_str_unchecked("abc/def")),
+            payload: PushBody::Put(Put {
+                payload: &[1, 2, 3],
+                ..Default::default()
+            }),
+            ..Default::default()
+        }),
+    };
+
+    let (tx, rx) = transport.split();
+    let mut received = 0;
+
+    for _ in 0..600 {
+        tx.encode_with(core::iter::once(msg()), true, |bytes| {
+            rx.decode_prefixed(bytes)?;
+            received += rx.flush().count();
+
+            Ok::<_, TransportError>(())
+        })
+        .unwrap();
+    }
+
+    assert_eq!(received, 600);
+}
@@ -2,27 +2,43 @@ use core::fmt::Display;
 use core::time::Duration;
 
 use establishment::Description;
-use zenoh_proto::{fields::*, msgs::*};
+use zenoh_proto::{exts::*, fields::*, msgs::*};
 
+mod compression;
+mod cookie;
 pub(crate) mod establishment;
 
 mod handshake;
 mod rx;
+pub(crate) mod sn;
 mod traits;
 mod tx;
+mod usrpwd;
 
 pub use handshake::*;
 pub use rx::*;
 pub use traits::*;
 pub use tx::*;
+pub use usrpwd::UsrPwd;
 
-use crate::transport::establishment::State;
+use crate::transport::{
+    cookie::{CookieKey, random},
+    establishment::State,
+};
 
 pub struct TransportBuilder<Buff> {
     zid: ZenohIdProto,
     batch_size: u16,
     lease: Duration,
     resolution: Resolution,
+    qos: bool,
+    cookie_key: Option<CookieKey>,
+    rng: fn(&mut [u8]),
+    clock: fn() -> Duration,
+    usrpwd: Option<UsrPwd>,
+    usrpwd_dictionary: &'static [UsrPwd],
+    compression: Option<Buff>,
+    defrag: Option<Buff>,
 
     buff: Buff,
 }
@@ -37,6 +53,14 @@ impl<Buff> TransportBuilder<Buff> {
             batch_size: buff.as_ref().len() as u16,
             lease: Duration::from_secs(10),
             resolution: Resolution::default(),
+            qos: false,
+            cookie_key: None,
+            rng: random,
+            clock: || Duration::ZERO,
+            usrpwd: None,
+            usrpwd_dictionary: &[],
+            compression: None,
+            defrag: None,
             buff,
         }
     }
@@ -60,16 +84,90 @@ impl<Buff> TransportBuilder<Buff> {
         self
     }
 
-    pub fn with_buff<NewBuff>(self, buff: NewBuff) -> TransportBuilder<NewBuff> {
+    pub fn with_qos(mut self, qos: bool) -> Self {
+        self.qos = qos;
+        self
+    }
+
+    /// Key used by a listener to seal its handshake cookies. Defaults to a key drawn from the
+    /// random generator for each handshake.
+    pub fn with_cookie_key(mut self, cookie_key: [u8; 32]) -> Self {
+        self.cookie_key = Some(cookie_key);
+        self
+    }
+
+    /// Random generator used for the cookie key and nonces, and the `usrpwd` challenges.
+    /// Defaults to the one of the platform, behind the default zid.
+    pub fn with_rng(mut self, rng: fn(&mut [u8])) -> Self {
+        self.rng = rng;
+        self
+    }
+
+    /// Monotonic clock used by a listener to refuse the cookies sealed more than a lease ago.
+    /// Without it, cookies sealed with a key given to [`Self::with_cookie_key`] never expire.
+    pub fn with_clock(mut self, clock: fn() -> Duration) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Credentials used to authenticate to a listener that requires it.
+    pub fn with_usrpwd(mut self, usrpwd: UsrPwd) -> Self {
+        self.usrpwd = Some(usrpwd);
+        self
+    }
+
+    /// Credentials accepted from connecting peers. When empty, peers are not authenticated.
+    pub fn with_usrpwd_dictionary(mut self, dictionary: &'static [UsrPwd]) -> Self {
+        self.usrpwd_dictionary = dictionary;
+        self
+    }
+
+    /// Negotiates the LZ4 compression of the batches. `scratch` is used to compress and
+    /// decompress them, so it should be as large as the batch size.
+    #[cfg(feature = "compression")]
+    pub fn with_compression(mut self, scratch: Buff) -> Self {
+        self.compression = Some(scratch);
+        self
+    }
+
+    /// Reassembles the fragmented messages received in `buff`, whose length bounds their size.
+    /// Without it, fragmented messages are dropped.
+    pub fn with_defrag(mut self, buff: Buff) -> Self {
+        self.defrag = Some(buff);
+        self
+    }
+
+    /// Replaces the batch buffer. Compression and reassembly stay enabled, with clones of `buff`
+    /// unless their own buffers are set again afterwards.
+    pub fn with_buff<NewBuff>(self, buff: NewBuff) -> TransportBuilder<NewBuff>
+    where
+        NewBuff: Clone,
+    {
         TransportBuilder {
             zid: self.zid,
             batch_size: self.batch_size,
             lease: self.lease,
             resolution: self.resolution,
+            qos: self.qos,
+            cookie_key: self.cookie_key,
+            rng: self.rng,
+            clock: self.clock,
+            usrpwd: self.usrpwd,
+            usrpwd_dictionary: self.usrpwd_dictionary,
+            compression: self.compression.map(|_| buff.clone()),
+            defrag: self.defrag.map(|_| buff.clone()),
             buff,
         }
     }
 
+    fn cookie_key(&self) -> CookieKey {
+        self.cookie_key.unwrap_or_else(|| {
+            let mut key = [0u8; 32];
+            (self.rng)(&mut key);
+            key
+        })
+    }
+
     pub fn codec(self) -> Transport<Buff>
     where
         Buff: Clone,
@@ -81,16 +179,22 @@ impl<Buff> TransportBuilder<Buff> {
                 0,
                 self.resolution,
                 self.lease,
-            ),
+                self.qos,
+            )
+            .with_compression(self.compression.clone()),
             rx: TransportRx::new(
                 self.buff,
                 self.batch_size as usize,
                 0,
                 self.resolution,
                 self.lease,
-            ),
+                self.qos,
+            )
+            .with_compression(self.compression)
+            .with_defrag(self.defrag),
             mine_zid: self.zid,
             other_zid: self.zid,
+            resolution: self.resolution,
         }
     }
 
@@ -111,6 +215,12 @@ impl<Buff> TransportBuilder<Buff> {
             mine_batch_size: self.batch_size,
             mine_resolution: self.resolution,
             mine_lease: self.lease,
+            mine_qos: self.qos,
+            mine_compression: self.compression.is_some(),
+            cookie_key: self.cookie_key(),
+            rng: self.rng,
+            clock: self.clock,
+            usrpwd: self.usrpwd_dictionary,
         };
 
         let tx = TransportTx::new(
@@ -119,6 +229,7 @@ impl<Buff> TransportBuilder<Buff> {
             0,
             self.resolution,
             self.lease,
+            false,
         );
 
         let rx = TransportRx::new(
@@ -127,6 +238,7 @@ impl<Buff> TransportBuilder<Buff> {
             0,
             self.resolution,
             self.lease,
+            false,
         );
 
         Handshake::PendingRecv {
@@ -134,6 +246,8 @@ impl<Buff> TransportBuilder<Buff> {
             prefixed: false,
             tx,
             rx,
+            scratch: self.compression,
+            defrag: self.defrag,
             handle,
             read,
             write,
@@ -157,6 +271,12 @@ impl<Buff> TransportBuilder<Buff> {
             mine_batch_size: self.batch_size,
             mine_resolution: self.resolution,
             mine_lease: self.lease,
+            mine_qos: self.qos,
+            mine_compression: self.compression.is_some(),
+            cookie_key: self.cookie_key(),
+            rng: self.rng,
+            clock: self.clock,
+            usrpwd: self.usrpwd_dictionary,
         };
 
         let tx = TransportTx::new(
@@ -165,6 +285,7 @@ impl<Buff> TransportBuilder<Buff> {
             0,
             self.resolution,
             self.lease,
+            false,
         );
 
         let rx = TransportRx::new(
@@ -173,6 +294,7 @@ impl<Buff> TransportBuilder<Buff> {
             0,
             self.resolution,
             self.lease,
+            false,
         );
 
         Handshake::PendingRecv {
@@ -180,6 +302,8 @@ impl<Buff> TransportBuilder<Buff> {
             prefixed: false,
             tx,
             rx,
+            scratch: self.compression,
+            defrag: self.defrag,
             handle,
             read,
             write,
@@ -203,6 +327,9 @@ impl<Buff> TransportBuilder<Buff> {
             mine_batch_size: self.batch_size,
             mine_resolution: self.resolution,
             mine_lease: self.lease,
+            mine_qos: self.qos,
+            mine_compression: self.compression.is_some(),
+            usrpwd: self.usrpwd,
         };
 
         let tx = TransportTx::new(
@@ -211,6 +338,7 @@ impl<Buff> TransportBuilder<Buff> {
             0,
             self.resolution,
             self.lease,
+            false,
         );
 
         let rx = TransportRx::new(
@@ -219,6 +347,7 @@ impl<Buff> TransportBuilder<Buff> {
             0,
             self.resolution,
             self.lease,
+            false,
         );
 
         Handshake::PendingInit {
@@ -232,11 +361,18 @@ impl<Buff> TransportBuilder<Buff> {
                     resolution: self.resolution,
                     batch_size: BatchSize(self.batch_size),
                 },
+                qos: self.qos.then_some(HasQoS {}),
+                compression: self.compression.is_some().then_some(HasCompression {}),
+                auth: self.usrpwd.map(|_| Auth {
+                    payload: &usrpwd::HAS_USRPWD,
+                }),
                 ..Default::default()
             },
             prefixed: false,
             tx,
             rx,
+            scratch: self.compression,
+            defrag: self.defrag,
             handle,
             read,
             write,
@@ -260,6 +396,9 @@ impl<Buff> TransportBuilder<Buff> {
             mine_batch_size: self.batch_size,
             mine_resolution: self.resolution,
             mine_lease: self.lease,
+            mine_qos: self.qos,
+            mine_compression: self.compression.is_some(),
+            usrpwd: self.usrpwd,
         };
 
         let tx = TransportTx::new(
@@ -268,6 +407,7 @@ impl<Buff> TransportBuilder<Buff> {
             0,
             self.resolution,
             self.lease,
+            false,
         );
 
         let rx = TransportRx::new(
@@ -276,6 +416,7 @@ impl<Buff> TransportBuilder<Buff> {
             0,
             self.resolution,
             self.lease,
+            false,
         );
 
         Handshake::PendingInit {
@@ -289,11 +430,18 @@ impl<Buff> TransportBuilder<Buff> {
                     resolution: self.resolution,
                     batch_size: BatchSize(self.batch_size),
                 },
+                qos: self.qos.then_some(HasQoS {}),
+                compression: self.compression.is_some().then_some(HasCompression {}),
+                auth: self.usrpwd.map(|_| Auth {
+                    payload: &usrpwd::HAS_USRPWD,
+                }),
                 ..Default::default()
             },
             prefixed: false,
             tx,
             rx,
+            scratch: self.compression,
+            defrag: self.defrag,
             handle,
             read,
             write,
@@ -307,6 +455,7 @@ pub struct Transport<Buff> {
 
     pub mine_zid: ZenohIdProto,
     pub other_zid: ZenohIdProto,
+    pub resolution: Resolution,
 }
 
 impl<Buff> Transport<Buff> {
@@ -317,7 +466,18 @@ impl<Buff> Transport<Buff> {
         TransportBuilder::new(buff)
     }
 
-    pub(crate) fn new(description: Description, tx: Buff, rx: Buff) -> Self {
+    pub(crate) fn new(
+        description: Description,
+        tx: Buff,
+        rx: Buff,
+        scratch: Option<Buff>,
+        defrag: Option<Buff>,
+    ) -> Self
+    where
+        Buff: Clone,
+    {
+        let scratch = scratch.filter(|_| description.compression);
+
         Self {
             tx: TransportTx::new(
                 tx,
@@ -325,16 +485,22 @@ impl<Buff> Transport<Buff> {
                 description.mine_sn,
                 description.resolution,
                 description.mine_lease,
-            ),
+                description.qos,
+            )
+            .with_compression(scratch.clone()),
             rx: TransportRx::new(
                 rx,
                 description.batch_size as usize,
                 description.other_sn,
                 description.resolution,
                 description.other_lease,
-            ),
+                description.qos,
+            )
+            .with_compression(scratch)
+            .with_defrag(defrag),
             mine_zid: description.mine_zid,
             other_zid: description.other_zid,
+            resolution: description.resolution,
         }
     }
 
@@ -0,0 +1,23 @@
+/// Flag of the batch header telling that the rest of the batch is compressed.
+pub(crate) const COMPRESSED: u8 = 0b0000_0001;
+
+/// Compresses `src` in `dst`. Returns `None` if the result does not fit in `dst`.
+#[cfg(feature = "compression")]
+pub(crate) fn compress(src: &[u8], dst: &mut [u8]) -> Option<usize> {
+    lz4_flex::block::compress_into(src, dst).ok()
+}
+
+#[cfg(not(feature = "compression"))]
+pub(crate) fn compress(_: &[u8], _: &mut [u8]) -> Option<usize> {
+    None
+}
+
+#[cfg(feature = "compression")]
+pub(crate) fn decompress(src: &[u8], dst: &mut [u8]) -> Option<usize> {
+    lz4_flex::block::decompress_into(src, dst).ok()
+}
+
+#[cfg(not(feature = "compression"))]
+pub(crate) fn decompress(_: &[u8], _: &mut [u8]) -> Option<usize> {
+    None
+}
@@ -0,0 +1,124 @@
+use core::time::Duration;
+
+use chacha20::{
+    ChaCha20,
+    cipher::{KeyIvInit, StreamCipher},
+};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+use zenoh_proto::fields::*;
+
+const NONCE_LEN: usize = 12;
+const ZID_LEN: usize = 16;
+const DATA_LEN: usize = 1 + ZID_LEN + 2 + 1 + 1 + 8 + 1 + 8;
+const TAG_LEN: usize = 32;
+
+pub(crate) const COOKIE_LEN: usize = NONCE_LEN + DATA_LEN + TAG_LEN;
+
+pub(crate) type CookieKey = [u8; 32];
+
+/// Fills `bytes` with the random generator of the platform, the one behind the default zid.
+pub(crate) fn random(bytes: &mut [u8]) {
+    for chunk in bytes.chunks_mut(16) {
+        chunk.copy_from_slice(&ZenohIdProto::default().as_le_bytes()[..chunk.len()]);
+    }
+}
+
+/// What the listener negotiated on `InitSyn`, handed back by the peer in its `OpenSyn`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(crate) struct Cookie {
+    pub other_zid: ZenohIdProto,
+    pub batch_size: u16,
+    pub resolution: Resolution,
+    pub qos: bool,
+    /// The `usrpwd` challenge sent in the InitAck
+    pub nonce: u64,
+    pub compression: bool,
+    /// The time after which the cookie is refused
+    pub expires: Duration,
+}
+
+impl Cookie {
+    /// Encrypts the cookie with ChaCha20 under a nonce drawn from `rng`, and appends an
+    /// HMAC-SHA256 of the nonce and the ciphertext. Both keys are derived from `key`.
+    pub(crate) fn seal(&self, key: &CookieKey, rng: fn(&mut [u8])) -> [u8; COOKIE_LEN] {
+        let mut cookie = [0u8; COOKIE_LEN];
+        let (nonce, rest) = cookie.split_at_mut(NONCE_LEN);
+        let (data, tag) = rest.split_at_mut(DATA_LEN);
+
+        rng(nonce);
+
+        data[0] = self.other_zid.size() as u8;
+        data[1..17].copy_from_slice(&self.other_zid.as_le_bytes());
+        data[17..19].copy_from_slice(&self.batch_size.to_le_bytes());
+        data[19] = self.resolution.get(Field::FrameSN) as u8
+            | (self.resolution.get(Field::RequestID) as u8) << (Field::RequestID as u8);
+        data[20] = self.qos as u8;
+        data[21..29].copy_from_slice(&self.nonce.to_le_bytes());
+        data[29] = self.compression as u8;
+        data[30..38].copy_from_slice(&(self.expires.as_millis() as u64).to_le_bytes());
+
+        ChaCha20::new((&derive(key, b"zenoh-cookie-enc")).into(), (&*nonce).into())
+            .apply_keystream(data);
+        tag.copy_from_slice(&Self::tag(key, nonce, data));
+
+        cookie
+    }
+
+    /// Authenticates and decrypts a cookie produced by [`Cookie::seal`] with the same key.
+    /// The expiry is left to the caller, which owns the clock.
+    pub(crate) fn open(key: &CookieKey, cookie: &[u8]) -> Option<Self> {
+        if cookie.len() != COOKIE_LEN {
+            return None;
+        }
+
+        let (nonce, rest) = cookie.split_at(NONCE_LEN);
+        let (data, tag) = rest.split_at(DATA_LEN);
+
+        let expected = Self::tag(key, nonce, data);
+        if expected
+            .iter()
+            .zip(tag)
+            .fold(0, |acc, (a, b)| acc | (a ^ b))
+            != 0
+        {
+            return None;
+        }
+
+        let mut data: [u8; DATA_LEN] = data.try_into().ok()?;
+        ChaCha20::new((&derive(key, b"zenoh-cookie-enc")).into(), nonce.into())
+            .apply_keystream(&mut data);
+
+        let size = data[0] as usize;
+        if size == 0 || size > ZID_LEN {
+            return None;
+        }
+
+        Some(Self {
+            other_zid: ZenohIdProto::try_from(&data[1..1 + size]).ok()?,
+            batch_size: u16::from_le_bytes([data[17], data[18]]),
+            resolution: Resolution::from(data[19]),
+            qos: data[20] != 0,
+            nonce: u64::from_le_bytes(data[21..29].try_into().ok()?),
+            compression: data[29] != 0,
+            expires: Duration::from_millis(u64::from_le_bytes(data[30..38].try_into().ok()?)),
+        })
+    }
+
+    fn tag(key: &CookieKey, nonce: &[u8], data: &[u8]) -> [u8; TAG_LEN] {
+        let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(&derive(key, b"zenoh-cookie-mac"))
+            .expect("HMAC accepts keys of any size");
+        mac.update(nonce);
+        mac.update(data);
+        mac.finalize().into_bytes().into()
+    }
+}
+
+/// A key for one use of the cookie key, so that the cipher and the MAC never share one.
+fn derive(key: &CookieKey, label: &[u8]) -> [u8; 32] {
+    let mut mac =
+        <Hmac<Sha256> as Mac>::new_from_slice(key).expect("HMAC accepts keys of any size");
+    mac.update(label);
+    mac.finalize().into_bytes().into()
+}
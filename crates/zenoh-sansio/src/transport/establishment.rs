@@ -5,7 +5,12 @@ use sha3::{
     digest::{ExtendableOutput, Update, XofReader},
 };
 
-use zenoh_proto::{TransportError, ZDecode, fields::*, msgs::*};
+use zenoh_proto::{TransportError, exts::*, fields::*, msgs::*};
+
+use crate::transport::{
+    cookie::{COOKIE_LEN, Cookie, CookieKey},
+    usrpwd::{self, UsrPwd, UsrPwdExt},
+};
 
 /// Everything that describes an Opened Transport between two peers
 #[derive(Copy, Clone, Debug, PartialEq)]
@@ -22,9 +27,12 @@ pub(crate) struct Description {
     pub other_sn: u32,
 
     pub other_zid: ZenohIdProto,
+
+    pub qos: bool,
+    pub compression: bool,
 }
 
-#[derive(Copy, Clone, Debug, PartialEq)]
+#[derive(Copy, Clone, Debug)]
 pub(crate) enum State {
     WaitingInitSyn {
         /// Mine zid
@@ -35,16 +43,34 @@ pub(crate) enum State {
         mine_resolution: Resolution,
         /// Mine lease,
         mine_lease: Duration,
+        /// Mine QoS support
+        mine_qos: bool,
+        /// Mine compression support
+        mine_compression: bool,
+        /// Key used to seal the cookie
+        cookie_key: CookieKey,
+        /// Source of the cookie nonce and of the `usrpwd` challenge
+        rng: fn(&mut [u8]),
+        /// Time used to bound the lifetime of the cookie
+        clock: fn() -> Duration,
+        /// Credentials accepted from the peer, if it has to authenticate
+        usrpwd: &'static [UsrPwd],
     },
     WaitingOpenSyn {
         /// Mine zid
         mine_zid: ZenohIdProto,
-        /// Mine startup batch_size
-        mine_batch_size: u16,
-        /// Mine startup resolution
-        mine_resolution: Resolution,
         /// Mine lease,
         mine_lease: Duration,
+        /// Key used to open the cookie
+        cookie_key: CookieKey,
+        /// Time checked against the expiry of the cookie
+        clock: fn() -> Duration,
+        /// Credentials accepted from the peer, if it has to authenticate
+        usrpwd: &'static [UsrPwd],
+        /// Sealed cookie sent in the InitAck
+        cookie: [u8; COOKIE_LEN],
+        /// Auth extension sent in the InitAck
+        auth: UsrPwdExt,
     },
     WaitingInitAck {
         /// Mine zid
@@ -55,6 +81,12 @@ pub(crate) enum State {
         mine_resolution: Resolution,
         /// Mine lease,
         mine_lease: Duration,
+        /// Mine QoS support
+        mine_qos: bool,
+        /// Mine compression support
+        mine_compression: bool,
+        /// Mine credentials
+        usrpwd: Option<UsrPwd>,
     },
     WaitingOpenAck {
         /// Mine zid
@@ -69,30 +101,41 @@ pub(crate) enum State {
         mine_lease: Duration,
         /// Peer zid
         other_zid: ZenohIdProto,
+        /// Negotiated QoS
+        qos: bool,
+        /// Negotiated compression
+        compression: bool,
+        /// Auth extension sent in the OpenSyn
+        auth: UsrPwdExt,
     },
     Opened(Description),
 }
 
 impl State {
     pub(crate) fn poll<'a>(
-        &mut self,
-        input: (TransportMessage<'a>, &'a [u8]),
-    ) -> (Option<TransportMessage<'a>>, Option<Description>) {
+        &'a mut self,
+        msg: TransportMessage<'a>,
+    ) -> core::result::Result<(Option<TransportMessage<'a>>, Option<Description>), TransportError>
+    {
         if let Self::Opened(description) = &self {
-            return (None, Some(*description));
+            return Ok((None, Some(*description)));
         }
 
-        let (msg, buff) = input;
-
         match msg {
-            // Don't do any computation at this stage. Pass the relevant values as the cookie
-            // for future computation.
+            // Negotiate values and seal them in the cookie, so that no state about the peer
+            // has to be kept until its OpenSyn.
             TransportMessage::InitSyn(syn) => match *self {
                 Self::WaitingInitSyn {
                     mine_zid,
                     mine_batch_size,
                     mine_resolution,
                     mine_lease,
+                    mine_qos,
+                    mine_compression,
+                    cookie_key,
+                    rng,
+                    clock,
+                    usrpwd,
                 } => {
                     zenoh_proto::debug!(
                         "Received InitSyn on transport {:?} -> NEW!({:?})",
@@ -100,30 +143,84 @@ impl State {
                         syn.identifier.zid
                     );
 
+                    let batch_size = mine_batch_size.min(syn.resolution.batch_size.0);
+                    let resolution = {
+                        let mut res = Resolution::default();
+                        let i_fsn_res = syn.resolution.resolution.get(Field::FrameSN);
+                        let m_fsn_res = mine_resolution.get(Field::FrameSN);
+                        if i_fsn_res > m_fsn_res {
+                            zenoh_proto::zbail!(@log TransportError::InvalidAttribute);
+                        }
+                        res.set(Field::FrameSN, i_fsn_res);
+                        let i_rid_res = syn.resolution.resolution.get(Field::RequestID);
+                        let m_rid_res = mine_resolution.get(Field::RequestID);
+                        if i_rid_res > m_rid_res {
+                            zenoh_proto::zbail!(@log TransportError::InvalidAttribute);
+                        }
+                        res.set(Field::RequestID, i_rid_res);
+                        res
+                    };
+                    let qos = mine_qos && syn.qos.is_some();
+                    let compression = mine_compression && syn.compression.is_some();
+
+                    let nonce = usrpwd::nonce(rng);
+                    let auth = if usrpwd.is_empty() {
+                        UsrPwdExt::default()
+                    } else {
+                        if usrpwd::find::<HasUsrPwd>(syn.auth.as_ref()).is_none() {
+                            zenoh_proto::zbail!(@log TransportError::UsrPwdAuthFailed);
+                        }
+
+                        match UsrPwdExt::new(&UsrPwdNonce { nonce }) {
+                            Some(auth) => auth,
+                            None => zenoh_proto::zbail!(@log TransportError::TransportTxFull),
+                        }
+                    };
+
+                    let cookie = Cookie {
+                        other_zid: syn.identifier.zid,
+                        batch_size,
+                        resolution,
+                        qos,
+                        nonce,
+                        compression,
+                        expires: clock() + mine_lease,
+                    };
+
                     *self = Self::WaitingOpenSyn {
                         mine_zid,
-                        mine_batch_size,
-                        mine_resolution,
                         mine_lease,
+                        cookie_key,
+                        clock,
+                        usrpwd,
+                        cookie: cookie.seal(&cookie_key, rng),
+                        auth,
                     };
 
-                    (
+                    let Self::WaitingOpenSyn { cookie, auth, .. } = self else {
+                        unreachable!()
+                    };
+
+                    Ok((
                         Some(TransportMessage::InitAck(InitAck {
                             identifier: InitIdentifier {
                                 zid: mine_zid,
                                 ..Default::default()
                             },
                             resolution: InitResolution {
-                                resolution: mine_resolution,
-                                batch_size: BatchSize(mine_batch_size),
+                                resolution,
+                                batch_size: BatchSize(batch_size),
                             },
-                            cookie: buff, // TODO: cypher ChaCha20
+                            cookie,
+                            qos: qos.then_some(HasQoS {}),
+                            compression: compression.then_some(HasCompression {}),
+                            auth: auth.auth(),
                             ..Default::default()
                         })),
                         None,
-                    )
+                    ))
                 }
-                _ => zenoh_proto::zbail!(@ret (None, None), TransportError::InvalidState),
+                _ => zenoh_proto::zbail!(@log TransportError::InvalidState),
             },
             // Negotiate values, pass the cookie back
             TransportMessage::InitAck(ack) => match *self {
@@ -132,6 +229,9 @@ impl State {
                     mine_batch_size,
                     mine_resolution,
                     mine_lease,
+                    mine_qos,
+                    mine_compression,
+                    usrpwd,
                 } => {
                     zenoh_proto::debug!(
                         "Received InitAck on transport {:?} -> ({:?})",
@@ -145,13 +245,13 @@ impl State {
                         let i_fsn_res = ack.resolution.resolution.get(Field::FrameSN);
                         let m_fsn_res = mine_resolution.get(Field::FrameSN);
                         if i_fsn_res > m_fsn_res {
-                            zenoh_proto::zbail!(@ret (None, None), TransportError::InvalidAttribute);
+                            zenoh_proto::zbail!(@log TransportError::InvalidAttribute);
                         }
                         res.set(Field::FrameSN, i_fsn_res);
                         let i_rid_res = ack.resolution.resolution.get(Field::RequestID);
                         let m_rid_res = mine_resolution.get(Field::RequestID);
                         if i_rid_res > m_rid_res {
-                            zenoh_proto::zbail!(@ret (None, None), TransportError::InvalidAttribute);
+                            zenoh_proto::zbail!(@log TransportError::InvalidAttribute);
                         }
                         res.set(Field::RequestID, i_rid_res);
                         res
@@ -164,7 +264,7 @@ impl State {
                         let mut array = 0_u32.to_le_bytes();
                         hasher.finalize_xof().read(&mut array);
                         u32::from_le_bytes(array)
-                            & match mine_resolution.get(Field::FrameSN) {
+                            & match resolution.get(Field::FrameSN) {
                                 Bits::U8 => u8::MAX as u32 >> 1,
                                 Bits::U16 => u16::MAX as u32 >> 2,
                                 Bits::U32 => u32::MAX >> 4,
@@ -172,6 +272,21 @@ impl State {
                             }
                     };
 
+                    // Answer the challenge of the listener, if it sent one
+                    let nonce = usrpwd::find::<UsrPwdNonce>(ack.auth.as_ref());
+                    let auth = match (usrpwd, nonce) {
+                        (Some(UsrPwd { user, password }), Some(UsrPwdNonce { nonce })) => {
+                            match UsrPwdExt::new(&UsrPwdOpenSyn {
+                                user: user.as_bytes(),
+                                hmac: &usrpwd::hmac(nonce, password),
+                            }) {
+                                Some(auth) => auth,
+                                None => zenoh_proto::zbail!(@log TransportError::InvalidAttribute),
+                            }
+                        }
+                        _ => UsrPwdExt::default(),
+                    };
+
                     *self = Self::WaitingOpenAck {
                         mine_zid,
                         batch_size,
@@ -179,68 +294,77 @@ impl State {
                         sn,
                         mine_lease,
                         other_zid: ack.identifier.zid,
+                        qos: mine_qos && ack.qos.is_some(),
+                        compression: mine_compression && ack.compression.is_some(),
+                        auth,
+                    };
+
+                    let Self::WaitingOpenAck { auth, .. } = self else {
+                        unreachable!()
                     };
 
-                    (
+                    Ok((
                         Some(TransportMessage::OpenSyn(OpenSyn {
                             lease: mine_lease,
                             sn,
                             cookie: ack.cookie,
+                            auth: auth.auth(),
                             ..Default::default()
                         })),
                         None,
-                    )
+                    ))
                 }
-                _ => zenoh_proto::zbail!(@ret (None, None), TransportError::InvalidState),
+                _ => zenoh_proto::zbail!(@log TransportError::InvalidState),
             },
             // Negotiate values, open the transport. Ack the open
             TransportMessage::OpenSyn(open) => match *self {
                 Self::WaitingOpenSyn {
                     mine_zid,
-                    mine_batch_size,
-                    mine_resolution,
                     mine_lease,
+                    cookie_key,
+                    clock,
+                    usrpwd,
+                    ..
                 } => {
-                    // TODO: decypher cookie ChaCha20
-                    let syn = match <InitSyn as ZDecode>::z_decode(&mut &open.cookie[..]) {
-                        Ok(syn) => syn,
-                        Err(e) => {
-                            zenoh_proto::zbail!(@ret (None, None), e)
-                        }
+                    let Some(Cookie {
+                        other_zid,
+                        batch_size,
+                        resolution,
+                        qos,
+                        nonce,
+                        compression,
+                        expires,
+                    }) = Cookie::open(&cookie_key, open.cookie)
+                    else {
+                        zenoh_proto::zbail!(@log TransportError::InvalidCookie)
                     };
 
+                    // Refuse the cookies sealed more than a lease ago, so they can't be replayed later on.
+                    if clock() > expires {
+                        zenoh_proto::zbail!(@log TransportError::InvalidCookie)
+                    }
+
+                    if !usrpwd.is_empty()
+                        && !usrpwd::find::<UsrPwdOpenSyn>(open.auth.as_ref())
+                            .is_some_and(|open| usrpwd::check(usrpwd, nonce, &open))
+                    {
+                        zenoh_proto::zbail!(@log TransportError::UsrPwdAuthFailed);
+                    }
+
                     zenoh_proto::debug!(
                         "Received OpenSyn on transport {:?} -> ({:?})",
                         mine_zid,
-                        syn.identifier.zid
+                        other_zid
                     );
 
-                    let batch_size = mine_batch_size.min(syn.resolution.batch_size.0);
-                    let resolution = {
-                        let mut res = Resolution::default();
-                        let i_fsn_res = syn.resolution.resolution.get(Field::FrameSN);
-                        let m_fsn_res = mine_resolution.get(Field::FrameSN);
-                        if i_fsn_res > m_fsn_res {
-                            zenoh_proto::zbail!(@ret (None, None), TransportError::InvalidAttribute);
-                        }
-                        res.set(Field::FrameSN, i_fsn_res);
-                        let i_rid_res = syn.resolution.resolution.get(Field::RequestID);
-                        let m_rid_res = mine_resolution.get(Field::RequestID);
-                        if i_rid_res > m_rid_res {
-                            zenoh_proto::zbail!(@ret (None, None), TransportError::InvalidAttribute);
-                        }
-                        res.set(Field::RequestID, i_rid_res);
-                        res
-                    };
                     let sn = {
                         let mut hasher = Shake128::default();
                         hasher.update(&mine_zid.as_le_bytes()[..mine_zid.size()]);
-                        hasher
-                            .update(&syn.identifier.zid.as_le_bytes()[..syn.identifier.zid.size()]);
+                        hasher.update(&other_zid.as_le_bytes()[..other_zid.size()]);
                         let mut array = 0_u32.to_le_bytes();
                         hasher.finalize_xof().read(&mut array);
                         u32::from_le_bytes(array)
-                            & match mine_resolution.get(Field::FrameSN) {
+                            & match resolution.get(Field::FrameSN) {
                                 Bits::U8 => u8::MAX as u32 >> 1,
                                 Bits::U16 => u16::MAX as u32 >> 2,
                                 Bits::U32 => u32::MAX >> 4,
@@ -256,21 +380,26 @@ impl State {
                         other_lease: open.lease,
                         mine_sn: sn,
                         other_sn: open.sn,
-                        other_zid: syn.identifier.zid,
+                        other_zid,
+                        qos,
+                        compression,
                     };
 
                     *self = Self::Opened(description);
 
-                    (
+                    Ok((
                         Some(TransportMessage::OpenAck(OpenAck {
                             lease: mine_lease,
                             sn,
+                            auth: (!usrpwd.is_empty()).then_some(Auth {
+                                payload: &usrpwd::HAS_USRPWD,
+                            }),
                             ..Default::default()
                         })),
                         Some(description),
-                    )
+                    ))
                 }
-                _ => zenoh_proto::zbail!(@ret (None, None), TransportError::InvalidState),
+                _ => zenoh_proto::zbail!(@log TransportError::InvalidState),
             },
             // Open the transport
             TransportMessage::OpenAck(ack) => match *self {
@@ -281,6 +410,9 @@ impl State {
                     sn,
                     mine_lease,
                     other_zid,
+                    qos,
+                    compression,
+                    ..
                 } => {
                     zenoh_proto::debug!(
                         "Received OpenAck on transport {:?} -> ({:?})",
@@ -297,15 +429,17 @@ impl State {
                         mine_sn: sn,
                         other_sn: ack.sn,
                         other_zid,
+                        qos,
+                        compression,
                     };
 
                     *self = Self::Opened(description);
 
-                    (None, Some(description))
+                    Ok((None, Some(description)))
                 }
-                _ => zenoh_proto::zbail!(@ret (None, None), TransportError::InvalidState),
+                _ => zenoh_proto::zbail!(@log TransportError::InvalidState),
             },
-            _ => zenoh_proto::zbail!(@ret (None, None), TransportError::InvalidState),
+            _ => zenoh_proto::zbail!(@log TransportError::InvalidState),
         }
     }
 
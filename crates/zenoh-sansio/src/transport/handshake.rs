@@ -17,6 +17,8 @@ pub enum Handshake<Buff, T, Read, Write> {
 
         tx: TransportTx<Buff>,
         rx: TransportRx<Buff>,
+        scratch: Option<Buff>,
+        defrag: Option<Buff>,
 
         handle: T,
 
@@ -32,6 +34,8 @@ pub enum Handshake<Buff, T, Read, Write> {
 
         tx: TransportTx<Buff>,
         rx: TransportRx<Buff>,
+        scratch: Option<Buff>,
+        defrag: Option<Buff>,
 
         handle: T,
 
@@ -44,6 +48,8 @@ pub enum Handshake<Buff, T, Read, Write> {
 
         tx: TransportTx<Buff>,
         rx: TransportRx<Buff>,
+        scratch: Option<Buff>,
+        defrag: Option<Buff>,
     },
     Opened,
 }
@@ -53,14 +59,25 @@ pub struct HandshakeReady<'a, Buff, T, Read, Write> {
 }
 
 impl<'a, Buff, T, Read, Write> HandshakeReady<'a, Buff, T, Read, Write> {
-    pub fn open(self) -> Transport<Buff> {
+    pub fn open(self) -> Transport<Buff>
+    where
+        Buff: Clone,
+    {
         if let Handshake::Ready {
             description,
             tx,
             rx,
+            scratch,
+            defrag,
         } = core::mem::replace(self.handshake, Handshake::Opened)
         {
-            Transport::new(description, tx.into_inner(), rx.into_inner())
+            Transport::new(
+                description,
+                tx.into_inner(),
+                rx.into_inner(),
+                scratch,
+                defrag,
+            )
         } else {
             unreachable!()
         }
@@ -119,6 +136,8 @@ impl<Buff, T, Read, Write> Handshake<Buff, T, Read, Write> {
                     prefixed,
                     tx,
                     rx,
+                    scratch,
+                    defrag,
                     handle,
                     read,
                     write,
@@ -130,6 +149,8 @@ impl<Buff, T, Read, Write> Handshake<Buff, T, Read, Write> {
                         prefixed,
                         tx,
                         rx,
+                        scratch,
+                        defrag,
                         handle,
                         read,
                         write,
@@ -148,14 +169,23 @@ impl<Buff, T, Read, Write> Handshake<Buff, T, Read, Write> {
                 handle,
                 read,
                 write,
+                ..
             } => {
                 if let Some(description) = state.description() {
-                    if let Self::PendingRecv { tx, rx, .. } = core::mem::replace(self, Self::Opened)
+                    if let Self::PendingRecv {
+                        tx,
+                        rx,
+                        scratch,
+                        defrag,
+                        ..
+                    } = core::mem::replace(self, Self::Opened)
                     {
                         *self = Self::Ready {
                             description,
                             tx,
                             rx,
+                            scratch,
+                            defrag,
                         };
 
                         return Ok(Some(HandshakeReady { handshake: self }));
@@ -165,13 +195,10 @@ impl<Buff, T, Read, Write> Handshake<Buff, T, Read, Write> {
                 }
 
                 rx.decode_with(|bytes| read(handle, bytes), *prefixed)?;
-                let resp = rx
-                    .flush_transport()
-                    .map(|msg| state.poll(msg))
-                    .filter_map(|response| response.0);
-
-                for resp in resp {
-                    tx.transport(resp)
+                for msg in rx.flush_transport() {
+                    if let (Some(resp), _) = state.poll(msg).map_err(EitherError::A)? {
+                        tx.transport(resp);
+                    }
                 }
 
                 if let Some(bytes) = tx.flush(*prefixed) {
@@ -217,6 +244,8 @@ impl<Buff, T, Read, Write> Handshake<Buff, T, Read, Write> {
                     prefixed,
                     tx,
                     rx,
+                    scratch,
+                    defrag,
                     handle,
                     read,
                     write,
@@ -228,6 +257,8 @@ impl<Buff, T, Read, Write> Handshake<Buff, T, Read, Write> {
                         prefixed,
                         tx,
                         rx,
+                        scratch,
+                        defrag,
                         handle,
                         read,
                         write,
@@ -246,14 +277,23 @@ impl<Buff, T, Read, Write> Handshake<Buff, T, Read, Write> {
                 handle,
                 read,
                 write,
+                ..
             } => {
                 if let Some(description) = state.description() {
-                    if let Self::PendingRecv { tx, rx, .. } = core::mem::replace(self, Self::Opened)
+                    if let Self::PendingRecv {
+                        tx,
+                        rx,
+                        scratch,
+                        defrag,
+                        ..
+                    } = core::mem::replace(self, Self::Opened)
                     {
                         *self = Self::Ready {
                             description,
                             tx,
                             rx,
+                            scratch,
+                            defrag,
                         };
 
                         return Ok(Some(HandshakeReady { handshake: self }));
@@ -265,12 +305,10 @@ impl<Buff, T, Read, Write> Handshake<Buff, T, Read, Write> {
                 rx.decode_with_async(async |bytes| read(handle, bytes).await, *prefixed)
                     .await?;
 
-                let resp = rx
-                    .flush_transport()
-                    .map(|msg| state.poll(msg))
-                    .filter_map(|response| response.0);
-                for resp in resp {
-                    tx.transport(resp);
+                for msg in rx.flush_transport() {
+                    if let (Some(resp), _) = state.poll(msg).map_err(EitherError::A)? {
+                        tx.transport(resp);
+                    }
                 }
 
                 if let Some(bytes) = tx.flush(*prefixed) {
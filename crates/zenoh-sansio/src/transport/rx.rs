@@ -2,10 +2,16 @@ use core::fmt::Display;
 use core::time::Duration;
 
 use zenoh_proto::{
-    EitherError, TransportError, ZBodyDecode, ZReadable, fields::Resolution, msgs::*,
+    EitherError, TransportError, ZBodyDecode, ZReadable,
+    exts::QoS,
+    fields::{Reliability, Resolution},
+    msgs::*,
 };
 
-use crate::{ZTransportRx, transport::TransportTx};
+use crate::{
+    ZTransportRx,
+    transport::{TransportTx, compression, sn::SeqNums},
+};
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
 enum State {
@@ -22,13 +28,79 @@ pub struct TransportRx<Buff> {
     cursor: usize,
     batch_size: usize,
 
-    sn: u32,
-    resolution: Resolution,
+    sn: SeqNums,
     lease: Duration,
 
     state: State,
 
     ignore_invalid_sn: bool,
+    defrag: Defrag,
+    peer_close: Option<Close>,
+
+    /// Scratch buffer used to decompress the batches, when compression has been negotiated
+    scratch: Option<Buff>,
+    /// Buffer the fragmented messages are reassembled in, which bounds their size
+    reassembly: Option<Buff>,
+}
+
+/// Reassembly state of a fragmented network message.
+#[derive(Debug, Default)]
+struct Defrag {
+    len: usize,
+    next_sn: Option<u32>,
+    reliability: Reliability,
+    qos: QoS,
+    dropping: bool,
+}
+
+impl Defrag {
+    /// Appends the payload of `fragment` to `buff`. Returns the length of the reassembled
+    /// message once its last fragment has been received.
+    fn push(&mut self, fragment: &Fragment<'_>, next_sn: u32, buff: &mut [u8]) -> Option<usize> {
+        if let Some(sn) = self.next_sn
+            && (sn != fragment.sn
+                || self.reliability != fragment.reliability
+                || self.qos != fragment.qos)
+        {
+            zenoh_proto::debug!(
+                "Unexpected fragment with `SN` {}, expected {}. Dropping the message being reassembled",
+                fragment.sn,
+                sn
+            );
+
+            self.len = 0;
+            self.dropping = true;
+        }
+
+        self.next_sn = Some(next_sn);
+        self.reliability = fragment.reliability;
+        self.qos = fragment.qos;
+
+        if !self.dropping {
+            let len = self.len + fragment.payload.len();
+
+            if len > buff.len() {
+                zenoh_proto::error!(
+                    "Fragmented message does not fit in the {} bytes reassembly buffer. Dropping it",
+                    buff.len()
+                );
+
+                self.dropping = true;
+            } else {
+                buff[self.len..len].copy_from_slice(fragment.payload);
+                self.len = len;
+            }
+        }
+
+        if fragment.more == FragmentMore::More {
+            return None;
+        }
+
+        let complete = (!self.dropping).then_some(self.len);
+        *self = Self::default();
+
+        complete
+    }
 }
 
 impl<Buff> TransportRx<Buff> {
@@ -39,6 +111,7 @@ impl<Buff> TransportRx<Buff> {
         sn: u32,
         resolution: Resolution,
         lease: Duration,
+        qos: bool,
     ) -> Self {
         Self {
             buff,
@@ -46,20 +119,79 @@ impl<Buff> TransportRx<Buff> {
             cursor: 0,
             batch_size,
 
-            sn,
-            resolution,
+            sn: SeqNums::new(sn, qos, resolution),
             lease,
 
             state: State::Opened,
             ignore_invalid_sn: false,
+            defrag: Defrag::default(),
+            peer_close: None,
+            scratch: None,
+            reassembly: None,
         }
     }
 
+    /// Decompresses the batches with `scratch`. Each batch then starts with a header byte.
+    pub(crate) fn with_compression(mut self, scratch: Option<Buff>) -> Self {
+        self.scratch = scratch;
+        self
+    }
+
+    /// Reassembles the fragmented messages in `reassembly`. Without it, they are dropped.
+    pub(crate) fn with_defrag(mut self, reassembly: Option<Buff>) -> Self {
+        self.reassembly = reassembly;
+        self
+    }
+
     pub(crate) fn into_inner(self) -> Buff {
         self.buff
     }
 
-    pub(crate) fn flush_transport(&mut self) -> impl Iterator<Item = (TransportMessage<'_>, &[u8])>
+    /// Strips the header of the batch of length `size`, decompressing it in place if needed.
+    /// Returns the range of the messages in the buffer.
+    fn decompress(&mut self, size: usize, max: usize) -> (usize, usize)
+    where
+        Buff: AsMut<[u8]> + AsRef<[u8]>,
+    {
+        let Some(scratch) = self.scratch.as_mut() else {
+            return (0, size);
+        };
+
+        let buff = self.buff.as_mut();
+        if size == 0 {
+            return (0, 0);
+        }
+
+        if buff[0] & compression::COMPRESSED == 0 {
+            return (1, size);
+        }
+
+        match compression::decompress(&buff[1..size], scratch.as_mut()).filter(|len| *len <= max) {
+            Some(len) => {
+                buff[..len].copy_from_slice(&scratch.as_ref()[..len]);
+                (0, len)
+            }
+            None => {
+                zenoh_proto::error!(
+                    "Could not decompress a batch of {} bytes. Dropping it",
+                    size
+                );
+                (0, 0)
+            }
+        }
+    }
+
+    pub(crate) fn flush_transport(&mut self) -> impl Iterator<Item = TransportMessage<'_>>
+    where
+        Buff: AsMut<[u8]> + AsRef<[u8]>,
+    {
+        self.messages().filter_map(|m| match m.0 {
+            Message::Transport(msg) => Some(msg),
+            _ => None,
+        })
+    }
+
+    fn messages(&mut self) -> impl Iterator<Item = (Message<'_>, &[u8])>
     where
         Buff: AsMut<[u8]> + AsRef<[u8]>,
     {
@@ -68,18 +200,31 @@ impl<Buff> TransportRx<Buff> {
             core::cmp::min(self.batch_size, self.cursor),
         );
         self.cursor = 0;
-        let mut reader = &self.buff.as_ref()[..size];
+        let max = core::cmp::min(self.buff.as_ref().len(), self.batch_size);
+        let (start, size) = self.decompress(size, max);
+        let mut reader = &self.buff.as_ref()[start..size];
+        let mut frag = self
+            .reassembly
+            .as_mut()
+            .map(|reassembly| reassembly.as_mut());
         let mut last_frame = None;
         let sn = &mut self.sn;
-        let resolution = self.resolution;
         let ignore = self.ignore_invalid_sn;
+        let defrag = &mut self.defrag;
+        let (state, peer_close) = (&mut self.state, &mut self.peer_close);
 
         core::iter::from_fn(move || {
-            Self::decode(&mut reader, &mut last_frame, sn, resolution, ignore)
-        })
-        .filter_map(|m| match m.0 {
-            Message::Transport(msg) => Some((msg, m.1)),
-            _ => None,
+            let msg = Self::decode(&mut reader, &mut last_frame, sn, ignore, defrag, &mut frag)?;
+
+            if let Message::Transport(TransportMessage::Close(close)) = &msg.0 {
+                *state = State::Closed;
+                *peer_close = Some(Close {
+                    reason: close.reason,
+                    behaviour: close.behaviour,
+                });
+            }
+
+            Some(msg)
         })
     }
 
@@ -122,12 +267,52 @@ impl<Buff> TransportRx<Buff> {
         matches!(self.state, State::Closed)
     }
 
-    pub(crate) fn decode<'a>(
+    /// The `Close` sent by the peer, if it closed the transport.
+    pub fn peer_close(&self) -> Option<&Close> {
+        self.peer_close.as_ref()
+    }
+
+    /// The number of messages missed, according to the sequence numbers, since the last call.
+    pub fn take_missed(&mut self) -> u32 {
+        self.sn.take_missed()
+    }
+
+    fn check_sn(
+        sn: &mut SeqNums,
+        reliability: Reliability,
+        qos: &QoS,
+        header: u32,
+        ignore: bool,
+    ) -> bool {
+        if ignore {
+            sn.resync(reliability, qos, header);
+            return true;
+        }
+
+        match sn.receive(reliability, qos, header) {
+            Some(0) => true,
+            Some(missed) => {
+                zenoh_proto::debug!("Transport missed {} messages", missed);
+                true
+            }
+            None => {
+                zenoh_proto::error!(
+                    "Duplicate or out of order `SN` value {}, expected {}",
+                    header,
+                    sn.get(reliability, qos)
+                );
+                false
+            }
+        }
+    }
+
+    fn decode<'a>(
         reader: &mut &'a [u8],
         last_frame: &mut Option<FrameHeader>,
-        sn: &mut u32,
-        resolution: Resolution,
+        sn: &mut SeqNums,
         ignore: bool,
+        defrag: &mut Defrag,
+        frag: &mut Option<&'a mut [u8]>,
     ) -> Option<(Message<'a>, &'a [u8])>
     where
         Buff: AsRef<[u8]>,
@@ -182,27 +367,50 @@ impl<Buff> TransportRx<Buff> {
             FrameHeader::ID => {
                 let header = decode!(FrameHeader);
 
-                *sn = sn.wrapping_add(1);
-
-                if !ignore {
-                    // Check for missed messages regarding resolution
-                    let _ = resolution;
-
-                    if header.sn < *sn - 1 {
-                        zenoh_proto::error!(
-                            "Inconsistent `SN` value {}, expected higher than {}",
-                            header.sn,
-                            *sn - 1
-                        );
-                        return None;
-                    } else if header.sn != *sn - 1 {
-                        zenoh_proto::debug!("Transport missed {} messages", header.sn - *sn + 1);
-                    }
+                if !Self::check_sn(sn, header.reliability, &header.qos, header.sn, ignore) {
+                    return None;
                 }
 
                 last_frame.replace(header);
 
-                return Self::decode(reader, last_frame, sn, resolution, ignore);
+                return Self::decode(reader, last_frame, sn, ignore, defrag, frag);
+            }
+            Fragment::ID => {
+                last_frame.take();
+                let fragment = decode!(Fragment);
+
+                if !Self::check_sn(sn, fragment.reliability, &fragment.qos, fragment.sn, ignore) {
+                    return None;
+                }
+
+                // A `Fragment` spans the rest of the batch, so the reassembly buffer is only
+                // needed once per batch and the message can borrow it for as long as the batch.
+                let buff = frag.take().unwrap_or(&mut []);
+                let Some(len) = defrag.push(&fragment, sn.increment(fragment.sn), &mut *buff)
+                else {
+                    return Self::decode(reader, last_frame, sn, ignore, defrag, frag);
+                };
+
+                let buff: &'a [u8] = buff;
+                let data = &buff[..len];
+
+                let mut inner_sn = *sn;
+                let mut frame = Some(FrameHeader {
+                    reliability: fragment.reliability,
+                    sn: fragment.sn,
+                    qos: fragment.qos,
+                });
+
+                return Self::decode(
+                    &mut &data[..],
+                    &mut frame,
+                    &mut inner_sn,
+                    true,
+                    &mut Defrag::default(),
+                    &mut None,
+                )
+                .filter(|(msg, _)| matches!(msg, Message::Network(_)))
+                .map(|(msg, _)| (msg, data));
             }
             InitAck::ID if ack => decode!(@Transport InitAck),
             InitSyn::ID => decode!(@Transport InitSyn),
@@ -429,21 +637,7 @@ where
     where
         Buff: AsMut<[u8]> + AsRef<[u8]>,
     {
-        let size = core::cmp::min(
-            self.buff.as_ref().len(),
-            core::cmp::min(self.batch_size, self.cursor),
-        );
-        self.clear();
-        let mut reader = &self.buff.as_ref()[..size];
-        let mut last_frame = None;
-        let sn = &mut self.sn;
-        let resolution = self.resolution;
-        let ignore = self.ignore_invalid_sn;
-
-        core::iter::from_fn(move || {
-            Self::decode(&mut reader, &mut last_frame, sn, resolution, ignore)
-        })
-        .filter_map(|m| match m.0 {
+        self.messages().filter_map(|m| match m.0 {
             Message::Network(msg) => Some((msg, m.1)),
             _ => None,
         })
@@ -0,0 +1,80 @@
+use zenoh_proto::{
+    exts::QoS,
+    fields::{Field, Priority, Reliability, Resolution},
+};
+
+/// Sequence numbers of one direction of a transport. Reliable and best effort frames have their
+/// own counters. When QoS has been negotiated each priority has its own counters too, otherwise
+/// all the priorities share the first ones.
+///
+/// Sequence numbers wrap around the negotiated `FrameSN` resolution.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct SeqNums {
+    sns: [[u32; Priority::NUM]; 2],
+    qos: bool,
+    mask: u32,
+    /// Sequence numbers skipped by `receive` since the last `take_missed`
+    missed: u32,
+}
+
+impl SeqNums {
+    pub(crate) fn new(sn: u32, qos: bool, resolution: Resolution) -> Self {
+        let mask = resolution.get(Field::FrameSN).mask() as u32;
+
+        Self {
+            sns: [[sn & mask; Priority::NUM]; 2],
+            qos,
+            mask,
+            missed: 0,
+        }
+    }
+
+    fn sn(&mut self, reliability: Reliability, qos: &QoS) -> &mut u32 {
+        let priority = if self.qos { qos.priority() as usize } else { 0 };
+        &mut self.sns[reliability as usize][priority]
+    }
+
+    pub(crate) fn get(&self, reliability: Reliability, qos: &QoS) -> u32 {
+        let priority = if self.qos { qos.priority() as usize } else { 0 };
+        self.sns[reliability as usize][priority]
+    }
+
+    pub(crate) fn increment(&self, sn: u32) -> u32 {
+        sn.wrapping_add(1) & self.mask
+    }
+
+    pub(crate) fn advance(&mut self, reliability: Reliability, qos: &QoS) {
+        let next = self.increment(self.get(reliability, qos));
+        *self.sn(reliability, qos) = next;
+    }
+
+    /// Distance from `from` to `to`, going forward.
+    fn gap(&self, from: u32, to: u32) -> u32 {
+        to.wrapping_sub(from) & self.mask
+    }
+
+    /// Checks `sn` against the next expected one on the channel of `reliability` and `qos` and
+    /// expects `sn + 1` from now on. Returns the number of missed sequence numbers, or `None`
+    /// (and leaves the state untouched) if `sn` is a duplicate or arrived out of order, i.e. if
+    /// it is behind the expected one by less than half of the resolution.
+    pub(crate) fn receive(&mut self, reliability: Reliability, qos: &QoS, sn: u32) -> Option<u32> {
+        let gap = self.gap(self.get(reliability, qos), sn);
+
+        if gap > self.mask >> 1 {
+            return None;
+        }
+
+        *self.sn(reliability, qos) = self.increment(sn);
+        self.missed = self.missed.saturating_add(gap);
+        Some(gap)
+    }
+
+    pub(crate) fn take_missed(&mut self) -> u32 {
+        core::mem::take(&mut self.missed)
+    }
+
+    /// Expects `sn + 1` on the channel of `reliability` and `qos`, whatever was expected before.
+    pub(crate) fn resync(&mut self, reliability: Reliability, qos: &QoS, sn: u32) {
+        *self.sn(reliability, qos) = self.increment(sn);
+    }
+}
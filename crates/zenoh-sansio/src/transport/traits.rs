@@ -90,7 +90,8 @@ pub trait ZTransportTx {
     fn init_ack(&mut self, ack: &InitAck);
     fn open_syn(&mut self, syn: &OpenSyn);
     fn open_ack(&mut self, ack: &OpenAck);
-    fn close(&mut self);
+    /// Encodes a `Close` of the whole session with `reason`. Nothing is sent afterwards.
+    fn close(&mut self, reason: u8);
 
     fn transport(&mut self, msg: TransportMessage);
     fn transport_ref(&mut self, msg: TransportMessageRef);
@@ -103,6 +104,46 @@ pub trait ZTransportTx {
         msgs: impl Iterator<Item = (NetworkMessageRef<'a>, &'a [u8])>,
     );
 
+    fn encode_with<'a, E>(
+        &mut self,
+        msgs: impl Iterator<Item = NetworkMessage<'a>>,
+        prefixed: bool,
+        write: impl FnMut(&[u8]) -> core::result::Result<(), E>,
+    ) -> core::result::Result<(), EitherError<TransportError, E>>
+    where
+        E: Display;
+
+    fn encode_with_async<'a, E>(
+        &mut self,
+        msgs: impl Iterator<Item = NetworkMessage<'a>>,
+        prefixed: bool,
+        write: impl AsyncFnMut(&[u8]) -> core::result::Result<(), E>,
+    ) -> impl Future<Output = core::result::Result<(), EitherError<TransportError, E>>>
+    where
+        E: Display;
+
+    /// Same as `encode_with`, but leaves the last batch pending, unless the last message is
+    /// express, so that the next messages can be added to it. It is sent by the next `flush`.
+    fn batch_with<'a, E>(
+        &mut self,
+        msgs: impl Iterator<Item = NetworkMessage<'a>>,
+        prefixed: bool,
+        write: impl FnMut(&[u8]) -> core::result::Result<(), E>,
+    ) -> core::result::Result<(), EitherError<TransportError, E>>
+    where
+        E: Display;
+
+    /// Same as `encode_with_async`, but leaves the last batch pending, unless the last message
+    /// is express. It is sent by the next `flush`.
+    fn batch_with_async<'a, E>(
+        &mut self,
+        msgs: impl Iterator<Item = NetworkMessage<'a>>,
+        prefixed: bool,
+        write: impl AsyncFnMut(&[u8]) -> core::result::Result<(), E>,
+    ) -> impl Future<Output = core::result::Result<(), EitherError<TransportError, E>>>
+    where
+        E: Display;
+
     fn flush_prefixed(&mut self) -> Option<&'_ [u8]>;
     fn flush_raw(&mut self) -> Option<&'_ [u8]>;
 
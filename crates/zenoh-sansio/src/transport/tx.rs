@@ -1,15 +1,20 @@
+use core::fmt::Display;
 use core::time::Duration;
 
 use zenoh_proto::{
-    TransportError, ZEncode, ZWriteable,
+    BytesError, EitherError, TransportError, ZEncode, ZLen, ZWriteable,
+    exts::QoS,
     fields::Resolution,
     msgs::{
-        Close, FrameHeader, KeepAlive, MessageRef, NetworkMessage, NetworkMessageRef,
-        TransportMessage, TransportMessageRef,
+        Close, CloseBehaviour, Fragment, FragmentMore, FrameHeader, KeepAlive, MessageRef,
+        NetworkMessage, NetworkMessageRef, TransportMessage, TransportMessageRef,
     },
 };
 
-use crate::{ZTransportTx, transport::TransportRx};
+use crate::{
+    ZTransportTx,
+    transport::{TransportRx, compression, sn::SeqNums},
+};
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
 enum State {
@@ -25,12 +30,16 @@ pub struct TransportTx<Buff> {
     cursor: usize,
     batch_size: usize,
 
-    sn: u32,
-    resolution: Resolution,
+    sn: SeqNums,
+    /// Whether QoS has been negotiated, otherwise the frames carry the default QoS
+    qos: bool,
     last_frame: Option<FrameHeader>,
     lease: Duration,
 
     state: State,
+
+    /// Scratch buffer used to compress the batches, when compression has been negotiated
+    scratch: Option<Buff>,
 }
 
 impl<Buff> TransportTx<Buff> {
@@ -41,23 +50,37 @@ impl<Buff> TransportTx<Buff> {
         sn: u32,
         resolution: Resolution,
         lease: Duration,
+        qos: bool,
     ) -> Self {
         Self {
             buff,
             cursor: 2,
             batch_size,
-            sn,
-            resolution,
+            sn: SeqNums::new(sn, qos, resolution),
+            qos,
             last_frame: None,
             lease,
             state: State::Opened,
+            scratch: None,
         }
     }
 
+    /// Compresses the batches with `scratch`. Each batch then starts with a header byte.
+    pub(crate) fn with_compression(mut self, scratch: Option<Buff>) -> Self {
+        self.scratch = scratch;
+        self.cursor = self.start();
+        self
+    }
+
     pub(crate) fn into_inner(self) -> Buff {
         self.buff
     }
 
+    /// Offset of the first message in the batch, after the length and the batch header.
+    fn start(&self) -> usize {
+        if self.scratch.is_some() { 3 } else { 2 }
+    }
+
     pub fn sync(&mut self, rx: Option<&TransportRx<Buff>>, now: Duration) {
         if let Some(rx) = rx
             && rx.closed()
@@ -115,33 +138,40 @@ impl<Buff> TransportTx<Buff> {
         match msg {
             MessageRef::Network(msg) => {
                 let r = msg.reliability;
-                let q = msg.qos;
+                let q = if self.qos { msg.qos } else { QoS::default() };
 
                 let header = if reliability != Some(&r) || qos != Some(&q) {
-                    let header = FrameHeader {
+                    Some(FrameHeader {
                         reliability: r,
-                        sn: self.sn,
+                        sn: self.sn.get(r, &q),
                         qos: q,
-                    };
-
-                    header.z_encode(&mut buff).ok()?;
-
-                    // TODO: wrap with resolution
-                    let _ = self.resolution;
-                    self.sn = self.sn.wrapping_add(1);
-
-                    Some(header)
+                    })
                 } else {
                     None
                 };
 
+                let len = match bytes {
+                    Some(bytes) => bytes.len(),
+                    None => msg.body.z_len(),
+                };
+
+                if header.as_ref().map_or(0, |h| h.z_len()) + len > buff.len() {
+                    return None;
+                }
+
+                if let Some(header) = &header {
+                    header.z_encode(&mut buff).ok()?;
+                }
+
                 if let Some(bytes) = bytes {
                     buff.write_exact(bytes).ok()?;
                 } else {
                     msg.body.z_encode(&mut buff).ok()?;
                 }
 
+                // The frame only takes its sequence number once the message made it in.
                 if let Some(header) = header {
+                    self.sn.advance(r, &q);
                     self.last_frame = Some(header);
                 }
             }
@@ -159,6 +189,169 @@ impl<Buff> TransportTx<Buff> {
         self.cursor += start - buff.len();
         Some(start - buff.len())
     }
+
+    /// Encodes the part of `msg` starting at `offset` as a `Fragment` filling the rest of the
+    /// current batch. Returns the offset of the next fragment, or `None` if the whole message has
+    /// already been encoded or if there is no room left in the batch.
+    pub(crate) fn fragment(
+        &mut self,
+        msg: &NetworkMessageRef<'_>,
+        bytes: Option<&[u8]>,
+        offset: usize,
+    ) -> Option<usize>
+    where
+        Buff: AsMut<[u8]> + AsRef<[u8]>,
+    {
+        let total = match bytes {
+            Some(bytes) => bytes.len(),
+            None => msg.body.z_len(),
+        };
+
+        if offset >= total {
+            return None;
+        }
+
+        let max = core::cmp::min(self.buff.as_ref().len(), self.batch_size);
+        let mut buff = &mut self.buff.as_mut()[self.cursor..max];
+
+        let start = buff.len();
+
+        let qos = if self.qos { msg.qos } else { QoS::default() };
+        let mut header = Fragment {
+            reliability: msg.reliability,
+            more: FragmentMore::More,
+            sn: self.sn.get(msg.reliability, &qos),
+            qos,
+            payload: &[],
+        };
+
+        let room = start.checked_sub(header.z_len()).filter(|room| *room > 0)?;
+        let len = core::cmp::min(room, total - offset);
+
+        if offset + len == total {
+            header.more = FragmentMore::Last;
+        }
+
+        header.z_encode(&mut buff).ok()?;
+
+        let (chunk, _) = core::mem::take(&mut buff).split_at_mut(len);
+        match bytes {
+            Some(bytes) => chunk.copy_from_slice(&bytes[offset..offset + len]),
+            None => msg
+                .body
+                .z_encode(&mut Window {
+                    dst: chunk,
+                    skip: offset,
+                })
+                .ok()?,
+        }
+
+        self.sn.advance(msg.reliability, &qos);
+        self.last_frame.take();
+
+        self.cursor += header.z_len() + len;
+        Some(offset + len)
+    }
+
+    /// Whether no message is pending in the current batch.
+    pub fn is_empty(&self) -> bool {
+        self.cursor <= self.start()
+    }
+
+    /// Adds `msg` to the pending batch if it has room for it, without sending anything.
+    /// Returns `false`, leaving the batch untouched, otherwise.
+    pub fn try_batch(&mut self, msg: NetworkMessageRef<'_>) -> bool
+    where
+        Buff: AsMut<[u8]> + AsRef<[u8]>,
+    {
+        let batched = self.encode(MessageRef::Network(msg), None).is_some();
+        if batched {
+            self.state = State::Used;
+        }
+
+        batched
+    }
+
+    /// Writes the batch header and compresses the batch of length `size` when it makes it
+    /// smaller. Returns the new length of the batch.
+    fn compress(&mut self, size: usize) -> usize
+    where
+        Buff: AsMut<[u8]> + AsRef<[u8]>,
+    {
+        let Some(scratch) = self.scratch.as_mut() else {
+            return size;
+        };
+
+        let buff = self.buff.as_mut();
+        if size < 3 {
+            return size;
+        }
+
+        match compression::compress(&buff[3..size], scratch.as_mut()).filter(|len| *len < size - 3)
+        {
+            Some(len) => {
+                buff[3..3 + len].copy_from_slice(&scratch.as_ref()[..len]);
+                buff[2] = compression::COMPRESSED;
+                3 + len
+            }
+            None => {
+                buff[2] = 0;
+                size
+            }
+        }
+    }
+}
+
+/// A writer that only keeps the bytes of the encoded stream that fall in
+/// `[skip, skip + dst.len())` and silently discards all the others.
+struct Window<'a> {
+    dst: &'a mut [u8],
+    skip: usize,
+}
+
+impl Window<'_> {
+    const SLOT_MAX: usize = 16;
+}
+
+impl ZWriteable for Window<'_> {
+    fn remaining(&self) -> usize {
+        usize::MAX
+    }
+
+    fn write(&mut self, src: &'_ [u8]) -> core::result::Result<usize, BytesError> {
+        let skipped = core::cmp::min(self.skip, src.len());
+        self.skip -= skipped;
+
+        let kept = &src[skipped..];
+        let len = core::cmp::min(kept.len(), self.dst.len());
+        let (head, tail) = core::mem::take(&mut self.dst).split_at_mut(len);
+        head.copy_from_slice(&kept[..len]);
+        self.dst = tail;
+
+        Ok(src.len())
+    }
+
+    fn write_u8(&mut self, value: u8) -> core::result::Result<(), BytesError> {
+        self.write(&[value]).map(|_| ())
+    }
+
+    fn write_slot(
+        &mut self,
+        len: usize,
+        writer: impl FnOnce(&mut [u8]) -> usize,
+    ) -> core::result::Result<usize, BytesError> {
+        let mut slot = [0u8; Self::SLOT_MAX];
+        if len > slot.len() {
+            zenoh_proto::zbail!(BytesError::DstIsTooSmall);
+        }
+
+        let written = writer(&mut slot[..len]);
+        if written > len {
+            zenoh_proto::zbail!(BytesError::DstIsTooSmall);
+        }
+
+        self.write(&slot[..written])
+    }
 }
 
 impl<Buff> ZTransportTx for TransportTx<Buff>
@@ -185,8 +378,13 @@ where
         self.transport_ref(TransportMessageRef::OpenAck(ack));
     }
 
-    fn close(&mut self) {
-        self.transport(TransportMessage::Close(Close::default()));
+    fn close(&mut self, reason: u8) {
+        self.transport(TransportMessage::Close(Close {
+            reason,
+            behaviour: CloseBehaviour::Session,
+        }));
+
+        self.state = State::Closed;
     }
 
     fn transport(&mut self, msg: TransportMessage) {
@@ -254,6 +452,160 @@ where
         }
     }
 
+    fn encode_with<'a, E>(
+        &mut self,
+        msgs: impl Iterator<Item = NetworkMessage<'a>>,
+        prefixed: bool,
+        mut write: impl FnMut(&[u8]) -> core::result::Result<(), E>,
+    ) -> core::result::Result<(), EitherError<TransportError, E>>
+    where
+        E: Display,
+    {
+        self.batch_with(msgs, prefixed, &mut write)?;
+
+        if !self.is_empty()
+            && let Some(bytes) = self.flush(prefixed)
+        {
+            write(bytes).map_err(EitherError::B)?;
+        }
+
+        Ok(())
+    }
+
+    fn batch_with<'a, E>(
+        &mut self,
+        msgs: impl Iterator<Item = NetworkMessage<'a>>,
+        prefixed: bool,
+        mut write: impl FnMut(&[u8]) -> core::result::Result<(), E>,
+    ) -> core::result::Result<(), EitherError<TransportError, E>>
+    where
+        E: Display,
+    {
+        for msg in msgs {
+            let msg = msg.as_ref();
+
+            let encoded = self
+                .encode(MessageRef::Network(msg.clone()), None)
+                .is_some()
+                || {
+                    if !self.is_empty()
+                        && let Some(bytes) = self.flush(prefixed)
+                    {
+                        write(bytes).map_err(EitherError::B)?;
+                    }
+
+                    self.encode(MessageRef::Network(msg.clone()), None)
+                        .is_some()
+                };
+
+            if encoded {
+                self.state = State::Used;
+
+                // Express messages are not batched with the following ones.
+                if msg.qos.is_express()
+                    && let Some(bytes) = self.flush(prefixed)
+                {
+                    write(bytes).map_err(EitherError::B)?;
+                }
+
+                continue;
+            }
+
+            let mut offset = 0;
+            while let Some(next) = self.fragment(&msg, None, offset) {
+                self.state = State::Used;
+                offset = next;
+
+                if let Some(bytes) = self.flush(prefixed) {
+                    write(bytes).map_err(EitherError::B)?;
+                }
+            }
+
+            if offset == 0 {
+                zenoh_proto::zbail!(@log TransportError::TransportTxFull);
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn encode_with_async<'a, E>(
+        &mut self,
+        msgs: impl Iterator<Item = NetworkMessage<'a>>,
+        prefixed: bool,
+        mut write: impl AsyncFnMut(&[u8]) -> core::result::Result<(), E>,
+    ) -> core::result::Result<(), EitherError<TransportError, E>>
+    where
+        E: Display,
+    {
+        self.batch_with_async(msgs, prefixed, &mut write).await?;
+
+        if !self.is_empty()
+            && let Some(bytes) = self.flush(prefixed)
+        {
+            write(bytes).await.map_err(EitherError::B)?;
+        }
+
+        Ok(())
+    }
+
+    async fn batch_with_async<'a, E>(
+        &mut self,
+        msgs: impl Iterator<Item = NetworkMessage<'a>>,
+        prefixed: bool,
+        mut write: impl AsyncFnMut(&[u8]) -> core::result::Result<(), E>,
+    ) -> core::result::Result<(), EitherError<TransportError, E>>
+    where
+        E: Display,
+    {
+        for msg in msgs {
+            let msg = msg.as_ref();
+
+            let encoded = self
+                .encode(MessageRef::Network(msg.clone()), None)
+                .is_some()
+                || {
+                    if !self.is_empty()
+                        && let Some(bytes) = self.flush(prefixed)
+                    {
+                        write(bytes).await.map_err(EitherError::B)?;
+                    }
+
+                    self.encode(MessageRef::Network(msg.clone()), None)
+                        .is_some()
+                };
+
+            if encoded {
+                self.state = State::Used;
+
+                // Express messages are not batched with the following ones.
+                if msg.qos.is_express()
+                    && let Some(bytes) = self.flush(prefixed)
+                {
+                    write(bytes).await.map_err(EitherError::B)?;
+                }
+
+                continue;
+            }
+
+            let mut offset = 0;
+            while let Some(next) = self.fragment(&msg, None, offset) {
+                self.state = State::Used;
+                offset = next;
+
+                if let Some(bytes) = self.flush(prefixed) {
+                    write(bytes).await.map_err(EitherError::B)?;
+                }
+            }
+
+            if offset == 0 {
+                zenoh_proto::zbail!(@log TransportError::TransportTxFull);
+            }
+        }
+
+        Ok(())
+    }
+
     fn flush_prefixed(&mut self) -> Option<&'_ [u8]> {
         let size = core::cmp::min(
             self.buff.as_ref().len(),
@@ -264,6 +616,7 @@ where
             zenoh_proto::zbail!(@None TransportError::TransportTxFull);
         }
 
+        let size = self.compress(size);
         let len = ((size - 2) as u16).to_le_bytes();
         self.buff.as_mut()[..2].copy_from_slice(&len);
         self.clear();
@@ -278,6 +631,7 @@ where
             core::cmp::min(self.batch_size, self.cursor),
         );
 
+        let size = self.compress(size);
         self.clear();
 
         let buff_ref = &self.buff.as_ref()[2..size];
@@ -285,7 +639,7 @@ where
     }
 
     fn clear(&mut self) {
-        self.cursor = 2;
+        self.cursor = self.start();
         self.last_frame.take();
     }
 }
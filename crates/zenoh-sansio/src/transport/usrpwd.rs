@@ -0,0 +1,118 @@
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+use zenoh_proto::{
+    ZExt, decode_ext_header,
+    exts::{Auth, HasUsrPwd, UsrPwdOpenSyn},
+    skip_ext, zext_decode, zext_encode, zext_header,
+};
+
+/// Id of the `usrpwd` method inside the `Auth` extension.
+const ID: u8 = 0x2;
+
+const HMAC_LEN: usize = 32;
+const MAX_USER_LEN: usize = 64;
+const MAX_LEN: usize = 3 + MAX_USER_LEN + 1 + HMAC_LEN;
+
+pub(crate) const HAS_USRPWD: [u8; 1] = [zext_header::<ID, false, HasUsrPwd>(false)];
+
+/// User and password of the zenoh `usrpwd` authentication.
+#[derive(Copy, Clone, PartialEq)]
+pub struct UsrPwd {
+    pub user: &'static str,
+    pub password: &'static str,
+}
+
+impl UsrPwd {
+    pub const fn new(user: &'static str, password: &'static str) -> Self {
+        Self { user, password }
+    }
+}
+
+impl core::fmt::Debug for UsrPwd {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("UsrPwd")
+            .field("user", &self.user)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The encoded `usrpwd` part of an `Auth` extension, kept in the handshake state until the
+/// message that carries it has been sent.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(crate) struct UsrPwdExt {
+    bytes: [u8; MAX_LEN],
+    len: usize,
+}
+
+impl Default for UsrPwdExt {
+    fn default() -> Self {
+        Self {
+            bytes: [0; MAX_LEN],
+            len: 0,
+        }
+    }
+}
+
+impl UsrPwdExt {
+    pub(crate) fn new<'a, T: ZExt<'a>>(x: &T) -> Option<Self> {
+        let mut ext = Self::default();
+        let mut writer = &mut ext.bytes[..];
+        zext_encode::<_, ID, false>(x, &mut writer, false).ok()?;
+        ext.len = MAX_LEN - writer.len();
+
+        Some(ext)
+    }
+
+    pub(crate) fn auth(&self) -> Option<Auth<'_>> {
+        (self.len != 0).then(|| Auth {
+            payload: &self.bytes[..self.len],
+        })
+    }
+}
+
+/// Finds the `usrpwd` extension of kind `T` among the methods of an `Auth` extension.
+pub(crate) fn find<'a, T: ZExt<'a>>(auth: Option<&Auth<'a>>) -> Option<T> {
+    let mut reader = auth?.payload;
+    while !reader.is_empty() {
+        let (id, kind, _, _) = decode_ext_header(&mut reader).ok()?;
+        if id == ID && kind == T::KIND {
+            return zext_decode::<T>(&mut reader).ok();
+        }
+
+        skip_ext(&mut reader, kind).ok()?;
+    }
+
+    None
+}
+
+/// A fresh challenge, drawn from `rng`.
+pub(crate) fn nonce(rng: fn(&mut [u8])) -> u64 {
+    let mut bytes = [0u8; 8];
+    rng(&mut bytes);
+    u64::from_le_bytes(bytes)
+}
+
+/// The HMAC of `password`, keyed with the `nonce` challenge.
+pub(crate) fn hmac(nonce: u64, password: &str) -> [u8; HMAC_LEN] {
+    let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(&nonce.to_le_bytes())
+        .expect("HMAC accepts keys of any size");
+    mac.update(password.as_bytes());
+    mac.finalize().into_bytes().into()
+}
+
+/// Checks the response of the peer against the known credentials.
+pub(crate) fn check(dictionary: &[UsrPwd], nonce: u64, open: &UsrPwdOpenSyn<'_>) -> bool {
+    dictionary
+        .iter()
+        .find(|usrpwd| usrpwd.user.as_bytes() == open.user)
+        .is_some_and(|usrpwd| {
+            let expected = hmac(nonce, usrpwd.password);
+            expected.len() == open.hmac.len()
+                && expected
+                    .iter()
+                    .zip(open.hmac)
+                    .fold(0, |acc, (a, b)| acc | (a ^ b))
+                    == 0
+        })
+}
@@ -68,6 +68,7 @@ async fn entry(spawner: embassy_executor::Spawner) -> zenoh::ZResult<()> {
 
         let _ = query
             .reply(query.keyexpr(), b"Response from z_queryable")
+            .finish()
             .await;
 
         let _ = query.finalize().await;
@@ -124,6 +124,12 @@ impl ZSessionConfig for ExampleConfig {
     #[cfg(feature = "alloc")]
     type GetCallbacks<'res> = AllocGetCallbacks<'res, zenoh::storage::Box, zenoh::storage::Box>;
 
+    #[cfg(not(feature = "alloc"))]
+    type Consolidations = FixedCapacityConsolidations<4, 8, 128, 128>;
+
+    #[cfg(feature = "alloc")]
+    type Consolidations = AllocConsolidations;
+
     #[cfg(not(feature = "alloc"))]
     type QueryableCallbacks<'res> = FixedCapacityQueryableCallbacks<
         'res,
@@ -137,6 +143,60 @@ impl ZSessionConfig for ExampleConfig {
     type QueryableCallbacks<'res> =
         AllocQueryableCallbacks<'res, Self, zenoh::storage::Box, zenoh::storage::Box>;
 
+    #[cfg(not(feature = "alloc"))]
+    type LivelinessCallbacks<'res> = FixedCapacitySubCallbacks<
+        'res,
+        4,
+        zenoh::storage::RawOrBox<56>,
+        zenoh::storage::RawOrBox<600>,
+    >;
+
+    #[cfg(feature = "alloc")]
+    type LivelinessCallbacks<'res> =
+        AllocSubCallbacks<'res, zenoh::storage::Box, zenoh::storage::Box>;
+
+    #[cfg(not(feature = "alloc"))]
+    type KeyExprs<'res> = FixedCapacityKeyExprs<'res, 8>;
+
+    #[cfg(feature = "alloc")]
+    type KeyExprs<'res> = AllocKeyExprs<'res>;
+
+    #[cfg(not(feature = "alloc"))]
+    type Tokens<'res> = FixedCapacityKeyExprs<'res, 4>;
+
+    #[cfg(feature = "alloc")]
+    type Tokens<'res> = AllocKeyExprs<'res>;
+
+    #[cfg(not(feature = "alloc"))]
+    type Matchings<'res> = FixedCapacityMatchings<'res, 8, 128>;
+
+    #[cfg(feature = "alloc")]
+    type Matchings<'res> = AllocMatchings<'res>;
+
+    #[cfg(not(feature = "alloc"))]
+    type DroppedTokens = FixedCapacityPending<u16, 4>;
+
+    #[cfg(feature = "alloc")]
+    type DroppedTokens = AllocPending<u16>;
+
+    #[cfg(not(feature = "alloc"))]
+    type CancelledGets = FixedCapacityPending<(u32, bool), 8>;
+
+    #[cfg(feature = "alloc")]
+    type CancelledGets = AllocPending<(u32, bool)>;
+
+    #[cfg(not(feature = "alloc"))]
+    type RemoteKeyExprs = FixedCapacityRemoteKeyExprs<8, 128>;
+
+    #[cfg(feature = "alloc")]
+    type RemoteKeyExprs = AllocRemoteKeyExprs;
+
+    #[cfg(not(feature = "alloc"))]
+    type RemoteDeclarations = FixedCapacityRemoteDeclarations<16, 128>;
+
+    #[cfg(feature = "alloc")]
+    type RemoteDeclarations = AllocRemoteDeclarations;
+
     fn buff(&self) -> Self::Buff {
         #[cfg(not(feature = "alloc"))]
         {
@@ -158,7 +218,7 @@ pub async fn init_broker_example(spawner: &embassy_executor::Spawner) -> Example
     {
         let _ = spawner;
         ExampleConfig {
-            transports: TransportLinkManager::from(LinkManager),
+            transports: TransportLinkManager::from(LinkManager).with_defrag(true),
         }
     }
     #[cfg(feature = "wasm")]
@@ -227,7 +287,7 @@ pub async fn init_broker_example(spawner: &embassy_executor::Spawner) -> Example
         zenoh::info!("Network initialized with IP: {}", ip);
 
         ExampleConfig {
-            transports: TransportLinkManager::from(LinkManager::new(stack)),
+            transports: TransportLinkManager::from(LinkManager::new(stack)).with_defrag(true),
         }
     }
 }
@@ -237,14 +297,14 @@ pub async fn init_session_example(spawner: &embassy_executor::Spawner) -> Exampl
     {
         let _ = spawner;
         ExampleConfig {
-            transports: TransportLinkManager::from(LinkManager),
+            transports: TransportLinkManager::from(LinkManager).with_defrag(true),
         }
     }
     #[cfg(feature = "wasm")]
     {
         let _ = spawner;
         ExampleConfig {
-            transports: TransportLinkManager::from(LinkManager),
+            transports: TransportLinkManager::from(LinkManager).with_defrag(true),
         }
     }
     #[cfg(feature = "esp32s3")]
@@ -314,7 +374,7 @@ pub async fn init_session_example(spawner: &embassy_executor::Spawner) -> Exampl
         zenoh::info!("Network initialized with IP: {}", ip);
 
         ExampleConfig {
-            transports: TransportLinkManager::from(LinkManager::new(stack)),
+            transports: TransportLinkManager::from(LinkManager::new(stack)).with_defrag(true),
         }
     }
 }